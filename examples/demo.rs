@@ -5,8 +5,11 @@
 //!  - Dynamic room and device management.
 //!  - The `Report` trait through a generic helper function.
 //!  - Error handling when a room or device is not found.
+//!  - Simulating room temperatures with a thermal model.
 
-use smart_home::{room, Report, SmartDevice, SmartHome, Socket, Thermometer};
+use smart_home::simulation::{Simulation, ThermalEffect, ThermalModel};
+use smart_home::{room, Report, SmartDevice, SmartHome, SmartHomeError, Socket, Thermometer};
+use std::time::Duration;
 
 /// Prints a labelled report for any type that implements [`Report`].
 fn print_report<R: Report>(label: &str, item: &R) {
@@ -15,7 +18,7 @@ fn print_report<R: Report>(label: &str, item: &R) {
     print!("{}", item.report());
 }
 
-fn main() {
+fn main() -> Result<(), SmartHomeError> {
     // ── Build initial home with the `room!` macro ─────────────────────────
 
     let living_room = room!(
@@ -40,9 +43,9 @@ fn main() {
     );
 
     let mut home = SmartHome::new("My Smart Home");
-    home.add_room("living_room", living_room);
-    home.add_room("bedroom", bedroom);
-    home.add_room("kitchen", kitchen);
+    home.add_room("living_room", living_room)?;
+    home.add_room("bedroom", bedroom)?;
+    home.add_room("kitchen", kitchen)?;
 
     // ── Full home report via the Report trait ─────────────────────────────
 
@@ -53,7 +56,7 @@ fn main() {
     println!("\n=== Dynamic device manipulation ===");
 
     if let Some(bedroom) = home.get_room_mut("bedroom") {
-        bedroom.add_device("night_lamp", Socket::new("Night lamp", 10.0));
+        bedroom.add_device("night_lamp", Socket::new("Night lamp", 10.0))?;
         println!("Added 'night_lamp' to bedroom.");
 
         let removed = bedroom.remove_device("heater");
@@ -84,7 +87,7 @@ fn main() {
         "light"  => Socket::new("Bathroom light", 60.0),
         "sensor" => Thermometer::new("Humidity sensor", 25.0),
     );
-    home.add_room("bathroom", bathroom);
+    home.add_room("bathroom", bathroom)?;
     println!("Added 'bathroom'.");
 
     if home.remove_room("kitchen").is_some() {
@@ -120,7 +123,53 @@ fn main() {
         Err(e) => println!("Expected error (device): {}", e),
     }
 
+    match home.add_room("bedroom", room!("Another bedroom")) {
+        Ok(()) => println!("Unexpected success"),
+        Err(e) => println!("Expected error (duplicate room): {}", e),
+    }
+
+    match home.get_socket_mut("living_room", "sensor") {
+        Ok(_) => println!("Unexpected success"),
+        Err(e) => println!("Expected error (wrong kind): {}", e),
+    }
+
+    // ── Thermal simulation ────────────────────────────────────────────────
+
+    println!("\n=== Thermal simulation (outside: 30.0°C) ===");
+
+    let mut sim = Simulation::try_new(30.0)?;
+    // A large, sunny living room that loses (and gains) heat quickly.
+    sim.add_room(
+        "living_room",
+        ThermalModel::try_new(3_000_000.0, 300.0)?,
+        22.5,
+    )?;
+    sim.tag_socket(
+        "living_room",
+        "ac",
+        ThermalEffect::Cooler { efficiency: 2.5 },
+    );
+
+    for hour in 1..=4 {
+        // Run the air conditioner for the first two hours only.
+        let ac = home.get_socket_mut("living_room", "ac")?;
+        if hour <= 2 {
+            ac.turn_on();
+        } else {
+            ac.turn_off();
+        }
+        sim.step(&mut home, Duration::from_secs(3600))?;
+        println!(
+            "After {} h: living room {:.1}°C, energy used {:.2} kWh",
+            hour,
+            home.get_thermometer("living_room", "sensor")?.temperature(),
+            sim.energy_kwh()
+        );
+    }
+
     // ── Final home report ─────────────────────────────────────────────────
 
     print_report("FINAL HOME STATE", &home);
+
+    Ok(())
 }
@@ -0,0 +1,627 @@
+//! Users, roles and permission checks.
+//!
+//! A [`User`] holds [`Grant`]s, each giving a [`Role`] over a [`Scope`] (the
+//! whole home, one room or one device), optionally limited to one home by
+//! name.  [`AuthorizedHome`] wraps a [`SmartHome`] for one user and checks
+//! every operation before performing it, and can record the changes in an
+//! [`AuditLog`] under the user's name.
+//!
+//! | Role        | View | Operate | Configure | Manage rooms |
+//! |-------------|------|---------|-----------|--------------|
+//! | `owner`     | yes  | yes     | yes       | yes          |
+//! | `resident`  | yes  | yes     | yes       |              |
+//! | `installer` | yes  |         | yes       |              |
+//! | `guest`     | yes  | yes     |           |              |
+
+use crate::audit::AuditLog;
+use crate::clock::{Clock, SystemClock};
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::room::Room;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Something a user may be allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Read rooms and devices.
+    View,
+    /// Switch sockets.
+    Operate,
+    /// Add and remove devices and set thermometer readings.
+    Configure,
+    /// Add and remove rooms and arrange them on floors and in zones.
+    ManageRooms,
+}
+
+impl Permission {
+    /// Returns the lowercase name used in messages, e.g. `"manage_rooms"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Permission::View => "view",
+            Permission::Operate => "operate",
+            Permission::Configure => "configure",
+            Permission::ManageRooms => "manage_rooms",
+        }
+    }
+}
+
+/// A set of permissions given to users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Everything.
+    Owner,
+    /// Day-to-day use and device setup, but not the room layout.
+    Resident,
+    /// Device setup without operating them.
+    Installer,
+    /// Viewing and switching.
+    Guest,
+}
+
+impl Role {
+    /// Returns `true` if the role includes `permission`.
+    pub fn allows(self, permission: Permission) -> bool {
+        use Permission::*;
+        match self {
+            Role::Owner => true,
+            Role::Resident => matches!(permission, View | Operate | Configure),
+            Role::Installer => matches!(permission, View | Configure),
+            Role::Guest => matches!(permission, View | Operate),
+        }
+    }
+}
+
+/// The part of a home a grant covers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// The whole home, including adding rooms.
+    Home,
+    /// One room and its devices.
+    Room(String),
+    /// One device.
+    Device {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+    },
+}
+
+impl Scope {
+    /// Returns `true` if the scope covers `room` (or the home itself when
+    /// `None`) and `device` within it.
+    pub fn covers(&self, room: Option<&str>, device: Option<&str>) -> bool {
+        match self {
+            Scope::Home => true,
+            Scope::Room(key) => room == Some(key.as_str()),
+            Scope::Device {
+                room: room_key,
+                device: device_key,
+            } => room == Some(room_key.as_str()) && device == Some(device_key.as_str()),
+        }
+    }
+}
+
+/// A role over a scope, in one home or in all homes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grant {
+    /// The role granted.
+    pub role: Role,
+    /// Where the role applies.
+    pub scope: Scope,
+    /// Name of the home the grant is limited to; `None` for every home.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home: Option<String>,
+}
+
+/// Someone using the home, with the grants that say what they may do.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::access::{Role, Scope, User};
+///
+/// let guest = User::new("sam").grant(Role::Guest, Scope::Room("living_room".into()));
+/// assert!(guest.can("Flat", smart_home::access::Permission::Operate, Some("living_room"), Some("lamp")));
+/// assert!(!guest.can("Flat", smart_home::access::Permission::Operate, Some("bedroom"), Some("lamp")));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct User {
+    name: String,
+    #[serde(default)]
+    grants: Vec<Grant>,
+}
+
+impl User {
+    /// Creates a user without any grants.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            grants: Vec::new(),
+        }
+    }
+
+    /// Adds a grant valid in every home.
+    pub fn grant(mut self, role: Role, scope: Scope) -> Self {
+        self.grants.push(Grant {
+            role,
+            scope,
+            home: None,
+        });
+        self
+    }
+
+    /// Adds a grant valid only in the home named `home`.
+    pub fn grant_in(mut self, home: impl Into<String>, role: Role, scope: Scope) -> Self {
+        self.grants.push(Grant {
+            role,
+            scope,
+            home: Some(home.into()),
+        });
+        self
+    }
+
+    /// Returns the user name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the user's grants.
+    pub fn grants(&self) -> &[Grant] {
+        &self.grants
+    }
+
+    /// Returns `true` if some grant allows `permission` on `room`/`device` in
+    /// the home named `home`.
+    pub fn can(
+        &self,
+        home: &str,
+        permission: Permission,
+        room: Option<&str>,
+        device: Option<&str>,
+    ) -> bool {
+        self.grants.iter().any(|grant| {
+            grant.home.as_deref().is_none_or(|name| name == home)
+                && grant.role.allows(permission)
+                && grant.scope.covers(room, device)
+        })
+    }
+
+    /// Checks that the user may apply `command` to the home named `home`.
+    ///
+    /// Use this before [`SharedHome::execute`][crate::SharedHome::execute] when
+    /// the home is shared; [`AuthorizedHome`] does it for a plain [`SmartHome`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::PermissionDenied`] if no grant allows it.
+    pub fn authorize(&self, home: &str, command: &Command) -> Result<(), SmartHomeError> {
+        requirements(command)
+            .into_iter()
+            .try_for_each(|(permission, room, device)| self.check(home, permission, room, device))
+    }
+
+    fn check(
+        &self,
+        home: &str,
+        permission: Permission,
+        room: Option<&str>,
+        device: Option<&str>,
+    ) -> Result<(), SmartHomeError> {
+        if self.can(home, permission, room, device) {
+            return Ok(());
+        }
+        let target = match (room, device) {
+            (Some(room), Some(device)) => format!("{}/{}", room, device),
+            (Some(room), None) => room.to_string(),
+            _ => home.to_string(),
+        };
+        Err(SmartHomeError::PermissionDenied {
+            user: self.name.clone(),
+            permission: permission.name(),
+            target,
+        })
+    }
+}
+
+impl fmt::Display for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// A permission needed on a room or device (or the home when both are `None`).
+type Requirement<'a> = (Permission, Option<&'a str>, Option<&'a str>);
+
+/// Returns the permissions a command needs on the rooms and devices it touches.
+fn requirements(command: &Command) -> Vec<Requirement<'_>> {
+    match command {
+        // Room keys are what room-scoped grants refer to, so changing them
+        // needs home-wide rights.
+        Command::AddRoom { .. } | Command::RenameRoom { .. } | Command::ReplaceHome { .. } => {
+            vec![(Permission::ManageRooms, None, None)]
+        }
+        Command::RemoveRoom { room }
+        | Command::SetFloor { room, .. }
+        | Command::AddToZone { room, .. }
+        | Command::RemoveFromZone { room, .. } => {
+            vec![(Permission::ManageRooms, Some(room), None)]
+        }
+        Command::AddDevice { room, .. } => vec![(Permission::Configure, Some(room), None)],
+        Command::RemoveDevice { room, device }
+        | Command::RenameDevice { room, device, .. }
+        | Command::SetTemperature { room, device, .. }
+        | Command::UpdateWireless { room, device, .. } => {
+            vec![(Permission::Configure, Some(room), Some(device))]
+        }
+        Command::MoveDevice {
+            room,
+            device,
+            to_room,
+            ..
+        } => vec![
+            (Permission::Configure, Some(room), Some(device)),
+            (Permission::Configure, Some(to_room), None),
+        ],
+        Command::Tag { room, device, .. }
+        | Command::Untag { room, device, .. }
+        | Command::SetAttribute { room, device, .. }
+        | Command::UnsetAttribute { room, device, .. } => {
+            vec![(Permission::Configure, Some(room), device.as_deref())]
+        }
+        // Groups span rooms, so editing them needs home-wide rights.
+        Command::AddGroup { .. }
+        | Command::RemoveGroup { .. }
+        | Command::AddToGroup { .. }
+        | Command::RemoveFromGroup { .. } => vec![(Permission::Configure, None, None)],
+        Command::TurnOn { room, device }
+        | Command::TurnOff { room, device }
+        | Command::Toggle { room, device } => {
+            vec![(Permission::Operate, Some(room), Some(device))]
+        }
+    }
+}
+
+/// A [`SmartHome`] as seen by one [`User`]: every operation is checked first.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::access::{AuthorizedHome, Role, Scope, User};
+/// use smart_home::{room, SmartHome, SmartHomeError, Socket};
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+/// let guest = User::new("sam").grant(Role::Guest, Scope::Home);
+///
+/// let mut as_guest = AuthorizedHome::new(&mut home, &guest);
+/// as_guest.turn_on("hall", "lamp")?;
+/// assert!(matches!(
+///     as_guest.remove_room("hall"),
+///     Err(SmartHomeError::PermissionDenied { .. })
+/// ));
+/// # Ok::<(), SmartHomeError>(())
+/// ```
+pub struct AuthorizedHome<'a, C = SystemClock> {
+    home: &'a mut SmartHome,
+    user: &'a User,
+    audit: Option<&'a mut AuditLog<C>>,
+}
+
+impl<'a> AuthorizedHome<'a> {
+    /// Wraps `home` for `user`.
+    pub fn new(home: &'a mut SmartHome, user: &'a User) -> Self {
+        Self {
+            home,
+            user,
+            audit: None,
+        }
+    }
+}
+
+impl<'a, C: Clock> AuthorizedHome<'a, C> {
+    /// Records every change made through the wrapper in `log`.
+    pub fn audited<L: Clock>(self, log: &'a mut AuditLog<L>) -> AuthorizedHome<'a, L> {
+        AuthorizedHome {
+            home: self.home,
+            user: self.user,
+            audit: Some(log),
+        }
+    }
+
+    /// Returns the user acting on the home.
+    pub fn user(&self) -> &User {
+        self.user
+    }
+
+    /// Applies `command` if the user may.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::PermissionDenied`] without touching the home
+    /// if the user may not, otherwise the error from [`Command::apply`] or
+    /// [`AuditLog::record`].
+    pub fn apply(&mut self, command: Command) -> Result<HomeEvent, SmartHomeError> {
+        self.user.authorize(self.home.name(), &command)?;
+        match &mut self.audit {
+            Some(log) => log.apply(self.home, self.user.name(), command),
+            None => command.apply(self.home),
+        }
+    }
+
+    /// Returns a room the user may view.
+    ///
+    /// # Errors
+    ///
+    /// [`SmartHomeError::PermissionDenied`] or [`SmartHomeError::RoomNotFound`].
+    pub fn get_room(&self, key: &str) -> Result<&Room, SmartHomeError> {
+        self.user
+            .check(self.home.name(), Permission::View, Some(key), None)?;
+        self.home
+            .get_room(key)
+            .ok_or_else(|| SmartHomeError::RoomNotFound(key.to_string()))
+    }
+
+    /// Returns a device the user may view.
+    ///
+    /// # Errors
+    ///
+    /// [`SmartHomeError::PermissionDenied`], or as [`SmartHome::get_device`].
+    pub fn get_device(&self, room: &str, device: &str) -> Result<&SmartDevice, SmartHomeError> {
+        self.user
+            .check(self.home.name(), Permission::View, Some(room), Some(device))?;
+        self.home.get_device(room, device)
+    }
+
+    /// Adds a room; needs [`Permission::ManageRooms`] on the whole home.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuthorizedHome::apply`].
+    pub fn add_room(&mut self, key: impl Into<String>, room: Room) -> Result<(), SmartHomeError> {
+        self.apply(Command::AddRoom {
+            room: key.into(),
+            data: room,
+        })
+        .map(drop)
+    }
+
+    /// Removes a room; needs [`Permission::ManageRooms`] on it.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuthorizedHome::apply`].
+    pub fn remove_room(&mut self, key: &str) -> Result<(), SmartHomeError> {
+        self.apply(Command::RemoveRoom {
+            room: key.to_string(),
+        })
+        .map(drop)
+    }
+
+    /// Adds a device; needs [`Permission::Configure`] on the room.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuthorizedHome::apply`].
+    pub fn add_device(
+        &mut self,
+        room: &str,
+        key: impl Into<String>,
+        device: impl Into<SmartDevice>,
+    ) -> Result<(), SmartHomeError> {
+        self.apply(Command::AddDevice {
+            room: room.to_string(),
+            device: key.into(),
+            data: device.into(),
+        })
+        .map(drop)
+    }
+
+    /// Removes a device; needs [`Permission::Configure`] on it.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuthorizedHome::apply`].
+    pub fn remove_device(&mut self, room: &str, device: &str) -> Result<(), SmartHomeError> {
+        self.apply(Command::RemoveDevice {
+            room: room.to_string(),
+            device: device.to_string(),
+        })
+        .map(drop)
+    }
+
+    /// Turns a socket on; needs [`Permission::Operate`] on it.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuthorizedHome::apply`].
+    pub fn turn_on(&mut self, room: &str, device: &str) -> Result<(), SmartHomeError> {
+        let (room, device) = (room.to_string(), device.to_string());
+        self.apply(Command::TurnOn { room, device }).map(drop)
+    }
+
+    /// Turns a socket off; needs [`Permission::Operate`] on it.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuthorizedHome::apply`].
+    pub fn turn_off(&mut self, room: &str, device: &str) -> Result<(), SmartHomeError> {
+        let (room, device) = (room.to_string(), device.to_string());
+        self.apply(Command::TurnOff { room, device }).map(drop)
+    }
+
+    /// Toggles a socket; needs [`Permission::Operate`] on it.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuthorizedHome::apply`].
+    pub fn toggle(&mut self, room: &str, device: &str) -> Result<(), SmartHomeError> {
+        let (room, device) = (room.to_string(), device.to_string());
+        self.apply(Command::Toggle { room, device }).map(drop)
+    }
+
+    /// Sets a thermometer reading; needs [`Permission::Configure`] on it.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuthorizedHome::apply`].
+    pub fn set_temperature(
+        &mut self,
+        room: &str,
+        device: &str,
+        temperature: f32,
+    ) -> Result<(), SmartHomeError> {
+        self.apply(Command::SetTemperature {
+            room: room.to_string(),
+            device: device.to_string(),
+            temperature,
+        })
+        .map(drop)
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "kettle" => Socket::new("Kettle", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 22.0),
+            ),
+        )
+        .unwrap();
+        home.add_room(
+            "bedroom",
+            room!("Bedroom", "lamp" => Socket::new("Lamp", 60.0)),
+        )
+        .unwrap();
+        home
+    }
+
+    fn denied<T: fmt::Debug>(result: Result<T, SmartHomeError>) -> String {
+        match result {
+            Err(SmartHomeError::PermissionDenied {
+                permission, target, ..
+            }) => format!("{} {}", permission, target),
+            other => panic!("expected a permission error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_roles() {
+        let mut home = make_home();
+        let owner = User::new("olga").grant(Role::Owner, Scope::Home);
+        let guest = User::new("sam").grant(Role::Guest, Scope::Home);
+        let installer = User::new("ivan").grant(Role::Installer, Scope::Home);
+
+        let mut as_guest = AuthorizedHome::new(&mut home, &guest);
+        as_guest.toggle("kitchen", "kettle").unwrap();
+        assert_eq!(
+            denied(as_guest.remove_room("kitchen")),
+            "manage_rooms kitchen"
+        );
+        assert_eq!(
+            denied(as_guest.remove_device("kitchen", "kettle")),
+            "configure kitchen/kettle"
+        );
+
+        let mut as_installer = AuthorizedHome::new(&mut home, &installer);
+        as_installer
+            .add_device("kitchen", "fridge", Socket::new("Fridge", 150.0))
+            .unwrap();
+        as_installer
+            .set_temperature("kitchen", "sensor", 19.0)
+            .unwrap();
+        assert_eq!(
+            denied(as_installer.turn_off("kitchen", "kettle")),
+            "operate kitchen/kettle"
+        );
+        assert_eq!(
+            denied(as_installer.add_room("attic", Room::new("Attic"))),
+            "manage_rooms Flat"
+        );
+
+        let mut as_owner = AuthorizedHome::new(&mut home, &owner);
+        as_owner.remove_room("kitchen").unwrap();
+        assert_eq!(home.room_count(), 1);
+    }
+
+    #[test]
+    fn test_scopes() {
+        let mut home = make_home();
+        let user = User::new("kid")
+            .grant(Role::Guest, Scope::Room("bedroom".into()))
+            .grant(
+                Role::Resident,
+                Scope::Device {
+                    room: "kitchen".into(),
+                    device: "sensor".into(),
+                },
+            );
+        let mut view = AuthorizedHome::new(&mut home, &user);
+        view.turn_on("bedroom", "lamp").unwrap();
+        assert!(view.get_room("bedroom").is_ok());
+        assert_eq!(denied(view.get_room("kitchen")), "view kitchen");
+        assert_eq!(
+            denied(view.toggle("kitchen", "kettle")),
+            "operate kitchen/kettle"
+        );
+        view.set_temperature("kitchen", "sensor", 20.0).unwrap();
+        assert!(view.get_device("kitchen", "sensor").is_ok());
+        // Permission is checked before existence, so hidden rooms stay hidden.
+        assert_eq!(denied(view.get_device("attic", "x")), "view attic/x");
+    }
+
+    #[test]
+    fn test_audited_changes_carry_the_user() {
+        let mut home = make_home();
+        let guest = User::new("sam").grant(Role::Guest, Scope::Home);
+        let mut log = AuditLog::new(crate::ManualClock::default());
+        let mut as_guest = AuthorizedHome::new(&mut home, &guest).audited(&mut log);
+        as_guest.turn_off("kitchen", "kettle").unwrap();
+        assert!(as_guest.remove_room("kitchen").is_err());
+        let entries = log.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            (entries[0].actor.as_str(), entries[0].path.as_str()),
+            ("sam", "kitchen/kettle")
+        );
+    }
+
+    #[test]
+    fn test_grants_limited_to_one_home() {
+        let user = User::new("olga").grant_in("Flat", Role::Owner, Scope::Home);
+        let command = Command::RemoveRoom {
+            room: "kitchen".into(),
+        };
+        assert!(user.authorize("Flat", &command).is_ok());
+        assert!(user.authorize("Cottage", &command).is_err());
+        assert!(!User::new("nobody").can("Flat", Permission::View, None, None));
+    }
+
+    #[test]
+    fn test_users_serialize() {
+        let user = User::new("sam").grant_in("Flat", Role::Guest, Scope::Room("hall".into()));
+        let json = serde_json::to_string(&user).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"sam","grants":[{"role":"guest","scope":{"room":"hall"},"home":"Flat"}]}"#
+        );
+        assert_eq!(serde_json::from_str::<User>(&json).unwrap(), user);
+    }
+}
@@ -0,0 +1,931 @@
+//! Alerts raised from conditions in a home, delivered through sinks.
+//!
+//! An [`AlertManager`] checks a home against its rules (temperature ranges,
+//! low batteries, power budgets) with [`AlertManager::evaluate`], and turns
+//! availability changes into offline alerts with
+//! [`AlertManager::observe_availability`].  Each condition has at most one
+//! open [`Alert`]: repeats only bump its occurrence count, and the alert is
+//! resolved once the condition clears.  Users can acknowledge an alert to say
+//! they are on it.
+//!
+//! Every new, escalated, acknowledged or resolved alert is passed to the
+//! manager's [`AlertSink`]s: [`StdoutSink`], [`FileSink`], [`WebhookSink`]
+//! and [`MemorySink`] for tests.
+
+use crate::area::Area;
+use crate::availability::{Availability, AvailabilityEvent};
+use crate::clock::{Clock, SystemClock};
+use crate::error::SmartHomeError;
+use crate::report::Report;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use crate::trace::format_timestamp;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+/// How urgent an alert is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing about.
+    Info,
+    /// Needs attention soon.
+    Warning,
+    /// Needs attention now.
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+/// The condition an alert is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// A thermometer reads outside its allowed range.
+    TemperatureOutOfRange,
+    /// A device has not been heard from for longer than its offline timeout.
+    DeviceOffline,
+    /// A wireless device's battery is at or below its low-battery threshold.
+    LowBattery,
+    /// The sockets in an area draw more than its power budget.
+    PowerBudgetExceeded,
+}
+
+impl AlertKind {
+    /// Returns `true` for conditions [`AlertManager::evaluate`] checks.
+    fn is_home_state(self) -> bool {
+        self != AlertKind::DeviceOffline
+    }
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AlertKind::TemperatureOutOfRange => "temperature out of range",
+            AlertKind::DeviceOffline => "device offline",
+            AlertKind::LowBattery => "low battery",
+            AlertKind::PowerBudgetExceeded => "power budget exceeded",
+        })
+    }
+}
+
+/// Where an alert is in its life.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "state", content = "by")]
+pub enum AlertState {
+    /// The condition holds and nobody has acknowledged it.
+    Active,
+    /// The condition holds and the named user is looking into it.
+    Acknowledged(String),
+    /// The condition cleared, or the alert was resolved by hand.
+    Resolved,
+}
+
+impl fmt::Display for AlertState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertState::Active => f.write_str("active"),
+            AlertState::Acknowledged(by) => write!(f, "acknowledged by {}", by),
+            AlertState::Resolved => f.write_str("resolved"),
+        }
+    }
+}
+
+/// An alert about one condition at one place.
+///
+/// Serialized as a JSON object with ISO 8601 timestamps, which is what
+/// [`FileSink`] and [`WebhookSink`] deliver.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    /// Number of the alert, unique within its manager.
+    pub id: u64,
+    /// The condition.
+    pub kind: AlertKind,
+    /// `"room/device"` for device conditions, or the area (e.g. `"floor:1"`).
+    pub path: String,
+    /// How urgent it is.
+    pub severity: Severity,
+    /// What was observed most recently, e.g. `"31.5 °C is above 28 °C"`.
+    pub message: String,
+    /// Where the alert is in its life.
+    #[serde(flatten)]
+    pub state: AlertState,
+    /// When the condition was first seen.
+    #[serde(serialize_with = "serialize_time")]
+    pub raised_at: SystemTime,
+    /// When the alert last changed.
+    #[serde(serialize_with = "serialize_time")]
+    pub updated_at: SystemTime,
+    /// How many times the condition was seen while the alert was open.
+    pub occurrences: u32,
+}
+
+impl Alert {
+    /// Returns `true` unless the alert is resolved.
+    pub fn is_open(&self) -> bool {
+        self.state != AlertState::Resolved
+    }
+}
+
+impl fmt::Display for Alert {
+    /// Formats as `#<id> <severity> [<path>] <kind>: <message> (<state>)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} {} [{}] {}: {} ({})",
+            self.id, self.severity, self.path, self.kind, self.message, self.state
+        )
+    }
+}
+
+fn serialize_time<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_timestamp(*time))
+}
+
+/// An inclusive range of acceptable temperatures in Celsius.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemperatureRange {
+    min: f32,
+    max: f32,
+}
+
+impl TemperatureRange {
+    /// Creates a range from `min` to `max`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if either bound is not finite
+    /// or `min` is greater than `max`.
+    pub fn new(min: f32, max: f32) -> Result<Self, SmartHomeError> {
+        if !min.is_finite() || !max.is_finite() || min > max {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "temperature range {} to {} °C is not a finite, ordered range",
+                min, max
+            )));
+        }
+        Ok(Self { min, max })
+    }
+
+    /// Returns the lowest acceptable temperature.
+    pub fn min(&self) -> f32 {
+        self.min
+    }
+
+    /// Returns the highest acceptable temperature.
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    /// Describes how `temperature` falls outside the range, if it does.
+    fn violation(&self, temperature: f32) -> Option<String> {
+        if temperature < self.min {
+            Some(format!("{} °C is below {} °C", temperature, self.min))
+        } else if temperature > self.max {
+            Some(format!("{} °C is above {} °C", temperature, self.max))
+        } else {
+            None
+        }
+    }
+}
+
+/// Receives alerts from an [`AlertManager`].
+pub trait AlertSink: Send {
+    /// Delivers an alert that was raised, escalated, acknowledged or resolved;
+    /// [`Alert::state`] tells which.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the alert could not be delivered.
+    fn notify(&mut self, alert: &Alert) -> Result<(), SmartHomeError>;
+}
+
+/// A condition seen during one check: `(kind, path)` with severity and message.
+type Finding = ((AlertKind, String), Severity, String);
+
+/// Raises, deduplicates and resolves alerts and passes them to sinks.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::alert::{AlertKind, AlertManager, MemorySink, TemperatureRange};
+/// use smart_home::{room, ManualClock, SmartHome, Thermometer};
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "sensor" => Thermometer::new("Sensor", 31.0)))?;
+/// let sink = MemorySink::new();
+/// let mut alerts = AlertManager::new(ManualClock::default())
+///     .with_temperature_range(TemperatureRange::new(16.0, 28.0)?);
+/// alerts.add_sink(sink.clone());
+///
+/// alerts.evaluate(&home)?;
+/// alerts.evaluate(&home)?; // still too hot: no second notification
+/// assert_eq!(sink.alerts().len(), 1);
+/// let alert = &sink.alerts()[0];
+/// assert_eq!(alert.kind, AlertKind::TemperatureOutOfRange);
+/// assert_eq!(alert.message, "31 °C is above 28 °C");
+///
+/// alerts.acknowledge(alert.id, "alice")?;
+/// home.get_thermometer_mut("hall", "sensor")?.set_temperature(22.0);
+/// alerts.evaluate(&home)?;
+/// assert!(alerts.open().next().is_none());
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+pub struct AlertManager<C = SystemClock> {
+    clock: C,
+    temperature: Option<TemperatureRange>,
+    temperature_overrides: BTreeMap<(String, String), TemperatureRange>,
+    power_budgets: Vec<(Area, f32)>,
+    alerts: Vec<Alert>,
+    open: BTreeMap<(AlertKind, String), usize>,
+    sinks: Vec<Box<dyn AlertSink>>,
+}
+
+impl<C: Clock> AlertManager<C> {
+    /// Creates a manager that only checks low batteries until more rules are added.
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            temperature: None,
+            temperature_overrides: BTreeMap::new(),
+            power_budgets: Vec::new(),
+            alerts: Vec::new(),
+            open: BTreeMap::new(),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// Alerts when a thermometer without its own range reads outside `range`.
+    pub fn with_temperature_range(mut self, range: TemperatureRange) -> Self {
+        self.temperature = Some(range);
+        self
+    }
+
+    /// Uses `range` for one thermometer, e.g. a freezer.
+    pub fn set_temperature_range(&mut self, room: &str, device: &str, range: TemperatureRange) {
+        self.temperature_overrides
+            .insert((room.to_string(), device.to_string()), range);
+    }
+
+    /// Alerts when the sockets in `area` draw more than `watts`.  Replaces an
+    /// earlier budget for the same area.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if `watts` is not a positive number.
+    pub fn set_power_budget(&mut self, area: Area, watts: f32) -> Result<(), SmartHomeError> {
+        if !(watts.is_finite() && watts > 0.0) {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "power budget {} W must be positive",
+                watts
+            )));
+        }
+        self.power_budgets.retain(|(a, _)| *a != area);
+        self.power_budgets.push((area, watts));
+        Ok(())
+    }
+
+    /// Adds a sink that receives every alert change from now on.
+    pub fn add_sink(&mut self, sink: impl AlertSink + 'static) {
+        self.sinks.push(Box::new(sink));
+    }
+
+    /// Checks `home` against the temperature, battery and power rules.  Raises
+    /// alerts for new conditions and resolves those that cleared, including
+    /// alerts of any kind, offline ones too, for devices that were removed.
+    /// Returns the alerts that changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first sink error.  All sinks are still tried, and the
+    /// alerts are updated either way.
+    pub fn evaluate(&mut self, home: &SmartHome) -> Result<Vec<Alert>, SmartHomeError> {
+        let findings = self.check(home);
+        let firing: BTreeSet<&(AlertKind, String)> = findings.iter().map(|(key, ..)| key).collect();
+        let cleared: Vec<(AlertKind, String)> = self
+            .open
+            .keys()
+            .filter(|key| {
+                if key.0.is_home_state() {
+                    !firing.contains(key)
+                } else {
+                    !device_exists(home, &key.1)
+                }
+            })
+            .cloned()
+            .collect();
+
+        let mut changed = Vec::new();
+        for (key, severity, message) in findings {
+            changed.extend(self.raise(key, severity, message));
+        }
+        for key in cleared {
+            changed.extend(self.clear(&key));
+        }
+        self.deliver(changed)
+    }
+
+    /// Raises a critical alert for each device that went offline and resolves
+    /// it when the device is back online.  Returns the alerts that changed.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AlertManager::evaluate`].
+    pub fn observe_availability(
+        &mut self,
+        events: &[AvailabilityEvent],
+    ) -> Result<Vec<Alert>, SmartHomeError> {
+        let mut changed = Vec::new();
+        for event in events {
+            let key = (
+                AlertKind::DeviceOffline,
+                format!("{}/{}", event.room, event.device),
+            );
+            match event.status {
+                Availability::Offline => {
+                    let message = format!("last seen {}", format_timestamp(event.last_seen));
+                    changed.extend(self.raise(key, Severity::Critical, message));
+                }
+                Availability::Online => changed.extend(self.clear(&key)),
+                Availability::Stale => {}
+            }
+        }
+        self.deliver(changed)
+    }
+
+    /// Marks an open alert as being looked into by `user`.  It stays open
+    /// until its condition clears, and is not announced again unless it gets
+    /// more severe.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::AlertNotFound`] for an unknown id,
+    /// [`SmartHomeError::InvalidValue`] if the alert is resolved, or the first
+    /// sink error.
+    pub fn acknowledge(&mut self, id: u64, user: &str) -> Result<Alert, SmartHomeError> {
+        let now = self.clock.now();
+        let alert = self.open_alert_mut(id)?;
+        alert.state = AlertState::Acknowledged(user.to_string());
+        alert.updated_at = now;
+        let alert = alert.clone();
+        self.deliver(vec![alert.clone()])?;
+        Ok(alert)
+    }
+
+    /// Resolves an open alert by hand, e.g. after fixing a device.  The alert
+    /// is raised again if the next check still finds the condition.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AlertManager::acknowledge`].
+    pub fn resolve(&mut self, id: u64) -> Result<Alert, SmartHomeError> {
+        let alert = self.open_alert_mut(id)?;
+        let key = (alert.kind, alert.path.clone());
+        let alert = self.clear(&key).expect("open alert is indexed");
+        self.deliver(vec![alert.clone()])?;
+        Ok(alert)
+    }
+
+    /// Returns the alert with number `id`.
+    pub fn get(&self, id: u64) -> Option<&Alert> {
+        self.alerts.iter().find(|a| a.id == id)
+    }
+
+    /// Returns every alert, open and resolved, oldest first.
+    pub fn alerts(&self) -> &[Alert] {
+        &self.alerts
+    }
+
+    /// Returns the open alerts, most severe first.
+    pub fn open(&self) -> impl Iterator<Item = &Alert> {
+        let mut open: Vec<&Alert> = self.open.values().map(|&i| &self.alerts[i]).collect();
+        open.sort_by_key(|a| (std::cmp::Reverse(a.severity), a.id));
+        open.into_iter()
+    }
+
+    /// Returns the conditions that hold in `home`, in room and device order.
+    fn check(&self, home: &SmartHome) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let mut devices: Vec<(&str, &str, &SmartDevice)> = home
+            .rooms()
+            .flat_map(|(room_key, room)| {
+                room.devices()
+                    .map(move |(device_key, device)| (room_key, device_key, device))
+            })
+            .collect();
+        devices.sort_by_key(|&(room, device, _)| (room, device));
+
+        for (room, device, data) in devices {
+            let path = format!("{}/{}", room, device);
+            let range = self
+                .temperature_overrides
+                .get(&(room.to_string(), device.to_string()))
+                .or(self.temperature.as_ref());
+            if let (Some(range), Some(t)) = (range, data.as_thermometer()) {
+                if let Some(message) = range.violation(t.temperature()) {
+                    let key = (AlertKind::TemperatureOutOfRange, path.clone());
+                    findings.push((key, Severity::Warning, message));
+                }
+            }
+            if let Some(warning) = data.wireless().and_then(|w| w.warning()) {
+                findings.push(((AlertKind::LowBattery, path), Severity::Warning, warning));
+            }
+        }
+
+        for (area, budget) in &self.power_budgets {
+            let power = area.power(home);
+            if power > *budget {
+                let key = (AlertKind::PowerBudgetExceeded, area.to_string());
+                let message = format!("{} W exceeds the {} W budget", power, budget);
+                findings.push((key, Severity::Critical, message));
+            }
+        }
+        findings
+    }
+
+    /// Opens an alert for `key`, or updates the open one.  Returns the alert
+    /// if sinks should hear about it: when new, or when more severe than before.
+    fn raise(
+        &mut self,
+        key: (AlertKind, String),
+        severity: Severity,
+        message: String,
+    ) -> Option<Alert> {
+        let now = self.clock.now();
+        if let Some(&index) = self.open.get(&key) {
+            let alert = &mut self.alerts[index];
+            alert.occurrences += 1;
+            alert.message = message;
+            if severity <= alert.severity {
+                return None;
+            }
+            alert.severity = severity;
+            alert.state = AlertState::Active;
+            alert.updated_at = now;
+            return Some(alert.clone());
+        }
+        let (kind, path) = key.clone();
+        let alert = Alert {
+            id: self.alerts.len() as u64 + 1,
+            kind,
+            path,
+            severity,
+            message,
+            state: AlertState::Active,
+            raised_at: now,
+            updated_at: now,
+            occurrences: 1,
+        };
+        self.open.insert(key, self.alerts.len());
+        self.alerts.push(alert.clone());
+        Some(alert)
+    }
+
+    /// Resolves the open alert for `key`, if any.
+    fn clear(&mut self, key: &(AlertKind, String)) -> Option<Alert> {
+        let index = self.open.remove(key)?;
+        let alert = &mut self.alerts[index];
+        alert.state = AlertState::Resolved;
+        alert.updated_at = self.clock.now();
+        Some(alert.clone())
+    }
+
+    fn open_alert_mut(&mut self, id: u64) -> Result<&mut Alert, SmartHomeError> {
+        let alert = self
+            .alerts
+            .iter_mut()
+            .find(|a| a.id == id)
+            .ok_or(SmartHomeError::AlertNotFound(id))?;
+        if !alert.is_open() {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "alert #{} is already resolved",
+                id
+            )));
+        }
+        Ok(alert)
+    }
+
+    /// Passes `alerts` to every sink and returns them.
+    fn deliver(&mut self, alerts: Vec<Alert>) -> Result<Vec<Alert>, SmartHomeError> {
+        let mut first_error = None;
+        for alert in &alerts {
+            for sink in &mut self.sinks {
+                if let Err(e) = sink.notify(alert) {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(alerts),
+        }
+    }
+}
+
+impl<C: Clock> Report for AlertManager<C> {
+    /// Lists the open alerts, most severe first.
+    fn report(&self) -> String {
+        let open: Vec<&Alert> = self.open().collect();
+        let mut s = format!("Alerts ({} open):\n", open.len());
+        for alert in open {
+            s.push_str(&format!("  {}\n", alert));
+        }
+        s
+    }
+}
+
+/// Returns `true` if `path` (`"room/device"`) names a device in `home`.
+fn device_exists(home: &SmartHome, path: &str) -> bool {
+    path.split_once('/')
+        .is_some_and(|(room, device)| home.get_device(room, device).is_ok())
+}
+
+// ── Sinks ─────────────────────────────────────────────────────────────────────
+
+/// Prints each alert as a line of text on standard output.
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl AlertSink for StdoutSink {
+    fn notify(&mut self, alert: &Alert) -> Result<(), SmartHomeError> {
+        writeln!(
+            io::stdout().lock(),
+            "{} {}",
+            format_timestamp(alert.updated_at),
+            alert
+        )?;
+        Ok(())
+    }
+}
+
+/// Appends each alert to a file as a line of JSON.
+#[derive(Debug)]
+pub struct FileSink {
+    file: File,
+}
+
+impl FileSink {
+    /// Opens `path` for appending, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the file cannot be opened.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SmartHomeError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+}
+
+impl AlertSink for FileSink {
+    fn notify(&mut self, alert: &Alert) -> Result<(), SmartHomeError> {
+        let mut line = serde_json::to_vec(alert)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        Ok(())
+    }
+}
+
+/// How long a webhook may take to accept a connection or answer.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Posts each alert as JSON to a plain-HTTP endpoint on the local network.
+///
+/// Any `2xx` answer counts as delivered.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    host: String,
+    path: String,
+}
+
+impl WebhookSink {
+    /// Creates a sink posting to `url`, e.g. `http://127.0.0.1:9000/alerts`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if `url` is not an `http://`
+    /// URL with a host.
+    pub fn new(url: &str) -> Result<Self, SmartHomeError> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            SmartHomeError::InvalidValue(format!("webhook URL '{}' must start with http://", url))
+        })?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "webhook URL '{}' has no host",
+                url
+            )));
+        }
+        Ok(Self {
+            host: host.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    fn address(&self) -> String {
+        if self
+            .host
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| !port.contains(']'))
+        {
+            self.host.clone()
+        } else {
+            format!("{}:80", self.host)
+        }
+    }
+}
+
+impl AlertSink for WebhookSink {
+    fn notify(&mut self, alert: &Alert) -> Result<(), SmartHomeError> {
+        let body = serde_json::to_string(alert)?;
+        let addr = self
+            .address()
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "webhook host not found"))?;
+        let mut stream = TcpStream::connect_timeout(&addr, WEBHOOK_TIMEOUT)?;
+        stream.set_read_timeout(Some(WEBHOOK_TIMEOUT))?;
+        stream.set_write_timeout(Some(WEBHOOK_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        )?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+        if status.len() == 3 && status.starts_with('2') {
+            Ok(())
+        } else {
+            Err(SmartHomeError::Remote(format!(
+                "webhook answered '{}'",
+                status_line.trim_end()
+            )))
+        }
+    }
+}
+
+/// Keeps every alert in memory; clones share the same list.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    alerts: Arc<Mutex<Vec<Alert>>>,
+}
+
+impl MemorySink {
+    /// Creates an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the alerts delivered so far, in order.
+    pub fn alerts(&self) -> Vec<Alert> {
+        self.alerts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl AlertSink for MemorySink {
+    fn notify(&mut self, alert: &Alert) -> Result<(), SmartHomeError> {
+        self.alerts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(alert.clone());
+        Ok(())
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::availability::AvailabilityTracker;
+    use crate::clock::ManualClock;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "kettle" => Socket::new("Kettle", 2000.0),
+                "heater" => Socket::new("Heater", 1500.0),
+                "freezer" => Thermometer::new("Freezer", -18.0),
+                "sensor" => Thermometer::new("Sensor", 21.0),
+            ),
+        )
+        .unwrap();
+        home
+    }
+
+    fn manager(sink: &MemorySink) -> AlertManager<ManualClock> {
+        let mut alerts = AlertManager::new(ManualClock::default())
+            .with_temperature_range(TemperatureRange::new(16.0, 26.0).unwrap());
+        alerts.set_temperature_range(
+            "kitchen",
+            "freezer",
+            TemperatureRange::new(-30.0, -15.0).unwrap(),
+        );
+        alerts.add_sink(sink.clone());
+        alerts
+    }
+
+    #[test]
+    fn test_temperature_alerts_deduplicate_and_resolve() {
+        let sink = MemorySink::new();
+        let mut alerts = manager(&sink);
+        let mut home = make_home();
+        assert!(alerts.evaluate(&home).unwrap().is_empty());
+
+        let freezer = home.get_thermometer_mut("kitchen", "freezer").unwrap();
+        freezer.set_temperature(-10.0);
+        alerts.evaluate(&home).unwrap();
+        home.get_thermometer_mut("kitchen", "freezer")
+            .unwrap()
+            .set_temperature(-8.0);
+        assert!(alerts.evaluate(&home).unwrap().is_empty());
+
+        let open: Vec<&Alert> = alerts.open().collect();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].path, "kitchen/freezer");
+        assert_eq!(open[0].occurrences, 2);
+        assert_eq!(open[0].message, "-8 °C is above -15 °C");
+
+        home.get_thermometer_mut("kitchen", "freezer")
+            .unwrap()
+            .set_temperature(-20.0);
+        let changed = alerts.evaluate(&home).unwrap();
+        assert_eq!(changed[0].state, AlertState::Resolved);
+        let states: Vec<AlertState> = sink.alerts().into_iter().map(|a| a.state).collect();
+        assert_eq!(states, [AlertState::Active, AlertState::Resolved]);
+    }
+
+    #[test]
+    fn test_acknowledge_and_resolve_by_hand() {
+        let sink = MemorySink::new();
+        let mut alerts = manager(&sink);
+        let mut home = make_home();
+        home.get_thermometer_mut("kitchen", "sensor")
+            .unwrap()
+            .wireless_mut()
+            .set_battery(Some(5))
+            .unwrap();
+        let id = alerts.evaluate(&home).unwrap()[0].id;
+        assert_eq!(alerts.get(id).unwrap().kind, AlertKind::LowBattery);
+
+        let alert = alerts.acknowledge(id, "alice").unwrap();
+        assert_eq!(alert.state, AlertState::Acknowledged("alice".into()));
+        assert!(alerts.evaluate(&home).unwrap().is_empty());
+        assert!(alerts.report().contains("(acknowledged by alice)"));
+
+        alerts.resolve(id).unwrap();
+        assert!(matches!(
+            alerts.acknowledge(id, "bob"),
+            Err(SmartHomeError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            alerts.resolve(99),
+            Err(SmartHomeError::AlertNotFound(99))
+        ));
+        // Still low: a new alert is raised.
+        let again = alerts.evaluate(&home).unwrap();
+        assert_ne!(again[0].id, id);
+        assert_eq!(sink.alerts().len(), 4);
+    }
+
+    #[test]
+    fn test_power_budget_and_removed_devices() {
+        let sink = MemorySink::new();
+        let mut alerts = manager(&sink);
+        alerts.set_power_budget(Area::Home, 3000.0).unwrap();
+        assert!(alerts.set_power_budget(Area::Home, -1.0).is_err());
+        let mut home = make_home();
+        home.get_socket_mut("kitchen", "kettle").unwrap().turn_on();
+        home.get_socket_mut("kitchen", "heater").unwrap().turn_on();
+        home.get_thermometer_mut("kitchen", "sensor")
+            .unwrap()
+            .set_temperature(30.0);
+
+        let changed = alerts.evaluate(&home).unwrap();
+        let kinds: Vec<AlertKind> = changed.iter().map(|a| a.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                AlertKind::TemperatureOutOfRange,
+                AlertKind::PowerBudgetExceeded
+            ]
+        );
+        assert_eq!(alerts.open().next().unwrap().path, "home");
+
+        home.remove_device("kitchen", "sensor").unwrap();
+        home.get_socket_mut("kitchen", "heater").unwrap().turn_off();
+        let changed = alerts.evaluate(&home).unwrap();
+        assert!(changed.iter().all(|a| a.state == AlertState::Resolved));
+        assert_eq!(changed.len(), 2);
+    }
+
+    #[test]
+    fn test_offline_devices() {
+        let sink = MemorySink::new();
+        let mut alerts = manager(&sink);
+        let clock = ManualClock::default();
+        let mut tracker = AvailabilityTracker::new(clock.clone());
+        tracker.seen("kitchen", "sensor");
+
+        clock.advance(Duration::from_secs(10 * 60));
+        assert!(alerts
+            .observe_availability(&tracker.check())
+            .unwrap()
+            .is_empty());
+        clock.advance(Duration::from_secs(10 * 60));
+        let changed = alerts.observe_availability(&tracker.check()).unwrap();
+        assert_eq!(changed[0].kind, AlertKind::DeviceOffline);
+        assert_eq!(changed[0].severity, Severity::Critical);
+
+        // Offline alerts survive evaluation until the device is back.
+        alerts.evaluate(&make_home()).unwrap();
+        assert_eq!(alerts.open().count(), 1);
+        let back: Vec<AvailabilityEvent> = tracker.seen("kitchen", "sensor").into_iter().collect();
+        alerts.observe_availability(&back).unwrap();
+        assert_eq!(alerts.open().count(), 0);
+    }
+
+    #[test]
+    fn test_offline_alerts_for_removed_devices_resolve() {
+        let sink = MemorySink::new();
+        let mut alerts = manager(&sink);
+        let clock = ManualClock::default();
+        let mut tracker = AvailabilityTracker::new(clock.clone());
+        let mut home = make_home();
+        tracker.track_all(&home);
+        clock.advance(Duration::from_secs(20 * 60));
+        alerts.observe_availability(&tracker.check()).unwrap();
+        assert_eq!(alerts.open().count(), 4);
+
+        home.remove_device("kitchen", "kettle").unwrap();
+        let changed = alerts.evaluate(&home).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].path, "kitchen/kettle");
+        assert_eq!(changed[0].state, AlertState::Resolved);
+        assert_eq!(alerts.open().count(), 3);
+    }
+
+    #[test]
+    fn test_file_sink_writes_json_lines() {
+        let path = std::env::temp_dir().join(format!("alerts-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut alerts = AlertManager::new(ManualClock::default())
+            .with_temperature_range(TemperatureRange::new(16.0, 20.0).unwrap());
+        alerts.add_sink(FileSink::open(&path).unwrap());
+        alerts.evaluate(&make_home()).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2, "freezer too cold, sensor too warm");
+        let json: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(json["path"], "kitchen/freezer");
+        assert_eq!(json["kind"], "temperature_out_of_range");
+        assert_eq!(json["state"], "active");
+        assert_eq!(json["raised_at"], "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_webhook_urls() {
+        let sink = WebhookSink::new("http://127.0.0.1:9000/alerts").unwrap();
+        assert_eq!(
+            (sink.address().as_str(), sink.path.as_str()),
+            ("127.0.0.1:9000", "/alerts")
+        );
+        assert_eq!(
+            WebhookSink::new("http://hub.local").unwrap().address(),
+            "hub.local:80"
+        );
+        assert!(WebhookSink::new("https://example.com/").is_err());
+        assert!(WebhookSink::new("http:///alerts").is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Floors, zones and other groups of rooms.
+//!
+//! A [`Room`] can be placed on one floor and in any number of zones (e.g.
+//! "upstairs" or "heating zone A").  An [`Area`] names such a group, or the
+//! whole home or a single room, and provides reports, power totals and bulk
+//! switching for it.
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::report::Report;
+use crate::room::Room;
+use crate::smart_home::SmartHome;
+use std::fmt;
+use std::str::FromStr;
+
+/// A group of rooms in a home.
+///
+/// Parsed from and displayed as `home`, `floor:<name>`, `zone:<name>` or
+/// `room:<key>`.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::area::Area;
+/// use smart_home::{room, SmartHome, Socket};
+///
+/// let mut home = SmartHome::new("House");
+/// home.add_room("bedroom", room!("Bedroom", "lamp" => Socket::new("Lamp", 60.0)))?;
+/// home.get_room_mut("bedroom").unwrap().set_floor(Some("upstairs".into()));
+///
+/// let upstairs: Area = "floor:upstairs".parse()?;
+/// for command in upstairs.switch_sockets(&home, true) {
+///     command.apply(&mut home)?;
+/// }
+/// assert_eq!(upstairs.power(&home), 60.0);
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Area {
+    /// Every room in the home.
+    Home,
+    /// Rooms on the named floor.
+    Floor(String),
+    /// Rooms in the named zone.
+    Zone(String),
+    /// The room with this key.
+    Room(String),
+}
+
+impl Area {
+    /// Returns `true` if the room with key `key` belongs to the area.
+    pub fn contains(&self, key: &str, room: &Room) -> bool {
+        match self {
+            Area::Home => true,
+            Area::Floor(floor) => room.floor() == Some(floor.as_str()),
+            Area::Zone(zone) => room.in_zone(zone),
+            Area::Room(room_key) => key == room_key,
+        }
+    }
+
+    /// Returns the `(key, room)` pairs of `home` in the area, sorted by key.
+    pub fn rooms<'a>(&self, home: &'a SmartHome) -> Vec<(&'a str, &'a Room)> {
+        let mut rooms: Vec<_> = home
+            .rooms()
+            .filter(|(key, room)| self.contains(key, room))
+            .collect();
+        rooms.sort_by_key(|(key, _)| *key);
+        rooms
+    }
+
+    /// Returns the total power drawn by sockets in the area, in watts.
+    pub fn power(&self, home: &SmartHome) -> f32 {
+        self.rooms(home)
+            .iter()
+            .map(|(_, room)| room.power())
+            .fold(0.0, |total, power| total + power)
+    }
+
+    /// Returns the commands that switch every socket in the area on or off.
+    ///
+    /// Apply them with [`SharedHome::execute_all`][crate::SharedHome::execute_all]
+    /// or [`SmartHome::transaction`] to switch the whole area at once.
+    pub fn switch_sockets(&self, home: &SmartHome, on: bool) -> Vec<Command> {
+        let mut commands = Vec::new();
+        for (room_key, room) in self.rooms(home) {
+            let mut sockets: Vec<_> = room
+                .devices()
+                .filter(|(_, device)| device.as_socket().is_some())
+                .map(|(key, _)| key)
+                .collect();
+            sockets.sort_unstable();
+            commands.extend(sockets.into_iter().map(|device| {
+                let (room, device) = (room_key.to_string(), device.to_string());
+                if on {
+                    Command::TurnOn { room, device }
+                } else {
+                    Command::TurnOff { room, device }
+                }
+            }));
+        }
+        commands
+    }
+
+    /// Returns a text report of the rooms in the area.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::RoomNotFound`] for an [`Area::Room`] that does
+    /// not exist.  Floors and zones without rooms give an empty report.
+    pub fn report(&self, home: &SmartHome) -> Result<String, SmartHomeError> {
+        let rooms = self.rooms(home);
+        if let (Area::Room(key), []) = (self, rooms.as_slice()) {
+            return Err(SmartHomeError::RoomNotFound(key.clone()));
+        }
+        let sep = "=".repeat(50);
+        let mut s = format!(
+            "\n{}\n{} of '{}' ({} room(s), {} W):\n{}\n",
+            sep,
+            self.title(),
+            home.name(),
+            rooms.len(),
+            self.power(home),
+            sep
+        );
+        for (key, room) in rooms {
+            s.push_str(&format!("\n[Room: {}]\n", key));
+            s.push_str(&room.report());
+        }
+        s.push_str(&format!("\n{}\n", sep));
+        Ok(s)
+    }
+
+    fn title(&self) -> String {
+        match self {
+            Area::Home => "Whole home".to_string(),
+            Area::Floor(name) => format!("Floor '{}'", name),
+            Area::Zone(name) => format!("Zone '{}'", name),
+            Area::Room(key) => format!("Room '{}'", key),
+        }
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Area::Home => write!(f, "home"),
+            Area::Floor(name) => write!(f, "floor:{}", name),
+            Area::Zone(name) => write!(f, "zone:{}", name),
+            Area::Room(key) => write!(f, "room:{}", key),
+        }
+    }
+}
+
+impl FromStr for Area {
+    type Err = SmartHomeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let area = match s.split_once(':') {
+            None if s == "home" => Area::Home,
+            Some(("floor", name)) if !name.is_empty() => Area::Floor(name.to_string()),
+            Some(("zone", name)) if !name.is_empty() => Area::Zone(name.to_string()),
+            Some(("room", key)) if !key.is_empty() => Area::Room(key.to_string()),
+            _ => {
+                return Err(SmartHomeError::InvalidValue(format!(
+                    "'{}' is not an area; expected home, floor:<name>, zone:<name> or room:<key>",
+                    s
+                )))
+            }
+        };
+        Ok(area)
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("House");
+        let rooms = [
+            ("kitchen", "ground", &["heating_a"][..]),
+            ("living_room", "ground", &["heating_a", "heating_b"][..]),
+            ("bedroom", "upstairs", &["heating_b"][..]),
+        ];
+        for (key, floor, zones) in rooms {
+            let mut room = room!(
+                key,
+                "lamp" => Socket::new("Lamp", 60.0),
+                "heater" => Socket::new("Heater", 1000.0),
+                "sensor" => Thermometer::new("Sensor", 20.0),
+            );
+            room.set_floor(Some(floor.to_string()));
+            for zone in zones {
+                room.add_zone(*zone);
+            }
+            home.add_room(key, room).unwrap();
+        }
+        home
+    }
+
+    fn keys(area: &Area, home: &SmartHome) -> Vec<String> {
+        area.rooms(home)
+            .into_iter()
+            .map(|(key, _)| key.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_rooms_in_areas() {
+        let home = make_home();
+        assert_eq!(keys(&Area::Home, &home).len(), 3);
+        assert_eq!(
+            keys(&Area::Floor("ground".into()), &home),
+            ["kitchen", "living_room"]
+        );
+        assert_eq!(
+            keys(&Area::Zone("heating_b".into()), &home),
+            ["bedroom", "living_room"]
+        );
+        assert_eq!(keys(&Area::Room("bedroom".into()), &home), ["bedroom"]);
+        assert!(keys(&Area::Floor("attic".into()), &home).is_empty());
+    }
+
+    #[test]
+    fn test_switch_sockets_in_zone() {
+        let mut home = make_home();
+        let zone = Area::Zone("heating_a".into());
+        let commands = zone.switch_sockets(&home, true);
+        assert_eq!(commands.len(), 4);
+        home.transaction(|h| commands.into_iter().try_for_each(|c| c.apply(h).map(drop)))
+            .unwrap();
+
+        assert_eq!(zone.power(&home), 2120.0);
+        assert_eq!(Area::Floor("upstairs".into()).power(&home), 0.0);
+        assert_eq!(Area::Home.power(&home), 2120.0);
+    }
+
+    #[test]
+    fn test_report() {
+        let home = make_home();
+        let report = Area::Floor("upstairs".into()).report(&home).unwrap();
+        assert!(report.contains("Floor 'upstairs' of 'House' (1 room(s), 0 W)"));
+        assert!(report.contains("[Room: bedroom]"));
+        assert!(!report.contains("kitchen"));
+        assert!(matches!(
+            Area::Room("attic".into()).report(&home),
+            Err(SmartHomeError::RoomNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for text in ["home", "floor:upstairs", "zone:heating a", "room:kitchen"] {
+            assert_eq!(text.parse::<Area>().unwrap().to_string(), text);
+        }
+        for text in ["", "floor", "floor:", "wing:east"] {
+            assert!(matches!(
+                text.parse::<Area>(),
+                Err(SmartHomeError::InvalidValue(_))
+            ));
+        }
+    }
+}
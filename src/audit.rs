@@ -0,0 +1,592 @@
+//! An append-only log of who changed what and when.
+//!
+//! An [`AuditLog`] records every change together with the user who made it.
+//! Opened on a file, it appends one JSON line per change, in the same layout
+//! as trace records plus the actor:
+//!
+//! ```text
+//! {"at_ms": 1760756410250, "actor": "sam", "path": "kitchen/freezer", "event": "socket_switched", "room": "kitchen", "device": "freezer", "on": false, "power": 0.0}
+//! ```
+//!
+//! Entries are never changed or removed; an [`AuditQuery`] selects them by
+//! device path, user and time range.
+
+use crate::clock::{Clock, SystemClock};
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::shared::SharedHome;
+use crate::smart_home::SmartHome;
+use crate::trace::{format_timestamp, from_unix_millis, unix_millis};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    at_ms: u64,
+    actor: String,
+    path: String,
+    #[serde(flatten)]
+    event: HomeEvent,
+}
+
+/// One recorded change.
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    /// When the change was made.
+    pub at: SystemTime,
+    /// Name of the user who made it.
+    pub actor: String,
+    /// `"room/device"`, `"room"` or `"group:<name>"`; see [`HomeEvent::path`].
+    pub path: String,
+    /// What changed.
+    pub event: HomeEvent,
+}
+
+impl fmt::Display for AuditEntry {
+    /// Formats as `<timestamp> <actor> <path>: <change>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = match &self.event {
+            HomeEvent::RoomAdded { .. } => "room added".to_string(),
+            HomeEvent::RoomRemoved { .. } => "room removed".to_string(),
+            HomeEvent::DeviceAdded { .. } => "device added".to_string(),
+            HomeEvent::DeviceRemoved { .. } => "device removed".to_string(),
+            HomeEvent::RoomRenamed { to, .. } => format!("room renamed to '{}'", to),
+            HomeEvent::DeviceRenamed { to, .. } => format!("device renamed to '{}'", to),
+            HomeEvent::DeviceMoved {
+                to_room, to_device, ..
+            } => format!("device moved to {}/{}", to_room, to_device),
+            HomeEvent::FloorChanged { floor, .. } => match floor {
+                Some(floor) => format!("moved to floor '{}'", floor),
+                None => "moved to no floor".to_string(),
+            },
+            HomeEvent::ZoneChanged { zone, in_zone, .. } => {
+                let verb = if *in_zone { "added to" } else { "removed from" };
+                format!("{} zone '{}'", verb, zone)
+            }
+            HomeEvent::GroupAdded { .. } => "group added".to_string(),
+            HomeEvent::GroupRemoved { .. } => "group removed".to_string(),
+            HomeEvent::GroupMembershipChanged { group, member, .. } => {
+                let verb = if *member { "added to" } else { "removed from" };
+                format!("{} group '{}'", verb, group)
+            }
+            HomeEvent::TagChanged { tag, tagged, .. } => {
+                format!("tag '{}' {}", tag, if *tagged { "set" } else { "cleared" })
+            }
+            HomeEvent::AttributeChanged { key, value, .. } => match value {
+                Some(value) => format!("{} set to '{}'", key, value),
+                None => format!("{} removed", key),
+            },
+            HomeEvent::HomeReplaced { .. } => "home replaced".to_string(),
+            HomeEvent::SocketSwitched { on, .. } => {
+                format!("switched {}", if *on { "on" } else { "off" })
+            }
+            HomeEvent::TemperatureChanged { temperature, .. } => {
+                format!("temperature set to {:.1} °C", temperature)
+            }
+            HomeEvent::WirelessUpdated {
+                battery,
+                signal_dbm,
+                battery_low,
+                ..
+            } => {
+                let battery = battery.map_or("unknown".to_string(), |b| format!("{}%", b));
+                let signal = signal_dbm.map_or("unknown".to_string(), |s| format!("{} dBm", s));
+                let low = if *battery_low { " (low)" } else { "" };
+                format!("battery {}{}, signal {}", battery, low, signal)
+            }
+        };
+        write!(
+            f,
+            "{} {} {}: {}",
+            format_timestamp(self.at),
+            self.actor,
+            self.path,
+            change
+        )
+    }
+}
+
+/// Selects audit entries.  All criteria must match.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::audit::AuditQuery;
+///
+/// let freezer_changes = AuditQuery::new().path("kitchen/freezer");
+/// let by_sam_in_kitchen = AuditQuery::new().path("kitchen").actor("sam");
+/// # let _ = (freezer_changes, by_sam_in_kitchen);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditQuery {
+    path: Option<String>,
+    actor: Option<String>,
+    since: Option<SystemTime>,
+    until: Option<SystemTime>,
+}
+
+impl AuditQuery {
+    /// Creates a query matching every entry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only changes to the device at `path`, or to the room at `path` and
+    /// any of its devices.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Only changes made by `actor`.
+    pub fn actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    /// Only changes made at or after `time`.
+    pub fn since(mut self, time: SystemTime) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    /// Only changes made before `time`.
+    pub fn until(mut self, time: SystemTime) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    /// Returns `true` if `entry` matches all criteria.
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.path.as_deref().is_none_or(|path| {
+            entry
+                .path
+                .strip_prefix(path)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        }) && self
+            .actor
+            .as_deref()
+            .is_none_or(|actor| entry.actor == actor)
+            && self.since.is_none_or(|since| entry.at >= since)
+            && self.until.is_none_or(|until| entry.at < until)
+    }
+}
+
+/// Append-only record of changes and the users who made them.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::audit::{AuditLog, AuditQuery};
+/// use smart_home::{room, Command, ManualClock, SmartHome, Socket};
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("kitchen", room!("Kitchen", "freezer" => Socket::new("Freezer", 120.0)))?;
+/// let mut log = AuditLog::new(ManualClock::default());
+///
+/// let off = Command::TurnOff { room: "kitchen".into(), device: "freezer".into() };
+/// log.apply(&mut home, "sam", off)?;
+///
+/// let who = log.query(&AuditQuery::new().path("kitchen/freezer"));
+/// assert_eq!(who[0].actor, "sam");
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+pub struct AuditLog<C = SystemClock> {
+    clock: C,
+    entries: Vec<AuditEntry>,
+    file: Option<File>,
+}
+
+impl<C: Clock> AuditLog<C> {
+    /// Creates an empty log kept in memory only.
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            entries: Vec::new(),
+            file: None,
+        }
+    }
+
+    /// Opens the log file at `path`, reading the entries already in it and
+    /// appending new ones.  The file is created if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] naming a line that is not a
+    /// valid entry, or [`SmartHomeError::Io`] if the file cannot be read or
+    /// opened.
+    pub fn open(path: impl AsRef<Path>, clock: C) -> Result<Self, SmartHomeError> {
+        let path = path.as_ref();
+        let entries = match File::open(path) {
+            Ok(file) => read_entries(BufReader::new(file))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            clock,
+            entries,
+            file: Some(file),
+        })
+    }
+
+    /// Reads a log written by [`AuditLog::open`] into memory; new entries are
+    /// not written back.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuditLog::open`].
+    pub fn read(reader: impl BufRead, clock: C) -> Result<Self, SmartHomeError> {
+        Ok(Self {
+            entries: read_entries(reader)?,
+            ..Self::new(clock)
+        })
+    }
+
+    /// Records that `actor` made the change described by `event`, now.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if appending to the file fails; the entry
+    /// is then not recorded at all.
+    pub fn record(&mut self, actor: &str, event: &HomeEvent) -> Result<(), SmartHomeError> {
+        let entry = AuditEntry {
+            at: self.clock.now(),
+            actor: actor.to_string(),
+            path: event.path(),
+            event: event.clone(),
+        };
+        if let Some(file) = &mut self.file {
+            let record = Record {
+                at_ms: unix_millis(entry.at),
+                actor: entry.actor.clone(),
+                path: entry.path.clone(),
+                event: entry.event.clone(),
+            };
+            let mut line = serde_json::to_vec(&record)?;
+            line.push(b'\n');
+            file.write_all(&line)?;
+            file.flush()?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Applies `command` to `home` and records it as made by `actor`.
+    ///
+    /// The change is only kept if it was recorded, so the log never misses a
+    /// change made through it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`Command::apply`] or [`AuditLog::record`]; the
+    /// home is unchanged and nothing is recorded then.
+    pub fn apply(
+        &mut self,
+        home: &mut SmartHome,
+        actor: &str,
+        command: Command,
+    ) -> Result<HomeEvent, SmartHomeError> {
+        home.transaction(|staged| {
+            let event = command.apply(staged)?;
+            self.record(actor, &event)?;
+            Ok(event)
+        })
+    }
+
+    /// Executes `command` on `shared` and records it as made by `actor`
+    /// before the change is announced.
+    ///
+    /// # Errors
+    ///
+    /// As for [`AuditLog::apply`]; nothing is announced then.
+    pub fn execute(
+        &mut self,
+        shared: &SharedHome,
+        actor: &str,
+        command: Command,
+    ) -> Result<HomeEvent, SmartHomeError> {
+        shared.execute_checked(command, |event| self.record(actor, event))
+    }
+
+    /// Returns a handle to `shared` that records every change made through it,
+    /// including [`SharedHome::write`] and [`SharedHome::replace`], in `log` as
+    /// made by `actor`, e.g. the front-end using the
+    /// handle.  See [`SharedHome::audited`].
+    ///
+    /// The home is locked before the log, so do not hold the log's lock while
+    /// using the handle.
+    pub fn attach(
+        log: &Arc<Mutex<Self>>,
+        shared: &SharedHome,
+        actor: impl Into<String>,
+    ) -> SharedHome
+    where
+        C: Send + 'static,
+    {
+        let (log, actor) = (Arc::clone(log), actor.into());
+        shared.audited(move |event| {
+            log.lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .record(&actor, event)
+        })
+    }
+
+    /// Returns all entries, oldest first.
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Returns the entries matching `query`, oldest first.
+    pub fn query(&self, query: &AuditQuery) -> Vec<&AuditEntry> {
+        self.entries.iter().filter(|e| query.matches(e)).collect()
+    }
+}
+
+fn read_entries(reader: impl BufRead) -> Result<Vec<AuditEntry>, SmartHomeError> {
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Record = serde_json::from_str(&line).map_err(|e| {
+            SmartHomeError::InvalidValue(format!("audit log line {}: {}", index + 1, e))
+        })?;
+        entries.push(AuditEntry {
+            at: from_unix_millis(record.at_ms),
+            actor: record.actor,
+            path: record.path,
+            event: record.event,
+        });
+    }
+    Ok(entries)
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::{room, Room, Socket, Thermometer};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "freezer" => Socket::new("Freezer", 120.0),
+                "sensor" => Thermometer::new("Sensor", 21.0),
+            ),
+        )
+        .unwrap();
+        home
+    }
+
+    fn switch(on: bool) -> Command {
+        let (room, device) = ("kitchen".to_string(), "freezer".to_string());
+        if on {
+            Command::TurnOn { room, device }
+        } else {
+            Command::TurnOff { room, device }
+        }
+    }
+
+    fn temperature(value: f32) -> Command {
+        Command::SetTemperature {
+            room: "kitchen".into(),
+            device: "sensor".into(),
+            temperature: value,
+        }
+    }
+
+    fn paths(entries: &[&AuditEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|e| format!("{} {}", e.actor, e.path))
+            .collect()
+    }
+
+    #[test]
+    fn test_query_by_path_actor_and_time() {
+        let mut home = make_home();
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let mut log = AuditLog::new(clock.clone());
+        log.apply(&mut home, "olga", switch(true)).unwrap();
+        clock.advance(Duration::from_secs(60));
+        log.apply(&mut home, "sam", temperature(19.0)).unwrap();
+        clock.advance(Duration::from_secs(60));
+        log.apply(&mut home, "sam", switch(false)).unwrap();
+        let missing = Command::Toggle {
+            room: "kitchen".into(),
+            device: "oven".into(),
+        };
+        assert!(log.apply(&mut home, "sam", missing).is_err());
+
+        assert_eq!(log.entries().len(), 3);
+        let freezer = log.query(&AuditQuery::new().path("kitchen/freezer"));
+        assert_eq!(
+            paths(&freezer),
+            ["olga kitchen/freezer", "sam kitchen/freezer"]
+        );
+        let by_sam = log.query(&AuditQuery::new().path("kitchen").actor("sam"));
+        assert_eq!(
+            paths(&by_sam),
+            ["sam kitchen/sensor", "sam kitchen/freezer"]
+        );
+        assert!(log.query(&AuditQuery::new().path("kitch")).is_empty());
+
+        let minute = UNIX_EPOCH + Duration::from_secs(60);
+        let window = AuditQuery::new()
+            .since(minute)
+            .until(minute + Duration::from_secs(60));
+        assert_eq!(paths(&log.query(&window)), ["sam kitchen/sensor"]);
+        assert_eq!(
+            freezer[1].to_string(),
+            "1970-01-01T00:02:00.000Z sam kitchen/freezer: switched off"
+        );
+    }
+
+    #[test]
+    fn test_file_is_appended_and_read_back() {
+        let dir = std::env::temp_dir().join(format!("audit-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let mut home = make_home();
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1000));
+
+        let mut log = AuditLog::open(&path, clock.clone()).unwrap();
+        log.apply(&mut home, "olga", switch(true)).unwrap();
+        drop(log);
+        let mut log = AuditLog::open(&path, clock.clone()).unwrap();
+        log.apply(&mut home, "sam", switch(false)).unwrap();
+        drop(log);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.starts_with(
+            r#"{"at_ms":1000000,"actor":"olga","path":"kitchen/freezer","event":"socket_switched""#
+        ));
+        let log = AuditLog::read(text.as_bytes(), clock).unwrap();
+        assert_eq!(log.entries()[1].actor, "sam");
+        assert!(matches!(
+            log.entries()[1].event,
+            HomeEvent::SocketSwitched { on: false, .. }
+        ));
+
+        let error = AuditLog::read("\n{oops".as_bytes(), ManualClock::default()).err();
+        assert!(
+            matches!(error, Some(SmartHomeError::InvalidValue(m)) if m.starts_with("audit log line 2"))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_changes_that_cannot_be_recorded_are_rolled_back() {
+        let path = std::env::temp_dir().join(format!("audit-ro-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let mut log = AuditLog::new(ManualClock::default());
+        // Opened read-only, so every append fails.
+        log.file = Some(File::open(&path).unwrap());
+
+        let mut home = make_home();
+        let result = log.apply(&mut home, "sam", switch(true));
+        assert!(matches!(result, Err(SmartHomeError::Io(_))));
+        assert!(!home.get_socket("kitchen", "freezer").unwrap().is_on());
+
+        let shared = SharedHome::new(home);
+        let events = shared.subscribe();
+        assert!(log.execute(&shared, "sam", switch(true)).is_err());
+        assert!(!shared.read(|h| h.get_socket("kitchen", "freezer").unwrap().is_on()));
+        assert!(events.try_recv().is_err());
+        assert!(log.entries().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_attached_handles_record_their_actor() {
+        let shared = SharedHome::new(make_home());
+        let log = Arc::new(Mutex::new(AuditLog::new(ManualClock::default())));
+        let http = AuditLog::attach(&log, &shared, "http");
+        let mqtt = AuditLog::attach(&log, &shared, "mqtt");
+
+        http.execute(switch(true)).unwrap();
+        mqtt.execute_all([switch(false), temperature(18.0)])
+            .unwrap();
+        shared.execute(switch(true)).unwrap();
+        let missing = Command::Toggle {
+            room: "kitchen".into(),
+            device: "oven".into(),
+        };
+        assert!(http.execute(missing).is_err());
+
+        let log = log.lock().unwrap();
+        let entries: Vec<&AuditEntry> = log.entries().iter().collect();
+        assert_eq!(
+            paths(&entries),
+            [
+                "http kitchen/freezer",
+                "mqtt kitchen/freezer",
+                "mqtt kitchen/sensor"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attached_writes_and_replacements_are_recorded() {
+        let shared = SharedHome::new(make_home());
+        let log = Arc::new(Mutex::new(AuditLog::new(ManualClock::default())));
+        let tui = AuditLog::attach(&log, &shared, "tui");
+
+        tui.write(|h| h.get_socket_mut("kitchen", "freezer").map(|s| s.turn_on()))
+            .unwrap();
+        tui.replace(SmartHome::new("Other")).unwrap();
+        assert_eq!(
+            paths(&log.lock().unwrap().entries().iter().collect::<Vec<_>>()),
+            ["tui home", "tui home"]
+        );
+
+        // Opened read-only, so every append fails and the change is undone.
+        let path = std::env::temp_dir().join(format!("audit-ro-w-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        log.lock().unwrap().file = Some(File::open(&path).unwrap());
+        let events = shared.subscribe();
+        assert!(tui
+            .write(|h| h.add_room("attic", Room::new("Attic")))
+            .is_err());
+        assert!(tui.replace(make_home()).is_err());
+        assert_eq!(shared.read(|h| h.name().to_string()), "Other");
+        assert!(events.try_recv().is_err());
+        assert_eq!(log.lock().unwrap().entries().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_execute_on_shared_home() {
+        let shared = SharedHome::new(make_home());
+        let mut log = AuditLog::new(ManualClock::default());
+        log.execute(
+            &shared,
+            "ivan",
+            Command::RemoveRoom {
+                room: "kitchen".into(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            paths(&log.query(&AuditQuery::new().actor("ivan"))),
+            ["ivan kitchen"]
+        );
+        assert_eq!(shared.read(|home| home.room_count()), 0);
+    }
+}
@@ -0,0 +1,600 @@
+//! Device availability from heartbeats.
+//!
+//! An [`AvailabilityTracker`] remembers when each device was last heard from,
+//! either through a change it caused (see [`AvailabilityTracker::observe`]) or
+//! an explicit heartbeat ([`AvailabilityTracker::seen`]), and classifies it as
+//! [`Availability::Online`], [`Availability::Stale`] or
+//! [`Availability::Offline`] from configurable [`Timeouts`].  Status changes
+//! are returned as [`AvailabilityEvent`]s.
+//!
+//! A thermometer keeps its last reading when its sensor goes quiet; use
+//! [`AvailabilityTracker::online_temperature`] where an old reading must not be
+//! mistaken for a current one.
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::report::Report;
+use crate::room::Room;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use crate::trace::format_timestamp;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// Whether a device has been heard from recently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Availability {
+    /// Heard from within the stale timeout.
+    Online,
+    /// Quiet for longer than the stale timeout; its state may be outdated.
+    Stale,
+    /// Quiet for longer than the offline timeout.
+    Offline,
+}
+
+impl fmt::Display for Availability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Availability::Online => "online",
+            Availability::Stale => "stale",
+            Availability::Offline => "offline",
+        })
+    }
+}
+
+/// How long a device may stay quiet before it counts as stale or offline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    stale_after: Duration,
+    offline_after: Duration,
+}
+
+impl Timeouts {
+    /// Creates timeouts; a device quiet for `stale_after` is stale, and one
+    /// quiet for `offline_after` is offline.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if `stale_after` is zero or
+    /// longer than `offline_after`.
+    pub fn new(stale_after: Duration, offline_after: Duration) -> Result<Self, SmartHomeError> {
+        if stale_after.is_zero() || stale_after > offline_after {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "stale timeout {:?} must be non-zero and at most the offline timeout {:?}",
+                stale_after, offline_after
+            )));
+        }
+        Ok(Self {
+            stale_after,
+            offline_after,
+        })
+    }
+
+    /// Returns the stale timeout.
+    pub fn stale_after(&self) -> Duration {
+        self.stale_after
+    }
+
+    /// Returns the offline timeout.
+    pub fn offline_after(&self) -> Duration {
+        self.offline_after
+    }
+
+    fn classify(&self, quiet_for: Duration) -> Availability {
+        if quiet_for >= self.offline_after {
+            Availability::Offline
+        } else if quiet_for >= self.stale_after {
+            Availability::Stale
+        } else {
+            Availability::Online
+        }
+    }
+}
+
+impl Default for Timeouts {
+    /// Stale after 5 minutes, offline after 15.
+    fn default() -> Self {
+        Self {
+            stale_after: Duration::from_secs(5 * 60),
+            offline_after: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+/// A device's availability changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvailabilityEvent {
+    /// Room key.
+    pub room: String,
+    /// Device key.
+    pub device: String,
+    /// The new status.
+    pub status: Availability,
+    /// When the device was last heard from.
+    pub last_seen: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    last_seen: SystemTime,
+    status: Availability,
+    timeouts: Option<Timeouts>,
+}
+
+/// Tracks when devices were last heard from.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::availability::{Availability, AvailabilityTracker};
+/// use smart_home::{room, ManualClock, SmartHome, Thermometer};
+/// use std::time::Duration;
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "sensor" => Thermometer::new("Sensor", 21.0)))?;
+/// let clock = ManualClock::default();
+/// let mut tracker = AvailabilityTracker::new(clock.clone());
+/// tracker.seen("hall", "sensor");
+///
+/// clock.advance(Duration::from_secs(10 * 60));
+/// let changes = tracker.check();
+/// assert_eq!(changes[0].status, Availability::Stale);
+/// assert_eq!(tracker.online_temperature(&home, "hall", "sensor")?, None);
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+pub struct AvailabilityTracker<C = SystemClock> {
+    clock: C,
+    timeouts: Timeouts,
+    devices: BTreeMap<(String, String), Entry>,
+}
+
+impl<C: Clock> AvailabilityTracker<C> {
+    /// Creates a tracker with the [default timeouts][Timeouts::default].
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            timeouts: Timeouts::default(),
+            devices: BTreeMap::new(),
+        }
+    }
+
+    /// Uses `timeouts` for devices without their own.
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Uses `timeouts` for one device, e.g. a sensor that reports every few
+    /// seconds.  The device counts as seen now if it was not tracked yet.
+    pub fn set_timeouts(&mut self, room: &str, device: &str, timeouts: Timeouts) {
+        let now = self.clock.now();
+        self.devices
+            .entry((room.to_string(), device.to_string()))
+            .or_insert(Entry {
+                last_seen: now,
+                status: Availability::Online,
+                timeouts: None,
+            })
+            .timeouts = Some(timeouts);
+    }
+
+    /// Starts tracking every device in `home` as seen now; events are in room
+    /// and device order.
+    pub fn track_all(&mut self, home: &SmartHome) -> Vec<AvailabilityEvent> {
+        let mut keys: Vec<(String, String)> = home
+            .rooms()
+            .flat_map(|(room_key, room)| {
+                room.devices()
+                    .map(move |(device_key, _)| (room_key.to_string(), device_key.to_string()))
+            })
+            .collect();
+        keys.sort();
+        keys.iter()
+            .filter_map(|(room, device)| self.seen(room, device))
+            .collect()
+    }
+
+    /// Records a heartbeat from a device.
+    ///
+    /// Returns an event if the device was not online before, including when it
+    /// is seen for the first time.
+    pub fn seen(&mut self, room: &str, device: &str) -> Option<AvailabilityEvent> {
+        let now = self.clock.now();
+        let key = (room.to_string(), device.to_string());
+        let previous = self.devices.get(&key).map(|entry| entry.status);
+        let entry = self.devices.entry(key).or_insert(Entry {
+            last_seen: now,
+            status: Availability::Online,
+            timeouts: None,
+        });
+        entry.last_seen = now;
+        entry.status = Availability::Online;
+        (previous != Some(Availability::Online)).then(|| AvailabilityEvent {
+            room: room.to_string(),
+            device: device.to_string(),
+            status: Availability::Online,
+            last_seen: now,
+        })
+    }
+
+    /// Updates the tracker from a change in the home: devices that changed
+    /// or were added count as seen, renamed and moved ones keep their history
+    /// under the new key, and removed ones are forgotten.
+    pub fn observe(&mut self, event: &HomeEvent) -> Vec<AvailabilityEvent> {
+        match event {
+            HomeEvent::RoomAdded { room, data } => {
+                let mut devices: Vec<String> = data
+                    .devices()
+                    .map(|(device, _)| device.to_string())
+                    .collect();
+                devices.sort();
+                devices
+                    .iter()
+                    .filter_map(|device| self.seen(room, device))
+                    .collect()
+            }
+            HomeEvent::RoomRemoved { room } => {
+                self.devices.retain(|(r, _), _| r != room);
+                Vec::new()
+            }
+            HomeEvent::DeviceRemoved { room, device } => {
+                self.devices.remove(&(room.clone(), device.clone()));
+                Vec::new()
+            }
+            HomeEvent::RoomRenamed { room, to } => {
+                let keys: Vec<(String, String)> = self
+                    .devices
+                    .keys()
+                    .filter(|(r, _)| r == room)
+                    .cloned()
+                    .collect();
+                for (r, device) in keys {
+                    self.rekey((r, device.clone()), (to.clone(), device));
+                }
+                Vec::new()
+            }
+            HomeEvent::DeviceRenamed { room, device, to } => {
+                self.rekey((room.clone(), device.clone()), (room.clone(), to.clone()));
+                Vec::new()
+            }
+            HomeEvent::DeviceMoved {
+                room,
+                device,
+                to_room,
+                to_device,
+            } => {
+                self.rekey(
+                    (room.clone(), device.clone()),
+                    (to_room.clone(), to_device.clone()),
+                );
+                Vec::new()
+            }
+            HomeEvent::HomeReplaced { data } => {
+                self.devices
+                    .retain(|(room, device), _| data.get_device(room, device).is_ok());
+                let mut added: Vec<(&str, &str)> = data
+                    .rooms()
+                    .flat_map(|(room, r)| r.devices().map(move |(device, _)| (room, device)))
+                    .filter(|&(room, device)| {
+                        !self
+                            .devices
+                            .contains_key(&(room.to_string(), device.to_string()))
+                    })
+                    .collect();
+                added.sort();
+                added
+                    .into_iter()
+                    .filter_map(|(room, device)| self.seen(room, device))
+                    .collect()
+            }
+            HomeEvent::FloorChanged { .. }
+            | HomeEvent::ZoneChanged { .. }
+            | HomeEvent::GroupAdded { .. }
+            | HomeEvent::GroupRemoved { .. }
+            | HomeEvent::GroupMembershipChanged { .. }
+            | HomeEvent::TagChanged { .. }
+            | HomeEvent::AttributeChanged { .. } => Vec::new(),
+            HomeEvent::DeviceAdded { room, device, .. }
+            | HomeEvent::SocketSwitched { room, device, .. }
+            | HomeEvent::TemperatureChanged { room, device, .. }
+            | HomeEvent::WirelessUpdated { room, device, .. } => {
+                self.seen(room, device).into_iter().collect()
+            }
+        }
+    }
+
+    fn rekey(&mut self, from: (String, String), to: (String, String)) {
+        if let Some(entry) = self.devices.remove(&from) {
+            self.devices.insert(to, entry);
+        }
+    }
+
+    /// Re-evaluates every device at the current time and returns those whose
+    /// status changed, in room and device order.  Call this periodically.
+    pub fn check(&mut self) -> Vec<AvailabilityEvent> {
+        let now = self.clock.now();
+        let default = self.timeouts;
+        let mut changes = Vec::new();
+        for ((room, device), entry) in &mut self.devices {
+            let quiet_for = now.duration_since(entry.last_seen).unwrap_or_default();
+            let status = entry.timeouts.unwrap_or(default).classify(quiet_for);
+            if status != entry.status {
+                entry.status = status;
+                changes.push(AvailabilityEvent {
+                    room: room.clone(),
+                    device: device.clone(),
+                    status,
+                    last_seen: entry.last_seen,
+                });
+            }
+        }
+        changes
+    }
+
+    /// Returns the current status of a device, or `None` if it was never seen.
+    pub fn status(&self, room: &str, device: &str) -> Option<Availability> {
+        let entry = self.entry(room, device)?;
+        let quiet_for = self.clock.since(entry.last_seen);
+        Some(entry.timeouts.unwrap_or(self.timeouts).classify(quiet_for))
+    }
+
+    /// Returns when a device was last heard from, or `None` if never.
+    pub fn last_seen(&self, room: &str, device: &str) -> Option<SystemTime> {
+        self.entry(room, device).map(|entry| entry.last_seen)
+    }
+
+    /// Returns a thermometer's reading if the thermometer is online, and
+    /// `None` if its reading may be outdated.
+    ///
+    /// # Errors
+    ///
+    /// As for [`SmartHome::get_thermometer`].
+    pub fn online_temperature(
+        &self,
+        home: &SmartHome,
+        room: &str,
+        device: &str,
+    ) -> Result<Option<f32>, SmartHomeError> {
+        let temperature = home.get_thermometer(room, device)?.temperature();
+        Ok((self.status(room, device) == Some(Availability::Online)).then_some(temperature))
+    }
+
+    /// Returns the device's report, marked e.g. `[STALE, last seen ...]` if the
+    /// device is stale or offline.
+    pub fn device_report(&self, room: &str, device_key: &str, device: &SmartDevice) -> String {
+        format!("{}{}", device.report(), self.note(room, device_key))
+    }
+
+    /// Returns the room's report with its stale and offline devices marked.
+    pub fn room_report(&self, room_key: &str, room: &Room) -> String {
+        room.report_with(|device| self.note(room_key, device))
+    }
+
+    /// Returns the home's report with its stale and offline devices marked.
+    pub fn home_report(&self, home: &SmartHome) -> String {
+        home.report_with(|room, device| self.note(room, device))
+    }
+
+    /// Returns `" [STALE, last seen <time>]"` or the same for offline
+    /// devices, and nothing for online or untracked ones.
+    fn note(&self, room: &str, device: &str) -> String {
+        match (self.status(room, device), self.last_seen(room, device)) {
+            (Some(status @ (Availability::Stale | Availability::Offline)), Some(last_seen)) => {
+                format!(
+                    " [{}, last seen {}]",
+                    status.to_string().to_uppercase(),
+                    format_timestamp(last_seen)
+                )
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn entry(&self, room: &str, device: &str) -> Option<&Entry> {
+        self.devices.get(&(room.to_string(), device.to_string()))
+    }
+}
+
+impl<C: Clock> Report for AvailabilityTracker<C> {
+    /// Lists every tracked device with its status and when it was last seen.
+    fn report(&self) -> String {
+        let mut s = format!("Availability ({} device(s)):\n", self.devices.len());
+        for (room, device) in self.devices.keys() {
+            let last_seen = self.last_seen(room, device).expect("key is tracked");
+            let status = self.status(room, device).expect("key is tracked");
+            s.push_str(&format!(
+                "  [{}/{}] {}, last seen {} ({} s ago)\n",
+                room,
+                device,
+                status,
+                format_timestamp(last_seen),
+                self.clock.since(last_seen).as_secs()
+            ));
+        }
+        s
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::command::Command;
+    use crate::{room, Socket, Thermometer};
+    use std::time::UNIX_EPOCH;
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "kettle" => Socket::new("Kettle", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 22.0),
+            ),
+        )
+        .unwrap();
+        home
+    }
+
+    fn statuses(events: &[AvailabilityEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|e| format!("{}/{} {}", e.room, e.device, e.status))
+            .collect()
+    }
+
+    fn minutes(n: u64) -> Duration {
+        Duration::from_secs(n * 60)
+    }
+
+    #[test]
+    fn test_devices_go_stale_then_offline() {
+        let home = make_home();
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let mut tracker = AvailabilityTracker::new(clock.clone());
+        assert_eq!(tracker.status("kitchen", "sensor"), None);
+        assert_eq!(
+            statuses(&tracker.track_all(&home)),
+            ["kitchen/kettle online", "kitchen/sensor online"]
+        );
+        assert!(tracker.check().is_empty());
+
+        clock.advance(minutes(4));
+        tracker.seen("kitchen", "kettle");
+        clock.advance(minutes(2));
+        assert_eq!(statuses(&tracker.check()), ["kitchen/sensor stale"]);
+        assert_eq!(
+            tracker
+                .online_temperature(&home, "kitchen", "sensor")
+                .unwrap(),
+            None
+        );
+
+        clock.advance(minutes(10));
+        assert_eq!(
+            statuses(&tracker.check()),
+            ["kitchen/kettle stale", "kitchen/sensor offline"]
+        );
+        assert!(tracker.check().is_empty());
+
+        let back = tracker.seen("kitchen", "sensor").unwrap();
+        assert_eq!(back.status, Availability::Online);
+        assert_eq!(back.last_seen, UNIX_EPOCH + minutes(16));
+        assert_eq!(
+            tracker
+                .online_temperature(&home, "kitchen", "sensor")
+                .unwrap(),
+            Some(22.0)
+        );
+        assert!(tracker.seen("kitchen", "sensor").is_none());
+    }
+
+    #[test]
+    fn test_per_device_timeouts() {
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let fast = Timeouts::new(Duration::from_secs(30), Duration::from_secs(90)).unwrap();
+        let mut tracker = AvailabilityTracker::new(clock.clone())
+            .with_timeouts(Timeouts::new(minutes(60), minutes(120)).unwrap());
+        tracker.seen("kitchen", "kettle");
+        tracker.set_timeouts("kitchen", "sensor", fast);
+
+        clock.advance(Duration::from_secs(100));
+        assert_eq!(statuses(&tracker.check()), ["kitchen/sensor offline"]);
+        assert_eq!(
+            tracker.status("kitchen", "kettle"),
+            Some(Availability::Online)
+        );
+
+        assert!(Timeouts::new(minutes(10), minutes(5)).is_err());
+        assert!(Timeouts::new(Duration::ZERO, minutes(5)).is_err());
+    }
+
+    #[test]
+    fn test_observes_home_events() {
+        let mut home = make_home();
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let mut tracker = AvailabilityTracker::new(clock.clone());
+        let reading = Command::SetTemperature {
+            room: "kitchen".into(),
+            device: "sensor".into(),
+            temperature: 19.5,
+        };
+        let event = reading.apply(&mut home).unwrap();
+        assert_eq!(
+            statuses(&tracker.observe(&event)),
+            ["kitchen/sensor online"]
+        );
+
+        clock.advance(minutes(6));
+        tracker.check();
+        assert_eq!(
+            statuses(&tracker.observe(&event)),
+            ["kitchen/sensor online"]
+        );
+
+        let renamed = Command::RenameRoom {
+            room: "kitchen".into(),
+            to: "galley".into(),
+        };
+        tracker.observe(&renamed.apply(&mut home).unwrap());
+        let moved = Command::RenameDevice {
+            room: "galley".into(),
+            device: "sensor".into(),
+            to: "thermo".into(),
+        };
+        tracker.observe(&moved.apply(&mut home).unwrap());
+        assert_eq!(tracker.last_seen("kitchen", "sensor"), None);
+        assert_eq!(
+            tracker.last_seen("galley", "thermo"),
+            Some(UNIX_EPOCH + minutes(6))
+        );
+
+        let removed = Command::RemoveRoom {
+            room: "galley".into(),
+        };
+        tracker.observe(&removed.apply(&mut home).unwrap());
+        assert_eq!(tracker.last_seen("galley", "thermo"), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let mut tracker = AvailabilityTracker::new(clock.clone());
+        tracker.seen("kitchen", "sensor");
+        clock.advance(minutes(7));
+        assert_eq!(
+            tracker.report(),
+            "Availability (1 device(s)):\n  [kitchen/sensor] stale, last seen 1970-01-01T00:00:00.000Z (420 s ago)\n"
+        );
+    }
+
+    #[test]
+    fn test_reports_mark_quiet_devices() {
+        let home = make_home();
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let mut tracker = AvailabilityTracker::new(clock.clone());
+        tracker.track_all(&home);
+        clock.advance(minutes(20));
+        tracker.seen("kitchen", "kettle");
+
+        let sensor = home.get_device("kitchen", "sensor").unwrap();
+        assert_eq!(
+            tracker.device_report("kitchen", "sensor", sensor),
+            "Thermometer 'Sensor': 22 °C [OFFLINE, last seen 1970-01-01T00:00:00.000Z]"
+        );
+        let room = tracker.room_report("kitchen", home.get_room("kitchen").unwrap());
+        assert!(room.contains("  [kettle] Socket 'Kettle': off (power: 0 W)\n"));
+        assert!(room.contains("22 °C [OFFLINE, last seen"));
+        assert!(tracker
+            .home_report(&home)
+            .contains("22 °C [OFFLINE, last seen"));
+        assert!(!home.report().contains("OFFLINE"));
+    }
+}
@@ -0,0 +1,241 @@
+//! `smart-home-server` — serves a home file over the REST API.
+//!
+//! ```text
+//! smart-home-server <home.json> [--addr <host:port>] [--ws-addr <host:port>]
+//!                   [--mqtt <host:port>] [--remote <host:port>] [--trace <trace.jsonl>]
+//!                   [--audit <audit.jsonl>]
+//! ```
+//!
+//! The file is created if it does not exist and is saved after every change,
+//! whichever front-end made it.  With the `ws` feature, `--ws-addr` also starts
+//! the WebSocket push channel; with the `mqtt` feature, `--mqtt` bridges the
+//! home to an MQTT broker and announces its devices to Home Assistant.  With
+//! the `remote` feature, `--remote` accepts authenticated remote commands (see
+//! `smart_home::remote`) using the hex key in `SMART_HOME_REMOTE_KEY`.
+//! `--trace` records every change to a trace file (see `smart_home::trace`).
+//! `--audit` appends every change to an audit log (see `smart_home::audit`)
+//! with the front-end that made it (`http`, `ws`, `mqtt` or `remote`) as the
+//! actor.
+
+use smart_home::audit::AuditLog;
+use smart_home::http::HttpServer;
+use smart_home::trace::Recorder;
+use smart_home::{storage, SharedHome, SmartHome, SmartHomeError, SystemClock};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: smart-home-server <home.json> [--addr <host:port>] \
+                     [--ws-addr <host:port>] [--mqtt <host:port>] [--remote <host:port>] \
+                     [--trace <trace.jsonl>] [--audit <audit.jsonl>]";
+const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next().map(PathBuf::from) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut ws_addr = None;
+    let mut mqtt_addr = None;
+    let mut remote_addr = None;
+    let mut trace_path = None;
+    let mut audit_path = None;
+    while let Some(flag) = args.next() {
+        match (flag.as_str(), args.next()) {
+            ("--addr", Some(value)) => addr = value,
+            ("--ws-addr", Some(value)) => ws_addr = Some(value),
+            ("--mqtt", Some(value)) => mqtt_addr = Some(value),
+            ("--remote", Some(value)) => remote_addr = Some(value),
+            ("--trace", Some(value)) => trace_path = Some(PathBuf::from(value)),
+            ("--audit", Some(value)) => audit_path = Some(PathBuf::from(value)),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let home = if path.exists() {
+        match storage::load(&path) {
+            Ok(home) => home,
+            Err(e) => {
+                eprintln!("error: {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        SmartHome::new("Smart Home")
+    };
+
+    let shared = SharedHome::new(home);
+
+    let audit = match audit_path {
+        Some(audit_path) => match AuditLog::open(&audit_path, SystemClock) {
+            Ok(log) => {
+                println!("Auditing changes to {}", audit_path.display());
+                Some(Arc::new(Mutex::new(log)))
+            }
+            Err(e) => {
+                eprintln!("error: {}: {}", audit_path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    // Each front-end gets its own handle so the log names who made a change.
+    let front_end = |actor: &str| match &audit {
+        Some(log) => AuditLog::attach(log, &shared, actor),
+        None => shared.clone(),
+    };
+
+    #[cfg(feature = "ws")]
+    let _ws = match ws_addr {
+        Some(ws_addr) => match smart_home::ws::WsServer::bind(ws_addr.as_str(), front_end("ws")) {
+            Ok(ws) => {
+                println!("WebSocket on ws://{}", ws.local_addr());
+                Some(ws.spawn())
+            }
+            Err(e) => {
+                eprintln!("error: cannot listen on {}: {}", ws_addr, e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    #[cfg(not(feature = "ws"))]
+    if ws_addr.is_some() {
+        eprintln!("error: --ws-addr requires the `ws` feature");
+        return ExitCode::from(2);
+    }
+
+    #[cfg(feature = "mqtt")]
+    if let Some(mqtt_addr) = mqtt_addr {
+        if let Err(e) = spawn_mqtt_bridge(&mqtt_addr, front_end("mqtt")) {
+            eprintln!("error: MQTT broker {}: {}", mqtt_addr, e);
+            return ExitCode::FAILURE;
+        }
+        println!("Bridging to MQTT broker {}", mqtt_addr);
+    }
+    #[cfg(not(feature = "mqtt"))]
+    if mqtt_addr.is_some() {
+        eprintln!("error: --mqtt requires the `mqtt` feature");
+        return ExitCode::from(2);
+    }
+
+    #[cfg(feature = "remote")]
+    let _remote = match remote_addr {
+        Some(remote_addr) => match spawn_remote_server(&remote_addr, front_end("remote")) {
+            Ok(remote) => {
+                println!("Remote control on {}", remote.local_addr());
+                Some(remote)
+            }
+            Err(e) => {
+                eprintln!("error: --remote {}: {}", remote_addr, e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    #[cfg(not(feature = "remote"))]
+    if remote_addr.is_some() {
+        eprintln!("error: --remote requires the `remote` feature");
+        return ExitCode::from(2);
+    }
+
+    if let Some(trace_path) = trace_path {
+        let recorder = File::create(&trace_path)
+            .map_err(SmartHomeError::from)
+            .and_then(|f| Recorder::start(&shared, SystemClock, BufWriter::new(f)));
+        let mut recorder = match recorder {
+            Ok(recorder) => recorder,
+            Err(e) => {
+                eprintln!("error: {}: {}", trace_path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        };
+        println!("Recording changes to {}", trace_path.display());
+        thread::spawn(move || loop {
+            if let Err(e) = recorder.wait(Duration::from_secs(1)) {
+                eprintln!("error: writing trace {}: {}", trace_path.display(), e);
+                return;
+            }
+        });
+    }
+
+    // Save on every announced change, so edits over WebSocket or MQTT are kept
+    // as well as those made over HTTP.
+    let events = shared.subscribe();
+    let saved = shared.clone();
+    thread::spawn(move || {
+        for _ in events {
+            if let Err(e) = storage::save(&saved.snapshot(), &path) {
+                eprintln!("error: saving {}: {}", path.display(), e);
+            }
+        }
+    });
+
+    let server = match HttpServer::bind(addr.as_str(), front_end("http")) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("error: cannot listen on {}: {}", addr, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Serving on http://{}", server.local_addr());
+    server.run();
+    ExitCode::SUCCESS
+}
+
+/// Starts the remote control server with the key from `SMART_HOME_REMOTE_KEY`.
+#[cfg(feature = "remote")]
+fn spawn_remote_server(
+    addr: &str,
+    shared: SharedHome,
+) -> Result<smart_home::remote::RemoteHandle, String> {
+    use smart_home::remote::{PresharedKey, RemoteServer};
+
+    let key: PresharedKey = std::env::var("SMART_HOME_REMOTE_KEY")
+        .map_err(|_| "SMART_HOME_REMOTE_KEY is not set")?
+        .parse()
+        .map_err(|e: SmartHomeError| e.to_string())?;
+    let server = RemoteServer::bind(addr, shared, key).map_err(|e| e.to_string())?;
+    Ok(server.spawn())
+}
+
+/// Connects to the broker at `addr` and runs the bridge on a background thread.
+#[cfg(feature = "mqtt")]
+fn spawn_mqtt_bridge(addr: &str, shared: SharedHome) -> Result<(), String> {
+    use smart_home::availability::AvailabilityTracker;
+    use smart_home::homeassistant::Discovery;
+    use smart_home::mqtt::{availability_will, MqttBridge, RumqttTransport, DEFAULT_PREFIX};
+    use smart_home::SystemClock;
+
+    let (host, port) = addr
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+        .ok_or("expected <host:port>")?;
+    let will = availability_will(DEFAULT_PREFIX);
+    let transport = RumqttTransport::connect("smart-home-server", host, port, Some(will));
+    let mut bridge =
+        MqttBridge::start(transport, shared, DEFAULT_PREFIX).map_err(|e| e.to_string())?;
+    bridge
+        .enable_discovery(Discovery::default())
+        .map_err(|e| e.to_string())?;
+    let mut bridge = bridge
+        .enable_availability(AvailabilityTracker::new(SystemClock))
+        .map_err(|e| e.to_string())?;
+    thread::spawn(move || loop {
+        if let Err(e) = bridge.poll(Duration::from_millis(100)) {
+            eprintln!("error: MQTT: {}", e);
+            // The client reconnects on the next poll.
+            thread::sleep(Duration::from_secs(1));
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! `smart-home-tui` — live terminal dashboard for a home file or server.
+//!
+//! ```text
+//! smart-home-tui <home.json> [--refresh-ms <ms>]
+//! smart-home-tui --http <host:port> [--refresh-ms <ms>]
+//! ```
+//!
+//! The file is re-read whenever it changes on disk (for example after
+//! `smart-home --file home.json on kitchen kettle`), and socket toggles made in
+//! the dashboard are written back to it.
+//!
+//! With `--http` the dashboard shows the home of a `smart-home-server` instead:
+//! it fetches `GET /` every refresh and sends socket toggles as `POST /batch`.
+
+use smart_home::{storage, tui, SharedHome, SmartHome};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const USAGE: &str = "Usage: smart-home-tui (<home.json> | --http <host:port>) [--refresh-ms <ms>]";
+
+/// How long a request to the server may take before it is abandoned.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the dashboard gets the home from.
+enum Source {
+    File(PathBuf),
+    Http(String),
+}
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut source = None;
+    let mut refresh = Duration::from_millis(500);
+    while !args.is_empty() {
+        match args[0].as_str() {
+            "--http" if args.len() >= 2 => source = Some(Source::Http(args.remove(1))),
+            "--refresh-ms" if args.len() >= 2 => match args.remove(1).parse() {
+                Ok(ms) => refresh = Duration::from_millis(ms),
+                Err(_) => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            "--http" | "--refresh-ms" => {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
+            }
+            path if source.is_none() && !path.starts_with('-') => {
+                source = Some(Source::File(PathBuf::from(path)))
+            }
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
+            }
+        }
+        args.remove(0);
+    }
+
+    let result = match source {
+        Some(Source::File(path)) => run_file(&path, refresh),
+        Some(Source::Http(addr)) => run_http(&addr, refresh),
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Shows the home in `path`, following changes on disk and saving toggles.
+fn run_file(path: &Path, refresh: Duration) -> Result<(), String> {
+    let home = storage::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let shared = SharedHome::new(home);
+    let last_seen = Arc::new(Mutex::new(modified(path)));
+
+    spawn_file_watcher(
+        path.to_path_buf(),
+        shared.clone(),
+        Arc::clone(&last_seen),
+        refresh,
+    );
+
+    let mut terminal = ratatui::init();
+    let result = tui::run(&mut terminal, &shared, refresh, |home, _| {
+        storage::save(home, path).map_err(io::Error::other)?;
+        *last_seen.lock().unwrap() = modified(path);
+        Ok(())
+    });
+    ratatui::restore();
+    result.map_err(|e| e.to_string())
+}
+
+/// Reloads the home into `shared` whenever the file's modification time changes.
+fn spawn_file_watcher(
+    path: PathBuf,
+    shared: SharedHome,
+    last_seen: Arc<Mutex<Option<SystemTime>>>,
+    interval: Duration,
+) {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let current = modified(&path);
+        if current == *last_seen.lock().unwrap() {
+            continue;
+        }
+        // A half-written file fails to parse; keep the old state and retry on
+        // the next tick.  The `last_seen` guard is not held during `replace`,
+        // which waits for the home lock.
+        let Ok(home) = storage::load(&path) else {
+            continue;
+        };
+        if shared.replace(home).is_ok() {
+            *last_seen.lock().unwrap() = current;
+        }
+    });
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
+
+/// Shows the home served at `addr`, polling it and forwarding toggles.
+fn run_http(addr: &str, refresh: Duration) -> Result<(), String> {
+    let home = fetch(addr).map_err(|e| format!("{}: {}", addr, e))?;
+    let shared = SharedHome::new(home);
+
+    spawn_poller(addr.to_string(), shared.clone(), refresh);
+
+    let mut terminal = ratatui::init();
+    let result = tui::run(&mut terminal, &shared, refresh, |_, command| {
+        let body = serde_json::to_string(&[command])?;
+        http_request(addr, "POST", "/batch", &body).map(drop)
+    });
+    ratatui::restore();
+    result.map_err(|e| e.to_string())
+}
+
+/// Refreshes `shared` from the server every `interval`.
+fn spawn_poller(addr: String, shared: SharedHome, interval: Duration) {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        // An unreachable server leaves the last state on screen; the next
+        // toggle reports the error.
+        let _ = refresh(&addr, &shared);
+    });
+}
+
+/// Fetches the server's home and replaces the one in `shared` if it differs,
+/// so subscribers only hear about real changes.  Returns whether it did.
+fn refresh(addr: &str, shared: &SharedHome) -> io::Result<bool> {
+    let home = fetch(addr)?;
+    if shared.read(|current| *current == home) {
+        return Ok(false);
+    }
+    shared.replace(home).map_err(io::Error::other)?;
+    Ok(true)
+}
+
+fn fetch(addr: &str) -> io::Result<SmartHome> {
+    let body = http_request(addr, "GET", "/", "")?;
+    storage::from_json(&body).map_err(io::Error::other)
+}
+
+/// Sends one HTTP/1.0 request and returns the body of a `2xx` response.
+fn http_request(addr: &str, method: &str, path: &str, body: &str) -> io::Result<String> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+    write!(
+        stream,
+        "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        addr,
+        body.len(),
+        body
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status_line = head.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if status.len() == 3 && status.starts_with('2') {
+        Ok(body.to_string())
+    } else {
+        Err(io::Error::other(format!(
+            "server answered '{}': {}",
+            status_line, body
+        )))
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use smart_home::http::HttpServer;
+    use smart_home::{room, Command, Socket};
+
+    fn make_shared() -> SharedHome {
+        let mut home = SmartHome::new("Home");
+        home.add_room(
+            "kitchen",
+            room!("Kitchen", "kettle" => Socket::new("Kettle", 2000.0)),
+        )
+        .unwrap();
+        SharedHome::new(home)
+    }
+
+    #[test]
+    fn test_fetch_and_refresh_follow_the_server() {
+        let served = make_shared();
+        let server = HttpServer::bind("127.0.0.1:0", served.clone())
+            .unwrap()
+            .spawn();
+        let addr = server.local_addr().to_string();
+
+        let home = fetch(&addr).unwrap();
+        assert_eq!(home, served.snapshot());
+
+        // An unchanged home is not replaced, so subscribers hear nothing.
+        let shared = SharedHome::new(home);
+        let events = shared.subscribe();
+        assert!(!refresh(&addr, &shared).unwrap());
+        assert!(events.try_recv().is_err());
+
+        // A toggle sent the way the dashboard sends it shows up on refresh.
+        let command = Command::TurnOn {
+            room: "kitchen".into(),
+            device: "kettle".into(),
+        };
+        let body = serde_json::to_string(&[command]).unwrap();
+        http_request(&addr, "POST", "/batch", &body).unwrap();
+        assert!(refresh(&addr, &shared).unwrap());
+        assert!(events.try_recv().is_ok());
+        assert!(shared.read(|h| h.get_socket("kitchen", "kettle").unwrap().is_on()));
+
+        let err = http_request(&addr, "GET", "/rooms/attic", "").unwrap_err();
+        assert!(err.to_string().contains("404"));
+        server.shutdown();
+    }
+}
@@ -0,0 +1,143 @@
+//! `smart-home` — command-line tool for inspecting and editing a home file.
+//!
+//! ```text
+//! smart-home [--file <home.json>]                  # interactive REPL
+//! smart-home [--file <home.json>] <command> ...    # run one command and exit
+//! ```
+//!
+//! In one-shot mode changes are written back to the file automatically; in the
+//! REPL use `save`.  Run `smart-home help` for the list of commands.
+//!
+//! `--audit <audit.jsonl>` appends every change to an audit log (see
+//! `smart_home::audit`) under the name in `$USER`.
+
+mod shell;
+
+use shell::{CliError, Outcome, Shell};
+use smart_home::audit::AuditLog;
+use smart_home::{storage, SmartHome, SystemClock};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "Usage: smart-home [--file <home.json>] [--audit <audit.jsonl>] [<command> [args...]]";
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut path = None;
+    let mut audit_path = None;
+    loop {
+        match args.first().map(String::as_str) {
+            Some("-f" | "--file" | "--audit") if args.len() >= 2 => {
+                let value = Some(PathBuf::from(args.remove(1)));
+                if args.remove(0) == "--audit" {
+                    audit_path = value;
+                } else {
+                    path = value;
+                }
+            }
+            Some("-f" | "--file" | "--audit") => {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
+            }
+            Some("-h" | "--help") => {
+                println!("{}\n\n{}", USAGE, shell::HELP);
+                return ExitCode::SUCCESS;
+            }
+            _ => break,
+        }
+    }
+
+    let home = match &path {
+        Some(path) if path.exists() => match storage::load(path) {
+            Ok(home) => home,
+            Err(e) => {
+                eprintln!("error: {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => SmartHome::new("Smart Home"),
+    };
+    let mut shell = Shell::new(home, path);
+    if let Some(audit_path) = audit_path {
+        match AuditLog::open(&audit_path, SystemClock) {
+            Ok(log) => {
+                let user = std::env::var("USER").unwrap_or_else(|_| "unknown".to_string());
+                shell = shell.with_audit(log, user);
+            }
+            Err(e) => {
+                eprintln!("error: {}: {}", audit_path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if args.is_empty() {
+        repl(&mut shell)
+    } else {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        one_shot(&mut shell, &args)
+    }
+}
+
+fn one_shot(shell: &mut Shell, args: &[&str]) -> ExitCode {
+    match shell.execute(args).and_then(|out| {
+        shell.save_if_dirty()?;
+        Ok(out)
+    }) {
+        Ok(Outcome::Continue(out)) => {
+            if !out.is_empty() {
+                println!("{}", out);
+            }
+            ExitCode::SUCCESS
+        }
+        Ok(Outcome::Quit) => ExitCode::SUCCESS,
+        Err(e @ CliError::Usage(_)) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn repl(shell: &mut Shell) -> ExitCode {
+    println!(
+        "Smart home '{}' — type 'help' for commands.",
+        shell.home().name()
+    );
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("smart-home> ");
+        // A broken stdout leaves nothing useful to do in the REPL.
+        if io::stdout().flush().is_err() {
+            return ExitCode::FAILURE;
+        }
+        line.clear();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) => {
+                println!();
+                if shell.is_dirty() {
+                    eprintln!("warning: unsaved changes were discarded");
+                }
+                return ExitCode::SUCCESS;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        match shell.execute_line(&line) {
+            Ok(Outcome::Continue(out)) if out.is_empty() => {}
+            Ok(Outcome::Continue(out)) => println!("{}", out),
+            Ok(Outcome::Quit) => return ExitCode::SUCCESS,
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+}
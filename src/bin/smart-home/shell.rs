@@ -0,0 +1,1078 @@
+//! Command interpreter shared by the REPL and one-shot modes.
+
+use smart_home::area::Area;
+use smart_home::audit::AuditLog;
+use smart_home::group::{Group, GroupOutcome};
+use smart_home::history::History;
+use smart_home::metadata::{Metadata, Query};
+use smart_home::trace::{format_timestamp, Trace};
+use smart_home::{
+    storage, Command, HomeEvent, Report, Room, SmartHome, SmartHomeError, Socket, Thermometer,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Help text listing every command.
+pub const HELP: &str = "\
+Commands:
+  rooms                                   List rooms
+  devices <room>                          List devices in a room
+  add-room <room> [name]                  Add an empty room
+  remove-room <room>                      Remove a room with all its devices
+  add-socket <room> <device> <watts> [name]
+  add-thermometer <room> <device> <celsius> [name]
+  remove-device <room> <device>           Remove a device
+  on|off|toggle <room> <device>           Switch a socket
+  set-temp <room> <device> <celsius>      Set a thermometer reading
+  set-battery <room> <device> <percent> [dBm]
+                                          Record a battery level (and signal)
+  all-on|all-off <area>                   Switch every socket in an area
+  set-floor <room> [floor]                Place a room on a floor (or on none)
+  add-zone|remove-zone <room> <zone>      Add a room to a zone or take it out
+  tag|untag <room> [device] <tag>         Set or clear a tag
+  set-meta <room> [device] <key> <value>  Set an attribute, e.g. manufacturer or model
+  unset-meta <room> [device] <key>        Remove an attribute
+  find [tag:<tag>] [kind:<kind>] [<key>=<value>] ...
+                                          List rooms and devices matching all terms
+  groups                                  List device groups
+  add-group|remove-group <group>          Create or delete a device group
+  group-add|group-remove <group> <room> <device>
+  group-on|group-off <group>              Switch every socket in a group
+  group-status <group>                    Show a group's power, mean temperature and
+                                          members that are missing
+  floors | zones                          List floors or zones with their rooms and power
+  undo | redo                             Revert or re-apply the last change
+  report [text|json] [room [device]]      Print a report of the home, a room or a device
+  report [text|json] <area>               Print a report of an area
+  load | load! <file>                     Replace the home with one read from a file
+                                          (load! discards unsaved changes)
+  save [file]                             Write the home to a file
+  trace <file>                            List the changes recorded in a trace
+  replay | replay! <file> [count]         Replace the home with a trace's state after
+                                          <count> changes (all by default; replay!
+                                          discards unsaved changes)
+  help                                    Show this help
+  quit | quit!                            Leave the REPL (quit! discards unsaved changes)
+
+Areas are written home, floor:<name>, zone:<name> or room:<key>.
+Arguments containing spaces can be wrapped in double quotes.";
+
+/// Errors reported by the shell.
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line could not be understood.
+    Usage(String),
+    /// The command was understood but the home rejected it.
+    Home(SmartHomeError),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{} (type 'help' for usage)", msg),
+            CliError::Home(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<SmartHomeError> for CliError {
+    fn from(err: SmartHomeError) -> Self {
+        CliError::Home(err)
+    }
+}
+
+/// What the caller should do after a command.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// Print the text (may be empty) and keep going.
+    Continue(String),
+    /// Leave the REPL.
+    Quit,
+}
+
+/// Holds the home being edited and the file it belongs to.
+pub struct Shell {
+    home: SmartHome,
+    path: Option<PathBuf>,
+    dirty: bool,
+    history: History,
+    /// Where changes are recorded, and the name they are recorded under.
+    audit: Option<(AuditLog, String)>,
+}
+
+/// Puts the home back after a change that could not be audited.
+type Rollback = fn(&mut History, &mut SmartHome) -> Result<Vec<HomeEvent>, SmartHomeError>;
+
+impl Shell {
+    /// Creates a shell editing `home`, optionally backed by the file at `path`.
+    pub fn new(home: SmartHome, path: Option<PathBuf>) -> Self {
+        Self {
+            home,
+            path,
+            dirty: false,
+            history: History::new(),
+            audit: None,
+        }
+    }
+
+    /// Records every change in `log` as made by `actor`.
+    pub fn with_audit(mut self, log: AuditLog, actor: impl Into<String>) -> Self {
+        self.audit = Some((log, actor.into()));
+        self
+    }
+
+    /// Returns the home being edited.
+    pub fn home(&self) -> &SmartHome {
+        &self.home
+    }
+
+    /// Returns `true` if the home changed since it was last loaded or saved.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Writes the home back to its file if there is one and it has unsaved changes.
+    pub fn save_if_dirty(&mut self) -> Result<(), CliError> {
+        if let (true, Some(path)) = (self.dirty, &self.path) {
+            storage::save(&self.home, path)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Parses and executes one command line.
+    pub fn execute_line(&mut self, line: &str) -> Result<Outcome, CliError> {
+        let args = split_args(line)?;
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.execute(&args)
+    }
+
+    /// Executes one already tokenized command.
+    pub fn execute(&mut self, args: &[&str]) -> Result<Outcome, CliError> {
+        let Some((&cmd, rest)) = args.split_first() else {
+            return Ok(Outcome::Continue(String::new()));
+        };
+        let out = match (cmd, rest) {
+            ("help", []) => HELP.to_string(),
+            ("quit" | "exit", []) if self.dirty => {
+                return Err(CliError::Usage(
+                    "unsaved changes; use 'save' or 'quit!' to discard them".to_string(),
+                ))
+            }
+            ("quit" | "exit" | "quit!", []) => return Ok(Outcome::Quit),
+            ("rooms", []) => self.list_rooms(),
+            ("devices", [room]) => self.list_devices(room)?,
+            ("add-room", [key, name @ ..]) => {
+                self.apply(Command::AddRoom {
+                    room: key.to_string(),
+                    data: Room::new(display_name(key, name)),
+                })?;
+                format!("Added room '{}'", key)
+            }
+            ("remove-room", [key]) => {
+                self.apply(Command::RemoveRoom {
+                    room: key.to_string(),
+                })?;
+                format!("Removed room '{}'", key)
+            }
+            ("add-socket", [room, key, watts, name @ ..]) => {
+                let socket = Socket::try_new(display_name(key, name), parse_number(watts)?)?;
+                self.apply(Command::AddDevice {
+                    room: room.to_string(),
+                    device: key.to_string(),
+                    data: socket.into(),
+                })?;
+                format!("Added socket '{}' to '{}'", key, room)
+            }
+            ("add-thermometer", [room, key, celsius, name @ ..]) => {
+                let therm = Thermometer::try_new(display_name(key, name), parse_number(celsius)?)?;
+                self.apply(Command::AddDevice {
+                    room: room.to_string(),
+                    device: key.to_string(),
+                    data: therm.into(),
+                })?;
+                format!("Added thermometer '{}' to '{}'", key, room)
+            }
+            ("remove-device", [room, key]) => {
+                self.apply(Command::RemoveDevice {
+                    room: room.to_string(),
+                    device: key.to_string(),
+                })?;
+                format!("Removed '{}' from '{}'", key, room)
+            }
+            ("on" | "off" | "toggle", [room, key]) => {
+                let (room, device) = (room.to_string(), key.to_string());
+                let command = match cmd {
+                    "on" => Command::TurnOn { room, device },
+                    "off" => Command::TurnOff { room, device },
+                    _ => Command::Toggle { room, device },
+                };
+                let on = matches!(
+                    self.apply(command)?,
+                    HomeEvent::SocketSwitched { on: true, .. }
+                );
+                format!("Socket '{}' is {}", key, if on { "on" } else { "off" })
+            }
+            ("set-temp", [room, key, celsius]) => {
+                let celsius = parse_number(celsius)?;
+                self.apply(Command::SetTemperature {
+                    room: room.to_string(),
+                    device: key.to_string(),
+                    temperature: celsius,
+                })?;
+                format!("Thermometer '{}' reads {} °C", key, celsius)
+            }
+            ("set-battery", [room, key, percent, dbm @ ..]) if dbm.len() <= 1 => {
+                let battery = percent
+                    .trim_end_matches('%')
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("'{}' is not a percentage", percent)))?;
+                let signal_dbm =
+                    match dbm {
+                        [dbm] => Some(dbm.parse().map_err(|_| {
+                            CliError::Usage(format!("'{}' is not a dBm value", dbm))
+                        })?),
+                        _ => self
+                            .home()
+                            .get_device(room, key)?
+                            .wireless()
+                            .and_then(|w| w.signal_dbm()),
+                    };
+                let event = self.apply(Command::UpdateWireless {
+                    room: room.to_string(),
+                    device: key.to_string(),
+                    battery: Some(battery),
+                    signal_dbm,
+                })?;
+                let low = matches!(
+                    event,
+                    HomeEvent::WirelessUpdated {
+                        battery_low: true,
+                        ..
+                    }
+                );
+                format!(
+                    "Battery of '{}' is at {}%{}",
+                    key,
+                    battery,
+                    if low { " (low)" } else { "" }
+                )
+            }
+            ("all-on" | "all-off", [area]) => {
+                let area: Area = area.parse()?;
+                let commands = area.switch_sockets(&self.home, cmd == "all-on");
+                let events = self.history.execute_all(&mut self.home, commands)?;
+                self.record(&events, History::undo)?;
+                let count = events.len();
+                self.dirty |= count > 0;
+                format!(
+                    "Switched {} {} socket(s) in {}",
+                    if cmd == "all-on" { "on" } else { "off" },
+                    count,
+                    area
+                )
+            }
+            ("set-floor", [room, floor @ ..]) => {
+                let floor = match floor {
+                    [] => None,
+                    [floor] => Some(floor.to_string()),
+                    _ => return Err(CliError::Usage("set-floor takes one floor".into())),
+                };
+                self.apply(Command::SetFloor {
+                    room: room.to_string(),
+                    floor: floor.clone(),
+                })?;
+                match floor {
+                    Some(floor) => format!("Room '{}' is on floor '{}'", room, floor),
+                    None => format!("Room '{}' is on no floor", room),
+                }
+            }
+            ("add-zone", [room, zone]) => {
+                self.apply(Command::AddToZone {
+                    room: room.to_string(),
+                    zone: zone.to_string(),
+                })?;
+                format!("Room '{}' is in zone '{}'", room, zone)
+            }
+            ("remove-zone", [room, zone]) => {
+                self.apply(Command::RemoveFromZone {
+                    room: room.to_string(),
+                    zone: zone.to_string(),
+                })?;
+                format!("Room '{}' is not in zone '{}'", room, zone)
+            }
+            ("tag" | "untag", [target @ .., tag]) if matches!(target.len(), 1 | 2) => {
+                let (room, device) = split_target(target);
+                let (command, verb) = if cmd == "tag" {
+                    let tag = tag.to_string();
+                    (Command::Tag { room, device, tag }, "Set tag")
+                } else {
+                    let tag = tag.to_string();
+                    (Command::Untag { room, device, tag }, "Cleared tag")
+                };
+                self.apply(command)?;
+                format!("{} '{}' on '{}'", verb, tag, target.join("/"))
+            }
+            ("set-meta", [target @ .., key, value]) if matches!(target.len(), 1 | 2) => {
+                let (room, device) = split_target(target);
+                self.apply(Command::SetAttribute {
+                    room,
+                    device,
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })?;
+                format!("Set {}={} on '{}'", key, value, target.join("/"))
+            }
+            ("unset-meta", [target @ .., key]) if matches!(target.len(), 1 | 2) => {
+                let (room, device) = split_target(target);
+                self.apply(Command::UnsetAttribute {
+                    room,
+                    device,
+                    key: key.to_string(),
+                })?;
+                format!("Removed {} from '{}'", key, target.join("/"))
+            }
+            ("find", terms) => self.find(terms)?,
+            ("groups", []) => self
+                .home
+                .groups()
+                .map(|(name, group)| format!("{}\t{} member(s)", name, group.len()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ("add-group", [name]) => {
+                self.apply(Command::AddGroup {
+                    group: name.to_string(),
+                    data: Group::new(),
+                })?;
+                format!("Added group '{}'", name)
+            }
+            ("remove-group", [name]) => {
+                self.apply(Command::RemoveGroup {
+                    group: name.to_string(),
+                })?;
+                format!("Removed group '{}'", name)
+            }
+            ("group-add", [name, room, key]) => {
+                self.apply(Command::AddToGroup {
+                    group: name.to_string(),
+                    room: room.to_string(),
+                    device: key.to_string(),
+                })?;
+                format!("Added '{}/{}' to group '{}'", room, key, name)
+            }
+            ("group-remove", [name, room, key]) => {
+                self.apply(Command::RemoveFromGroup {
+                    group: name.to_string(),
+                    room: room.to_string(),
+                    device: key.to_string(),
+                })?;
+                format!("Removed '{}/{}' from group '{}'", room, key, name)
+            }
+            ("group-on" | "group-off", [name]) => {
+                let outcome = self
+                    .group(name)?
+                    .switch_sockets(&self.home, cmd == "group-on");
+                let events = self.history.execute_all(&mut self.home, outcome.value)?;
+                self.record(&events, History::undo)?;
+                let count = events.len();
+                self.dirty |= count > 0;
+                format!(
+                    "Switched {} {} socket(s)\n{}",
+                    if cmd == "group-on" { "on" } else { "off" },
+                    count,
+                    GroupOutcome::<()> {
+                        value: (),
+                        failures: outcome.failures,
+                    }
+                    .report()
+                )
+            }
+            ("group-status", [name]) => self.group_status(name)?,
+            ("floors", []) => self.list_areas(self.home.floors(), |f| Area::Floor(f.into())),
+            ("zones", []) => self.list_areas(self.home.zones(), |z| Area::Zone(z.into())),
+            ("undo", []) => {
+                let events = self.history.undo(&mut self.home)?;
+                self.record(&events, History::redo)?;
+                self.undone("Undid", "Nothing to undo", &events)
+            }
+            ("redo", []) => {
+                let events = self.history.redo(&mut self.home)?;
+                self.record(&events, History::undo)?;
+                self.undone("Redid", "Nothing to redo", &events)
+            }
+            ("report", rest) => self.report(rest)?,
+            ("load", [_]) if self.dirty => {
+                return Err(CliError::Usage(
+                    "unsaved changes; use 'save' or 'load!' to discard them".to_string(),
+                ))
+            }
+            ("load" | "load!", [path]) => {
+                let home = storage::load(path)?;
+                self.record_replacement(&home)?;
+                self.home = home;
+                self.history.clear();
+                self.path = Some(PathBuf::from(path));
+                self.dirty = false;
+                format!("Loaded '{}' from {}", self.home.name(), path)
+            }
+            ("save", []) => {
+                let path = self
+                    .path
+                    .clone()
+                    .ok_or_else(|| CliError::Usage("no file to save to".to_string()))?;
+                self.save_to(path)?
+            }
+            ("save", [path]) => self.save_to(PathBuf::from(path))?,
+            ("trace", [path]) => list_trace(&Trace::load(path)?),
+            ("replay", [_, ..]) if self.dirty => {
+                return Err(CliError::Usage(
+                    "unsaved changes; use 'save' or 'replay!' to discard them".to_string(),
+                ))
+            }
+            ("replay" | "replay!", [path, count @ ..]) => {
+                let trace = Trace::load(path)?;
+                let count = match count {
+                    [] => trace.entries.len(),
+                    [count] => count
+                        .parse()
+                        .map_err(|_| CliError::Usage(format!("'{}' is not a count", count)))?,
+                    _ => return Err(CliError::Usage("replay takes a file and a count".into())),
+                };
+                let home = trace.state_after(count)?;
+                self.record_replacement(&home)?;
+                self.home = home;
+                self.history.clear();
+                let applied = count.min(trace.entries.len());
+                let at = match applied {
+                    0 => trace.started_at,
+                    n => trace.entries[n - 1].at,
+                };
+                self.changed(format!(
+                    "Replayed {} change(s), state as of {}",
+                    applied,
+                    format_timestamp(at)
+                ))
+            }
+            _ => {
+                return Err(CliError::Usage(format!(
+                    "invalid command: {}",
+                    args.join(" ")
+                )))
+            }
+        };
+        Ok(Outcome::Continue(out))
+    }
+
+    fn apply(&mut self, command: Command) -> Result<HomeEvent, SmartHomeError> {
+        let event = self.history.execute(&mut self.home, command)?;
+        self.record(std::slice::from_ref(&event), History::undo)?;
+        self.dirty = true;
+        Ok(event)
+    }
+
+    /// Records changes just made in the audit log, if there is one.  If that
+    /// fails they are reverted with `rollback`, so no change goes unrecorded.
+    fn record(&mut self, events: &[HomeEvent], rollback: Rollback) -> Result<(), SmartHomeError> {
+        let Some((log, actor)) = &mut self.audit else {
+            return Ok(());
+        };
+        if let Err(e) = events.iter().try_for_each(|event| log.record(actor, event)) {
+            rollback(&mut self.history, &mut self.home)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Records that the home is about to be replaced by `home`.
+    fn record_replacement(&mut self, home: &SmartHome) -> Result<(), SmartHomeError> {
+        let event = HomeEvent::HomeReplaced { data: home.clone() };
+        // Nothing has changed yet, so there is nothing to roll back.
+        self.record(&[event], |_, _| Ok(Vec::new()))
+    }
+
+    fn undone(&mut self, verb: &str, nothing: &str, events: &[HomeEvent]) -> String {
+        match events {
+            [] => nothing.to_string(),
+            [event] => self.changed(format!("{} change to '{}'", verb, event.path())),
+            events => self.changed(format!("{} {} changes", verb, events.len())),
+        }
+    }
+
+    fn changed(&mut self, msg: String) -> String {
+        self.dirty = true;
+        msg
+    }
+
+    fn save_to(&mut self, path: PathBuf) -> Result<String, CliError> {
+        storage::save(&self.home, &path)?;
+        let msg = format!("Saved to {}", path.display());
+        self.path = Some(path);
+        self.dirty = false;
+        Ok(msg)
+    }
+
+    fn list_rooms(&self) -> String {
+        let mut rooms: Vec<_> = self.home.rooms().collect();
+        rooms.sort_by_key(|(key, _)| *key);
+        rooms
+            .iter()
+            .map(|(key, room)| {
+                format!(
+                    "{}\t{}\t{} device(s)",
+                    key,
+                    room.name(),
+                    room.device_count()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn find(&self, terms: &[&str]) -> Result<String, CliError> {
+        let query = terms
+            .iter()
+            .try_fold(Query::new(), |query, term| query.term(term))?;
+        let describe = |meta: &Metadata| {
+            meta.tags()
+                .map(|tag| format!("#{}", tag))
+                .chain(meta.attributes().map(|(k, v)| format!("{}={}", k, v)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let rooms = self
+            .home
+            .find_rooms(&query)
+            .into_iter()
+            .map(|(key, room)| format!("{}\troom\t{}", key, describe(room.metadata())));
+        let devices = self
+            .home
+            .find_devices(&query)
+            .into_iter()
+            .map(|(room, key, device)| {
+                format!(
+                    "{}/{}\t{}\t{}",
+                    room,
+                    key,
+                    device.kind(),
+                    describe(device.metadata())
+                )
+            });
+        Ok(rooms.chain(devices).collect::<Vec<_>>().join("\n"))
+    }
+
+    fn group(&self, name: &str) -> Result<&Group, SmartHomeError> {
+        self.home
+            .get_group(name)
+            .ok_or_else(|| SmartHomeError::GroupNotFound(name.to_string()))
+    }
+
+    fn group_status(&self, name: &str) -> Result<String, CliError> {
+        let group = self.group(name)?;
+        let members: Vec<String> = group.members().map(ToString::to_string).collect();
+        let mut lines = vec![format!("Members: {}", members.join(", "))];
+        let power = group.power(&self.home);
+        let temperature = group.average_temperature(&self.home);
+        // A member counts as failed only if it is neither a socket nor a thermometer.
+        let failures = power.failures.into_iter().filter(|failure| {
+            temperature
+                .failures
+                .iter()
+                .any(|other| other.member == failure.member)
+        });
+        let failures = GroupOutcome {
+            value: (),
+            failures: failures.collect(),
+        };
+        lines.push(format!("Power: {} W", power.value));
+        if let Some(celsius) = temperature.value {
+            lines.push(format!("Average temperature: {:.1} °C", celsius));
+        }
+        lines.push(failures.report().trim_end().to_string());
+        Ok(lines.join("\n"))
+    }
+
+    fn list_areas(&self, names: Vec<&str>, area: impl Fn(&str) -> Area) -> String {
+        names
+            .into_iter()
+            .map(|name| {
+                let area = area(name);
+                let rooms: Vec<_> = area.rooms(&self.home).iter().map(|(k, _)| *k).collect();
+                format!(
+                    "{}\t{} W\t{}",
+                    name,
+                    area.power(&self.home),
+                    rooms.join(", ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn list_devices(&self, room_key: &str) -> Result<String, CliError> {
+        let room = self
+            .home
+            .get_room(room_key)
+            .ok_or_else(|| SmartHomeError::RoomNotFound(room_key.to_string()))?;
+        let mut devices: Vec<_> = room.devices().collect();
+        devices.sort_by_key(|(key, _)| *key);
+        Ok(devices
+            .iter()
+            .map(|(key, device)| format!("{}\t{}", key, device))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn report(&self, args: &[&str]) -> Result<String, CliError> {
+        let (json, target) = match args.split_first() {
+            Some((&"json", rest)) => (true, rest),
+            Some((&"text", rest)) => (false, rest),
+            _ => (false, args),
+        };
+        let text = match target {
+            [] if json => storage::to_json(&self.home),
+            [] => self.home.report(),
+            [area] if area.contains(':') => {
+                let area: Area = area.parse()?;
+                if json {
+                    to_pretty_json(
+                        &area
+                            .rooms(&self.home)
+                            .into_iter()
+                            .collect::<BTreeMap<_, _>>(),
+                    )
+                } else {
+                    area.report(&self.home)?
+                }
+            }
+            [room] => {
+                let room = self
+                    .home
+                    .get_room(room)
+                    .ok_or_else(|| SmartHomeError::RoomNotFound(room.to_string()))?;
+                if json {
+                    to_pretty_json(room)
+                } else {
+                    room.report()
+                }
+            }
+            [room, device] => {
+                let device = self.home.get_device(room, device)?;
+                if json {
+                    to_pretty_json(device)
+                } else {
+                    device.report()
+                }
+            }
+            _ => {
+                return Err(CliError::Usage(
+                    "report takes at most a room and a device".into(),
+                ))
+            }
+        };
+        Ok(text.trim_end().to_string())
+    }
+}
+
+/// Splits `[room]` or `[room, device]` into a room key and optional device key.
+fn split_target(target: &[&str]) -> (String, Option<String>) {
+    (target[0].to_string(), target.get(1).map(|d| d.to_string()))
+}
+
+fn list_trace(trace: &Trace) -> String {
+    let mut lines = vec![format!("0\t{}\tstart", format_timestamp(trace.started_at))];
+    lines.extend(trace.entries.iter().enumerate().map(|(i, entry)| {
+        let event = serde_json::to_value(&entry.event).expect("events are always serializable");
+        format!(
+            "{}\t{}\t{}\t{}",
+            i + 1,
+            format_timestamp(entry.at),
+            entry.path,
+            event["event"].as_str().unwrap_or_default()
+        )
+    }));
+    lines.join("\n")
+}
+
+fn to_pretty_json(value: &impl serde::Serialize) -> String {
+    serde_json::to_string_pretty(value).expect("home data is always serializable")
+}
+
+fn display_name(key: &str, words: &[&str]) -> String {
+    if words.is_empty() {
+        key.to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+fn parse_number(text: &str) -> Result<f32, CliError> {
+    text.parse()
+        .map_err(|_| CliError::Usage(format!("'{}' is not a number", text)))
+}
+
+/// Splits a command line on whitespace, keeping `"quoted strings"` together.
+pub fn split_args(line: &str) -> Result<Vec<String>, CliError> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut in_quotes = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_quotes {
+        return Err(CliError::Usage("unterminated quote".to_string()));
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(shell: &mut Shell, line: &str) -> String {
+        match shell.execute_line(line).unwrap() {
+            Outcome::Continue(out) => out,
+            Outcome::Quit => panic!("unexpected quit"),
+        }
+    }
+
+    fn shell_with_kitchen() -> Shell {
+        let mut shell = Shell::new(SmartHome::new("Test"), None);
+        run(&mut shell, "add-room kitchen Kitchen");
+        run(
+            &mut shell,
+            "add-socket kitchen kettle 2000 \"Electric kettle\"",
+        );
+        run(&mut shell, "add-thermometer kitchen sensor 21.5");
+        shell
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args(r#"add-room hall "Front hall"  "#).unwrap(),
+            vec!["add-room", "hall", "Front hall"]
+        );
+        assert_eq!(split_args(r#"x """#).unwrap(), vec!["x", ""]);
+        assert!(split_args(r#"add-room "hall"#).is_err());
+    }
+
+    #[test]
+    fn test_add_and_list() {
+        let mut shell = shell_with_kitchen();
+        assert!(run(&mut shell, "rooms").contains("kitchen\tKitchen\t2 device(s)"));
+        let devices = run(&mut shell, "devices kitchen");
+        assert!(devices.contains("Electric kettle"));
+        assert!(devices.contains("21.5"));
+        assert!(shell.is_dirty());
+    }
+
+    #[test]
+    fn test_toggle_and_set_temperature() {
+        let mut shell = shell_with_kitchen();
+        run(&mut shell, "toggle kitchen kettle");
+        assert!(shell
+            .home()
+            .get_socket("kitchen", "kettle")
+            .unwrap()
+            .is_on());
+        run(&mut shell, "off kitchen kettle");
+        assert!(!shell
+            .home()
+            .get_socket("kitchen", "kettle")
+            .unwrap()
+            .is_on());
+
+        run(&mut shell, "set-temp kitchen sensor 19");
+        assert_eq!(
+            shell
+                .home()
+                .get_thermometer("kitchen", "sensor")
+                .unwrap()
+                .temperature(),
+            19.0
+        );
+
+        run(&mut shell, "set-battery kitchen sensor 15% -72");
+        run(&mut shell, "set-battery kitchen sensor 12");
+        let sensor = shell.home().get_thermometer("kitchen", "sensor").unwrap();
+        assert_eq!(sensor.wireless().battery(), Some(12));
+        assert_eq!(sensor.wireless().signal_dbm(), Some(-72));
+        assert!(sensor.wireless().is_battery_low());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut shell = shell_with_kitchen();
+        assert!(matches!(
+            shell.execute_line("on kitchen sensor"),
+            Err(CliError::Home(SmartHomeError::WrongDeviceKind { .. }))
+        ));
+        assert!(matches!(
+            shell.execute_line("set-temp kitchen sensor NaN"),
+            Err(CliError::Home(SmartHomeError::InvalidValue(_)))
+        ));
+        assert!(matches!(
+            shell.execute_line("add-room kitchen"),
+            Err(CliError::Home(SmartHomeError::DuplicateRoom(_)))
+        ));
+        assert!(matches!(
+            shell.execute_line("remove-device kitchen lamp"),
+            Err(CliError::Home(SmartHomeError::DeviceNotFound { .. }))
+        ));
+        assert!(matches!(
+            shell.execute_line("frobnicate"),
+            Err(CliError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_report_formats() {
+        let mut shell = shell_with_kitchen();
+        assert!(run(&mut shell, "report").contains("Smart Home 'Test'"));
+        assert!(run(&mut shell, "report text kitchen").contains("Room 'Kitchen'"));
+        let json: serde_json::Value =
+            serde_json::from_str(&run(&mut shell, "report json kitchen kettle")).unwrap();
+        assert_eq!(json["kind"], "socket");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut shell = shell_with_kitchen();
+        run(&mut shell, "remove-device kitchen kettle");
+        assert_eq!(shell.home().get_room("kitchen").unwrap().device_count(), 1);
+        run(&mut shell, "remove-room kitchen");
+        assert_eq!(shell.home().room_count(), 0);
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut shell = shell_with_kitchen();
+        assert_eq!(run(&mut shell, "redo"), "Nothing to redo");
+        run(&mut shell, "remove-room kitchen");
+        assert_eq!(run(&mut shell, "undo"), "Undid change to 'kitchen'");
+        assert!(shell.home().get_device("kitchen", "kettle").is_ok());
+
+        run(&mut shell, "toggle kitchen kettle");
+        run(&mut shell, "undo");
+        let kettle = shell.home().get_socket("kitchen", "kettle").unwrap();
+        assert!(!kettle.is_on());
+        assert_eq!(run(&mut shell, "redo"), "Redid change to 'kitchen/kettle'");
+        assert!(shell
+            .home()
+            .get_socket("kitchen", "kettle")
+            .unwrap()
+            .is_on());
+    }
+
+    #[test]
+    fn test_floors_and_zones() {
+        let mut shell = shell_with_kitchen();
+        run(&mut shell, "add-room hall");
+        run(&mut shell, "add-socket hall lamp 60");
+        run(&mut shell, "set-floor kitchen ground");
+        run(&mut shell, "set-floor hall ground");
+        run(&mut shell, "add-zone kitchen heating");
+        assert_eq!(run(&mut shell, "floors"), "ground\t0 W\thall, kitchen");
+
+        assert_eq!(
+            run(&mut shell, "all-on floor:ground"),
+            "Switched on 2 socket(s) in floor:ground"
+        );
+        assert_eq!(run(&mut shell, "zones"), "heating\t2000 W\tkitchen");
+        assert!(run(&mut shell, "report floor:ground").contains("(2 room(s), 2060 W)"));
+
+        assert_eq!(run(&mut shell, "undo"), "Undid 2 changes");
+        assert_eq!(run(&mut shell, "floors"), "ground\t0 W\thall, kitchen");
+        assert_eq!(run(&mut shell, "undo"), "Undid change to 'kitchen'");
+        assert_eq!(run(&mut shell, "zones"), "");
+        run(&mut shell, "undo");
+        assert_eq!(run(&mut shell, "floors"), "ground\t0 W\tkitchen");
+        assert!(shell.execute_line("all-off wing:east").is_err());
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut shell = shell_with_kitchen();
+        run(&mut shell, "add-room hall");
+        run(&mut shell, "add-socket hall lamp 60");
+        run(&mut shell, "add-group everything");
+        run(&mut shell, "group-add everything hall lamp");
+        run(&mut shell, "group-add everything kitchen kettle");
+        run(&mut shell, "group-add everything kitchen sensor");
+        assert!(shell
+            .execute_line("group-add everything kitchen fridge")
+            .is_err());
+        assert_eq!(run(&mut shell, "groups"), "everything\t3 member(s)");
+
+        run(&mut shell, "group-remove everything kitchen sensor");
+        assert_eq!(run(&mut shell, "undo"), "Undid change to 'kitchen/sensor'");
+        run(&mut shell, "remove-group everything");
+        assert_eq!(
+            run(&mut shell, "undo"),
+            "Undid change to 'group:everything'"
+        );
+        assert_eq!(run(&mut shell, "groups"), "everything\t3 member(s)");
+
+        run(&mut shell, "remove-device hall lamp");
+        let out = run(&mut shell, "group-on everything");
+        assert!(out.starts_with("Switched on 1 socket(s)\n2 member(s) failed:"));
+        assert!(out.contains("[hall/lamp] Device 'lamp' not found"));
+        assert!(out.contains("[kitchen/sensor] Device 'sensor' is a thermometer"));
+
+        let status = run(&mut shell, "group-status everything");
+        assert!(status.contains("Power: 2000 W"));
+        assert!(status.contains("Average temperature: 21.5 °C"));
+        assert!(status.ends_with(
+            "1 member(s) failed:\n  [hall/lamp] Device 'lamp' not found in room 'hall'"
+        ));
+    }
+
+    #[test]
+    fn test_tags_and_find() {
+        let mut shell = shell_with_kitchen();
+        run(&mut shell, "tag kitchen kettle critical");
+        run(&mut shell, "set-meta kitchen kettle model \"KE 2000\"");
+        run(&mut shell, "tag kitchen critical");
+        run(&mut shell, "set-meta kitchen sensor model \"KE 2000\"");
+        assert_eq!(
+            run(&mut shell, "find tag:critical"),
+            "kitchen\troom\t#critical\nkitchen/kettle\tsocket\t#critical model=KE 2000"
+        );
+        assert_eq!(
+            run(&mut shell, "find kind:thermometer \"model=KE 2000\""),
+            "kitchen/sensor\tthermometer\tmodel=KE 2000"
+        );
+        run(&mut shell, "untag kitchen kettle critical");
+        run(&mut shell, "unset-meta kitchen kettle model");
+        assert_eq!(run(&mut shell, "find kind:socket tag:critical"), "");
+        assert_eq!(run(&mut shell, "undo"), "Undid change to 'kitchen/kettle'");
+        run(&mut shell, "undo");
+        assert_eq!(
+            run(&mut shell, "find kind:socket tag:critical"),
+            "kitchen/kettle\tsocket\t#critical model=KE 2000"
+        );
+        assert!(shell.execute_line("find critical").is_err());
+        assert!(shell.execute_line("tag attic critical").is_err());
+    }
+
+    #[test]
+    fn test_changes_are_audited() {
+        let log = AuditLog::new(smart_home::SystemClock);
+        let mut shell = shell_with_kitchen().with_audit(log, "olga");
+        run(&mut shell, "on kitchen kettle");
+        run(&mut shell, "all-off home");
+        run(&mut shell, "undo");
+        assert!(shell.execute_line("on kitchen fridge").is_err());
+
+        let (log, _) = shell.audit.as_ref().unwrap();
+        let changes: Vec<String> = log.entries().iter().map(|e| e.to_string()).collect();
+        assert_eq!(changes.len(), 3);
+        assert!(changes[0].ends_with(" olga kitchen/kettle: switched on"));
+        assert!(changes[1].ends_with(" olga kitchen/kettle: switched off"));
+        assert!(changes[2].ends_with(" olga kitchen/kettle: switched on"));
+    }
+
+    #[test]
+    fn test_quit_with_unsaved_changes() {
+        let mut shell = shell_with_kitchen();
+        assert!(shell.execute_line("quit").is_err());
+        assert_eq!(shell.execute_line("quit!").unwrap(), Outcome::Quit);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("smart_home_cli_{}.json", std::process::id()));
+        let path_str = path.to_str().unwrap();
+
+        let mut shell = shell_with_kitchen();
+        run(&mut shell, &format!("save \"{}\"", path_str));
+        assert!(!shell.is_dirty());
+
+        let mut other = Shell::new(SmartHome::new("Empty"), None);
+        run(&mut other, &format!("load \"{}\"", path_str));
+        assert_eq!(other.home().name(), "Test");
+        assert!(other.home().get_device("kitchen", "kettle").is_ok());
+
+        run(&mut other, "add-room attic");
+        let load = format!("load \"{}\"", path_str);
+        assert!(other.execute_line(&load).is_err());
+        assert!(other.home().get_room("attic").is_some());
+        run(&mut other, &format!("load! \"{}\"", path_str));
+        std::fs::remove_file(&path).unwrap();
+        assert!(other.home().get_room("attic").is_none());
+        assert!(!other.is_dirty());
+    }
+
+    #[test]
+    fn test_trace_and_replay() {
+        use smart_home::trace::Recorder;
+        use smart_home::{Command, ManualClock, SharedHome};
+
+        let path =
+            std::env::temp_dir().join(format!("smart_home_cli_{}.jsonl", std::process::id()));
+        let shared = SharedHome::new(shell_with_kitchen().home().clone());
+        let file = std::fs::File::create(&path).unwrap();
+        let mut recorder = Recorder::start(&shared, ManualClock::default(), file).unwrap();
+        for _ in 0..2 {
+            shared
+                .execute(Command::Toggle {
+                    room: "kitchen".into(),
+                    device: "kettle".into(),
+                })
+                .unwrap();
+        }
+        recorder.poll().unwrap();
+        let path_str = path.to_str().unwrap().to_string();
+
+        let mut shell = Shell::new(SmartHome::new("Empty"), None);
+        let listing = run(&mut shell, &format!("trace \"{}\"", path_str));
+        assert!(listing.contains("2\t1970-01-01T00:00:00.000Z\tkitchen/kettle\tsocket_switched"));
+
+        run(&mut shell, &format!("replay \"{}\" 1", path_str));
+        assert!(shell
+            .home()
+            .get_socket("kitchen", "kettle")
+            .unwrap()
+            .is_on());
+        assert!(shell.is_dirty());
+
+        // Unsaved changes, including a previous replay, are kept unless forced.
+        let replay = format!("replay \"{}\" 0", path_str);
+        assert!(shell.execute_line(&replay).is_err());
+        assert!(shell
+            .home()
+            .get_socket("kitchen", "kettle")
+            .unwrap()
+            .is_on());
+        run(&mut shell, &format!("replay! \"{}\" 2", path_str));
+        std::fs::remove_file(&path).unwrap();
+        assert!(!shell
+            .home()
+            .get_socket("kitchen", "kettle")
+            .unwrap()
+            .is_on());
+        assert!(shell.is_dirty());
+    }
+}
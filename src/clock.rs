@@ -0,0 +1,218 @@
+//! Injectable sources of the current time.
+//!
+//! Time-dependent features take a [`Clock`] instead of calling
+//! [`SystemTime::now`] themselves, so tests can use a [`ManualClock`] and move
+//! time forward explicitly instead of sleeping.
+
+use crate::error::SmartHomeError;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How far past its start an [`AcceleratedClock`] runs before it stops
+/// (2^40 seconds, about 35,000 years).
+const MAX_ACCELERATED_SPAN: Duration = Duration::from_secs(1 << 40);
+
+/// A source of the current wall-clock time.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+
+    /// Returns the time elapsed since `earlier`, or zero if `earlier` is in the
+    /// future.
+    fn since(&self, earlier: SystemTime) -> Duration {
+        self.now().duration_since(earlier).unwrap_or_default()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+/// The operating system's clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so a test can keep one and hand another to the
+/// code under test.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::clock::{Clock, ManualClock};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let clock = ManualClock::new(UNIX_EPOCH);
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(90));
+/// assert_eq!(clock.since(start), Duration::from_secs(90));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl ManualClock {
+    /// Creates a clock showing `start`.
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.lock() += duration;
+    }
+
+    /// Sets the clock to `time`, which may be in the past.
+    pub fn set(&self, time: SystemTime) {
+        *self.lock() = time;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SystemTime> {
+        self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for ManualClock {
+    /// A clock stopped at the Unix epoch.
+    fn default() -> Self {
+        Self::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.lock()
+    }
+}
+
+/// A clock running `factor` times faster than real time from a chosen start.
+///
+/// Useful for demos and soak tests: with a factor of 3600, one real second is
+/// one simulated hour.  The clock stops about 35,000 simulated years after its
+/// start instead of overflowing.
+#[derive(Debug, Clone)]
+pub struct AcceleratedClock {
+    start: SystemTime,
+    origin: Instant,
+    factor: f64,
+}
+
+impl AcceleratedClock {
+    /// Creates a clock showing `start` now and running `factor` times faster
+    /// than real time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is negative, NaN or infinite; use
+    /// [`AcceleratedClock::try_new`] for factors from configuration.
+    pub fn new(start: SystemTime, factor: f64) -> Self {
+        Self::try_new(start, factor).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a clock like [`AcceleratedClock::new`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if `factor` is negative, NaN or
+    /// infinite.
+    pub fn try_new(start: SystemTime, factor: f64) -> Result<Self, SmartHomeError> {
+        if !factor.is_finite() || factor < 0.0 {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "clock factor must be a non-negative number, got {}",
+                factor
+            )));
+        }
+        Ok(Self {
+            start,
+            origin: Instant::now(),
+            factor,
+        })
+    }
+
+    /// Returns the speed-up factor.
+    pub fn factor(&self) -> f64 {
+        self.factor
+    }
+}
+
+impl Clock for AcceleratedClock {
+    fn now(&self) -> SystemTime {
+        let scaled = Duration::try_from_secs_f64(self.origin.elapsed().as_secs_f64() * self.factor)
+            .unwrap_or(Duration::MAX)
+            .min(MAX_ACCELERATED_SPAN);
+        self.start.checked_add(scaled).unwrap_or(self.start)
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_manual_clock_is_shared() {
+        let clock = ManualClock::default();
+        let other: Arc<dyn Clock> = Arc::new(clock.clone());
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(other.now(), UNIX_EPOCH + Duration::from_secs(5));
+
+        clock.set(UNIX_EPOCH);
+        assert_eq!(
+            other.since(UNIX_EPOCH + Duration::from_secs(5)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_accelerated_clock() {
+        let stopped = AcceleratedClock::new(UNIX_EPOCH, 0.0);
+        assert_eq!(stopped.now(), UNIX_EPOCH);
+
+        let fast = AcceleratedClock::new(UNIX_EPOCH, 1_000_000.0);
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(fast.since(UNIX_EPOCH) >= Duration::from_secs(2000));
+    }
+
+    #[test]
+    fn test_accelerated_clock_saturates() {
+        let fastest = AcceleratedClock::try_new(UNIX_EPOCH, f64::MAX).unwrap();
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(fastest.since(UNIX_EPOCH), MAX_ACCELERATED_SPAN);
+    }
+
+    #[test]
+    fn test_accelerated_clock_try_new_rejects_bad_factors() {
+        for factor in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                AcceleratedClock::try_new(UNIX_EPOCH, factor),
+                Err(SmartHomeError::InvalidValue(_))
+            ));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "clock factor")]
+    fn test_accelerated_clock_rejects_negative_factor() {
+        AcceleratedClock::new(UNIX_EPOCH, -1.0);
+    }
+}
@@ -0,0 +1,1088 @@
+//! Serializable mutations of a [`SmartHome`].
+//!
+//! Front-ends (servers, dashboards, scripts) describe changes as [`Command`]s
+//! and apply them with [`Command::apply`], which reports what happened as a
+//! [`HomeEvent`].  Going through commands instead of mutable references lets a
+//! [`SharedHome`][crate::SharedHome] announce every change to its subscribers.
+
+use crate::devices::Wireless;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::group::Group;
+use crate::metadata::Metadata;
+use crate::room::Room;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use serde::{Deserialize, Serialize};
+
+/// A single change to a home.
+///
+/// Serialized with a `"command"` tag, e.g.
+/// `{"command": "turn_on", "room": "kitchen", "device": "kettle"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Add a room; fails if the key is taken.
+    AddRoom {
+        /// Room key.
+        room: String,
+        /// The room to add, possibly with devices.
+        data: Room,
+    },
+    /// Remove a room with all its devices.
+    RemoveRoom {
+        /// Room key.
+        room: String,
+    },
+    /// Add a device to a room; fails if the key is taken.
+    AddDevice {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// The device to add.
+        data: SmartDevice,
+    },
+    /// Remove a device from a room.
+    RemoveDevice {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+    },
+    /// Change the key of a room; see [`SmartHome::rename_room`].
+    RenameRoom {
+        /// Current room key.
+        room: String,
+        /// New room key.
+        to: String,
+    },
+    /// Change the key of a device within its room.
+    RenameDevice {
+        /// Room key.
+        room: String,
+        /// Current device key.
+        device: String,
+        /// New device key.
+        to: String,
+    },
+    /// Move a device to another room and key; see [`SmartHome::move_device`].
+    MoveDevice {
+        /// Current room key.
+        room: String,
+        /// Current device key.
+        device: String,
+        /// Destination room key.
+        to_room: String,
+        /// Destination device key.
+        to_device: String,
+    },
+    /// Place a room on a floor, or on none.
+    SetFloor {
+        /// Room key.
+        room: String,
+        /// Floor name; `None` takes the room off its floor.
+        #[serde(default)]
+        floor: Option<String>,
+    },
+    /// Add a room to a zone.
+    AddToZone {
+        /// Room key.
+        room: String,
+        /// Zone name.
+        zone: String,
+    },
+    /// Take a room out of a zone.
+    RemoveFromZone {
+        /// Room key.
+        room: String,
+        /// Zone name.
+        zone: String,
+    },
+    /// Add a device group; fails if the name is taken.
+    AddGroup {
+        /// Group name.
+        group: String,
+        /// The group to add, possibly with members.
+        #[serde(default)]
+        data: Group,
+    },
+    /// Remove a device group; its members stay in the home.
+    RemoveGroup {
+        /// Group name.
+        group: String,
+    },
+    /// Add a device to a group; see [`SmartHome::add_to_group`].
+    AddToGroup {
+        /// Group name.
+        group: String,
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+    },
+    /// Take a device out of a group.
+    RemoveFromGroup {
+        /// Group name.
+        group: String,
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+    },
+    /// Set a tag on a room, or on a device when `device` is given.
+    Tag {
+        /// Room key.
+        room: String,
+        /// Device key; `None` tags the room itself.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        /// The tag.
+        tag: String,
+    },
+    /// Clear a tag from a room or device.
+    Untag {
+        /// Room key.
+        room: String,
+        /// Device key; `None` for the room itself.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        /// The tag.
+        tag: String,
+    },
+    /// Set an attribute, e.g. `manufacturer` or `model`, on a room or device.
+    SetAttribute {
+        /// Room key.
+        room: String,
+        /// Device key; `None` for the room itself.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        /// Attribute name.
+        key: String,
+        /// New value.
+        value: String,
+    },
+    /// Remove an attribute from a room or device.
+    UnsetAttribute {
+        /// Room key.
+        room: String,
+        /// Device key; `None` for the room itself.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        /// Attribute name.
+        key: String,
+    },
+    /// Replace the whole home, e.g. with one loaded from a file.
+    ReplaceHome {
+        /// The new home.
+        data: SmartHome,
+    },
+    /// Switch a socket on.
+    TurnOn {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+    },
+    /// Switch a socket off.
+    TurnOff {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+    },
+    /// Switch a socket to the opposite state.
+    Toggle {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+    },
+    /// Set a thermometer reading (validated against its range).
+    SetTemperature {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// New reading in Celsius.
+        temperature: f32,
+    },
+    /// Record the battery level and signal strength a wireless device reported.
+    ///
+    /// Both values replace the stored ones; `None` (or a missing field) means
+    /// unknown.
+    UpdateWireless {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// Battery level in percent (0–100).
+        #[serde(default)]
+        battery: Option<u8>,
+        /// Signal strength in dBm.
+        #[serde(default)]
+        signal_dbm: Option<i16>,
+    },
+}
+
+impl Command {
+    /// Applies the command to `home` and returns the resulting event.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as the corresponding [`SmartHome`] methods; the
+    /// home is unchanged when an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smart_home::{room, Command, HomeEvent, SmartHome, Socket};
+    ///
+    /// let mut home = SmartHome::new("Flat");
+    /// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+    ///
+    /// let cmd = Command::TurnOn { room: "hall".into(), device: "lamp".into() };
+    /// let event = cmd.apply(&mut home)?;
+    /// assert!(matches!(event, HomeEvent::SocketSwitched { on: true, .. }));
+    /// # Ok::<(), smart_home::SmartHomeError>(())
+    /// ```
+    pub fn apply(self, home: &mut SmartHome) -> Result<HomeEvent, SmartHomeError> {
+        match self {
+            Command::AddRoom { room, data } => {
+                home.add_room(room.clone(), data.clone())?;
+                Ok(HomeEvent::RoomAdded { room, data })
+            }
+            Command::RemoveRoom { room } => {
+                home.remove_room(&room)
+                    .ok_or_else(|| SmartHomeError::RoomNotFound(room.clone()))?;
+                Ok(HomeEvent::RoomRemoved { room })
+            }
+            Command::AddDevice { room, device, data } => {
+                home.add_device(&room, device.clone(), data.clone())?;
+                Ok(HomeEvent::DeviceAdded { room, device, data })
+            }
+            Command::RemoveDevice { room, device } => {
+                home.remove_device(&room, &device)?;
+                Ok(HomeEvent::DeviceRemoved { room, device })
+            }
+            Command::RenameRoom { room, to } => {
+                home.rename_room(&room, to.clone())?;
+                Ok(HomeEvent::RoomRenamed { room, to })
+            }
+            Command::RenameDevice { room, device, to } => {
+                home.rename_device(&room, &device, &to)?;
+                Ok(HomeEvent::DeviceRenamed { room, device, to })
+            }
+            Command::MoveDevice {
+                room,
+                device,
+                to_room,
+                to_device,
+            } => {
+                home.move_device((&room, &device), (&to_room, &to_device))?;
+                Ok(HomeEvent::DeviceMoved {
+                    room,
+                    device,
+                    to_room,
+                    to_device,
+                })
+            }
+            Command::SetFloor { room, floor } => {
+                room_mut(home, &room)?.set_floor(floor.clone());
+                Ok(HomeEvent::FloorChanged { room, floor })
+            }
+            Command::AddToZone { room, zone } => {
+                room_mut(home, &room)?.add_zone(zone.clone());
+                Ok(HomeEvent::ZoneChanged {
+                    room,
+                    zone,
+                    in_zone: true,
+                })
+            }
+            Command::RemoveFromZone { room, zone } => {
+                room_mut(home, &room)?.remove_zone(&zone);
+                Ok(HomeEvent::ZoneChanged {
+                    room,
+                    zone,
+                    in_zone: false,
+                })
+            }
+            Command::AddGroup { group, data } => {
+                home.add_group(group.clone(), data.clone())?;
+                Ok(HomeEvent::GroupAdded { group, data })
+            }
+            Command::RemoveGroup { group } => {
+                home.remove_group(&group)
+                    .ok_or_else(|| SmartHomeError::GroupNotFound(group.clone()))?;
+                Ok(HomeEvent::GroupRemoved { group })
+            }
+            Command::AddToGroup {
+                group,
+                room,
+                device,
+            } => {
+                home.add_to_group(&group, &room, &device)?;
+                Ok(HomeEvent::GroupMembershipChanged {
+                    group,
+                    room,
+                    device,
+                    member: true,
+                })
+            }
+            Command::RemoveFromGroup {
+                group,
+                room,
+                device,
+            } => {
+                home.remove_from_group(&group, &room, &device)?;
+                Ok(HomeEvent::GroupMembershipChanged {
+                    group,
+                    room,
+                    device,
+                    member: false,
+                })
+            }
+            Command::Tag { room, device, tag } => {
+                metadata_mut(home, &room, device.as_deref())?.add_tag(tag.clone());
+                Ok(HomeEvent::TagChanged {
+                    room,
+                    device,
+                    tag,
+                    tagged: true,
+                })
+            }
+            Command::Untag { room, device, tag } => {
+                metadata_mut(home, &room, device.as_deref())?.remove_tag(&tag);
+                Ok(HomeEvent::TagChanged {
+                    room,
+                    device,
+                    tag,
+                    tagged: false,
+                })
+            }
+            Command::SetAttribute {
+                room,
+                device,
+                key,
+                value,
+            } => {
+                metadata_mut(home, &room, device.as_deref())?.set(key.clone(), value.clone());
+                Ok(HomeEvent::AttributeChanged {
+                    room,
+                    device,
+                    key,
+                    value: Some(value),
+                })
+            }
+            Command::UnsetAttribute { room, device, key } => {
+                metadata_mut(home, &room, device.as_deref())?.remove(&key);
+                Ok(HomeEvent::AttributeChanged {
+                    room,
+                    device,
+                    key,
+                    value: None,
+                })
+            }
+            Command::ReplaceHome { data } => {
+                *home = data.clone();
+                Ok(HomeEvent::HomeReplaced { data })
+            }
+            Command::TurnOn { room, device } => switch(home, room, device, Some(true)),
+            Command::TurnOff { room, device } => switch(home, room, device, Some(false)),
+            Command::Toggle { room, device } => switch(home, room, device, None),
+            Command::SetTemperature {
+                room,
+                device,
+                temperature,
+            } => {
+                home.get_thermometer_mut(&room, &device)?
+                    .try_set_temperature(temperature)?;
+                Ok(HomeEvent::TemperatureChanged {
+                    room,
+                    device,
+                    temperature,
+                })
+            }
+            Command::UpdateWireless {
+                room,
+                device,
+                battery,
+                signal_dbm,
+            } => {
+                let wireless = wireless_mut(home, &room, &device)?;
+                wireless.set_battery(battery)?;
+                wireless.set_signal_dbm(signal_dbm);
+                Ok(HomeEvent::WirelessUpdated {
+                    room,
+                    device,
+                    battery,
+                    signal_dbm,
+                    battery_low: wireless.is_battery_low(),
+                })
+            }
+        }
+    }
+
+    /// Returns the command that undoes this one when applied right after it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error [`Command::apply`] would return if the command cannot
+    /// be applied to `home`.
+    pub fn inverse(&self, home: &SmartHome) -> Result<Command, SmartHomeError> {
+        let inverse = match self {
+            Command::AddRoom { room, .. } => Command::RemoveRoom { room: room.clone() },
+            Command::RemoveRoom { room } => Command::AddRoom {
+                room: room.clone(),
+                data: home
+                    .get_room(room)
+                    .cloned()
+                    .ok_or_else(|| SmartHomeError::RoomNotFound(room.clone()))?,
+            },
+            Command::AddDevice { room, device, .. } => Command::RemoveDevice {
+                room: room.clone(),
+                device: device.clone(),
+            },
+            Command::RemoveDevice { room, device } => Command::AddDevice {
+                room: room.clone(),
+                device: device.clone(),
+                data: home.get_device(room, device)?.clone(),
+            },
+            Command::RenameRoom { room, to } => {
+                get_room(home, room)?;
+                Command::RenameRoom {
+                    room: to.clone(),
+                    to: room.clone(),
+                }
+            }
+            Command::RenameDevice { room, device, to } => {
+                home.get_device(room, device)?;
+                Command::RenameDevice {
+                    room: room.clone(),
+                    device: to.clone(),
+                    to: device.clone(),
+                }
+            }
+            Command::MoveDevice {
+                room,
+                device,
+                to_room,
+                to_device,
+            } => {
+                home.get_device(room, device)?;
+                Command::MoveDevice {
+                    room: to_room.clone(),
+                    device: to_device.clone(),
+                    to_room: room.clone(),
+                    to_device: device.clone(),
+                }
+            }
+            Command::SetFloor { room, .. } => Command::SetFloor {
+                room: room.clone(),
+                floor: get_room(home, room)?.floor().map(str::to_string),
+            },
+            Command::AddToZone { room, zone } | Command::RemoveFromZone { room, zone } => {
+                let (room, zone) = (room.clone(), zone.clone());
+                if get_room(home, &room)?.in_zone(&zone) {
+                    Command::AddToZone { room, zone }
+                } else {
+                    Command::RemoveFromZone { room, zone }
+                }
+            }
+            Command::AddGroup { group, .. } => Command::RemoveGroup {
+                group: group.clone(),
+            },
+            Command::RemoveGroup { group } => Command::AddGroup {
+                group: group.clone(),
+                data: get_group(home, group)?.clone(),
+            },
+            Command::AddToGroup {
+                group,
+                room,
+                device,
+            }
+            | Command::RemoveFromGroup {
+                group,
+                room,
+                device,
+            } => {
+                if matches!(self, Command::AddToGroup { .. }) {
+                    home.get_device(room, device)?;
+                }
+                let member = get_group(home, group)?.contains(room, device);
+                let (group, room, device) = (group.clone(), room.clone(), device.clone());
+                if member {
+                    Command::AddToGroup {
+                        group,
+                        room,
+                        device,
+                    }
+                } else {
+                    Command::RemoveFromGroup {
+                        group,
+                        room,
+                        device,
+                    }
+                }
+            }
+            Command::Tag { room, device, tag } | Command::Untag { room, device, tag } => {
+                let tagged = metadata(home, room, device.as_deref())?.has_tag(tag);
+                let (room, device, tag) = (room.clone(), device.clone(), tag.clone());
+                if tagged {
+                    Command::Tag { room, device, tag }
+                } else {
+                    Command::Untag { room, device, tag }
+                }
+            }
+            Command::SetAttribute {
+                room, device, key, ..
+            }
+            | Command::UnsetAttribute { room, device, key } => {
+                let old = metadata(home, room, device.as_deref())?.get(key);
+                let (room, device, key) = (room.clone(), device.clone(), key.clone());
+                match old {
+                    Some(value) => Command::SetAttribute {
+                        room,
+                        device,
+                        key,
+                        value: value.to_string(),
+                    },
+                    None => Command::UnsetAttribute { room, device, key },
+                }
+            }
+            Command::ReplaceHome { .. } => Command::ReplaceHome { data: home.clone() },
+            Command::TurnOn { room, device }
+            | Command::TurnOff { room, device }
+            | Command::Toggle { room, device } => {
+                let (room, device) = (room.clone(), device.clone());
+                if home.get_socket(&room, &device)?.is_on() {
+                    Command::TurnOn { room, device }
+                } else {
+                    Command::TurnOff { room, device }
+                }
+            }
+            Command::SetTemperature { room, device, .. } => Command::SetTemperature {
+                room: room.clone(),
+                device: device.clone(),
+                temperature: home.get_thermometer(room, device)?.temperature(),
+            },
+            Command::UpdateWireless { room, device, .. } => {
+                let data = home.get_device(room, device)?;
+                let wireless = data
+                    .wireless()
+                    .ok_or_else(|| not_wireless(device, data.kind()))?;
+                Command::UpdateWireless {
+                    room: room.clone(),
+                    device: device.clone(),
+                    battery: wireless.battery(),
+                    signal_dbm: wireless.signal_dbm(),
+                }
+            }
+        };
+        Ok(inverse)
+    }
+
+    /// Applies the command and also returns its [inverse](Command::inverse).
+    ///
+    /// # Errors
+    ///
+    /// As for [`Command::apply`].
+    pub fn apply_reversible(
+        self,
+        home: &mut SmartHome,
+    ) -> Result<(HomeEvent, Command), SmartHomeError> {
+        let inverse = self.inverse(home)?;
+        Ok((self.apply(home)?, inverse))
+    }
+}
+
+/// Converts an event back into the command that reproduces it.
+///
+/// Applying the commands for a sequence of events to the home the events
+/// started from yields the same final state; this is how traces are replayed.
+impl From<HomeEvent> for Command {
+    fn from(event: HomeEvent) -> Self {
+        match event {
+            HomeEvent::RoomAdded { room, data } => Command::AddRoom { room, data },
+            HomeEvent::RoomRemoved { room } => Command::RemoveRoom { room },
+            HomeEvent::DeviceAdded { room, device, data } => {
+                Command::AddDevice { room, device, data }
+            }
+            HomeEvent::DeviceRemoved { room, device } => Command::RemoveDevice { room, device },
+            HomeEvent::RoomRenamed { room, to } => Command::RenameRoom { room, to },
+            HomeEvent::DeviceRenamed { room, device, to } => {
+                Command::RenameDevice { room, device, to }
+            }
+            HomeEvent::DeviceMoved {
+                room,
+                device,
+                to_room,
+                to_device,
+            } => Command::MoveDevice {
+                room,
+                device,
+                to_room,
+                to_device,
+            },
+            HomeEvent::FloorChanged { room, floor } => Command::SetFloor { room, floor },
+            HomeEvent::ZoneChanged {
+                room,
+                zone,
+                in_zone: true,
+            } => Command::AddToZone { room, zone },
+            HomeEvent::ZoneChanged { room, zone, .. } => Command::RemoveFromZone { room, zone },
+            HomeEvent::GroupAdded { group, data } => Command::AddGroup { group, data },
+            HomeEvent::GroupRemoved { group } => Command::RemoveGroup { group },
+            HomeEvent::GroupMembershipChanged {
+                group,
+                room,
+                device,
+                member: true,
+            } => Command::AddToGroup {
+                group,
+                room,
+                device,
+            },
+            HomeEvent::GroupMembershipChanged {
+                group,
+                room,
+                device,
+                ..
+            } => Command::RemoveFromGroup {
+                group,
+                room,
+                device,
+            },
+            HomeEvent::TagChanged {
+                room,
+                device,
+                tag,
+                tagged: true,
+            } => Command::Tag { room, device, tag },
+            HomeEvent::TagChanged {
+                room, device, tag, ..
+            } => Command::Untag { room, device, tag },
+            HomeEvent::AttributeChanged {
+                room,
+                device,
+                key,
+                value: Some(value),
+            } => Command::SetAttribute {
+                room,
+                device,
+                key,
+                value,
+            },
+            HomeEvent::AttributeChanged {
+                room, device, key, ..
+            } => Command::UnsetAttribute { room, device, key },
+            HomeEvent::HomeReplaced { data } => Command::ReplaceHome { data },
+            HomeEvent::SocketSwitched {
+                room,
+                device,
+                on: true,
+                ..
+            } => Command::TurnOn { room, device },
+            HomeEvent::SocketSwitched { room, device, .. } => Command::TurnOff { room, device },
+            HomeEvent::TemperatureChanged {
+                room,
+                device,
+                temperature,
+            } => Command::SetTemperature {
+                room,
+                device,
+                temperature,
+            },
+            HomeEvent::WirelessUpdated {
+                room,
+                device,
+                battery,
+                signal_dbm,
+                ..
+            } => Command::UpdateWireless {
+                room,
+                device,
+                battery,
+                signal_dbm,
+            },
+        }
+    }
+}
+
+fn get_room<'a>(home: &'a SmartHome, room: &str) -> Result<&'a Room, SmartHomeError> {
+    home.get_room(room)
+        .ok_or_else(|| SmartHomeError::RoomNotFound(room.to_string()))
+}
+
+fn get_group<'a>(home: &'a SmartHome, group: &str) -> Result<&'a Group, SmartHomeError> {
+    home.get_group(group)
+        .ok_or_else(|| SmartHomeError::GroupNotFound(group.to_string()))
+}
+
+fn room_mut<'a>(home: &'a mut SmartHome, room: &str) -> Result<&'a mut Room, SmartHomeError> {
+    home.get_room_mut(room)
+        .ok_or_else(|| SmartHomeError::RoomNotFound(room.to_string()))
+}
+
+/// Returns the tags and attributes of a room, or of a device when one is given.
+fn metadata<'a>(
+    home: &'a SmartHome,
+    room: &str,
+    device: Option<&str>,
+) -> Result<&'a Metadata, SmartHomeError> {
+    match device {
+        Some(device) => Ok(home.get_device(room, device)?.metadata()),
+        None => Ok(get_room(home, room)?.metadata()),
+    }
+}
+
+fn metadata_mut<'a>(
+    home: &'a mut SmartHome,
+    room: &str,
+    device: Option<&str>,
+) -> Result<&'a mut Metadata, SmartHomeError> {
+    match device {
+        Some(device) => Ok(home.get_device_mut(room, device)?.metadata_mut()),
+        None => Ok(room_mut(home, room)?.metadata_mut()),
+    }
+}
+
+/// Switches a socket to `on`, or toggles it when `on` is `None`.
+fn switch(
+    home: &mut SmartHome,
+    room: String,
+    device: String,
+    on: Option<bool>,
+) -> Result<HomeEvent, SmartHomeError> {
+    let socket = home.get_socket_mut(&room, &device)?;
+    if on.unwrap_or(!socket.is_on()) {
+        socket.turn_on();
+    } else {
+        socket.turn_off();
+    }
+    Ok(HomeEvent::SocketSwitched {
+        room,
+        device,
+        on: socket.is_on(),
+        power: socket.power(),
+    })
+}
+
+/// Returns the battery and signal state of a wireless device.
+fn wireless_mut<'a>(
+    home: &'a mut SmartHome,
+    room: &str,
+    device: &str,
+) -> Result<&'a mut Wireless, SmartHomeError> {
+    let data = home.get_device_mut(room, device)?;
+    let found = data.kind();
+    data.wireless_mut()
+        .ok_or_else(|| not_wireless(device, found))
+}
+
+fn not_wireless(device: &str, found: &'static str) -> SmartHomeError {
+    SmartHomeError::WrongDeviceKind {
+        device: device.to_string(),
+        expected: "wireless device",
+        found,
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "kettle" => Socket::new("Kettle", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 22.0),
+            ),
+        )
+        .unwrap();
+        home.add_group("all", Group::new()).unwrap();
+        home.add_to_group("all", "kitchen", "kettle").unwrap();
+        let kettle = home
+            .get_device_mut("kitchen", "kettle")
+            .unwrap()
+            .metadata_mut();
+        kettle.add_tag("critical");
+        kettle.set("model", "KE 2000");
+        home
+    }
+
+    fn path(device: &str) -> (String, String) {
+        ("kitchen".to_string(), device.to_string())
+    }
+
+    #[test]
+    fn test_switch_commands() {
+        let mut home = make_home();
+        let (room, device) = path("kettle");
+        let event = Command::Toggle { room, device }.apply(&mut home).unwrap();
+        assert!(matches!(
+            event,
+            HomeEvent::SocketSwitched { on: true, power, .. } if power == 2000.0
+        ));
+
+        let (room, device) = path("kettle");
+        Command::TurnOff { room, device }.apply(&mut home).unwrap();
+        assert!(!home.get_socket("kitchen", "kettle").unwrap().is_on());
+    }
+
+    #[test]
+    fn test_set_temperature_validates() {
+        let mut home = make_home();
+        let (room, device) = path("sensor");
+        let err = Command::SetTemperature {
+            room,
+            device,
+            temperature: f32::NAN,
+        }
+        .apply(&mut home)
+        .unwrap_err();
+        assert!(matches!(err, SmartHomeError::InvalidValue(_)));
+        assert_eq!(
+            home.get_thermometer("kitchen", "sensor")
+                .unwrap()
+                .temperature(),
+            22.0
+        );
+    }
+
+    #[test]
+    fn test_update_wireless() {
+        let mut home = make_home();
+        let (room, device) = path("sensor");
+        let event = Command::UpdateWireless {
+            room,
+            device,
+            battery: Some(15),
+            signal_dbm: None,
+        }
+        .apply(&mut home)
+        .unwrap();
+        assert!(matches!(
+            event,
+            HomeEvent::WirelessUpdated {
+                battery: Some(15),
+                battery_low: true,
+                ..
+            }
+        ));
+
+        let (room, device) = path("sensor");
+        let err = Command::UpdateWireless {
+            room,
+            device,
+            battery: Some(101),
+            signal_dbm: Some(-50),
+        }
+        .apply(&mut home)
+        .unwrap_err();
+        assert!(matches!(err, SmartHomeError::InvalidValue(_)));
+        let wireless = *home
+            .get_thermometer("kitchen", "sensor")
+            .unwrap()
+            .wireless();
+        assert_eq!(
+            (wireless.battery(), wireless.signal_dbm()),
+            (Some(15), None)
+        );
+
+        let (room, device) = path("kettle");
+        let err = Command::UpdateWireless {
+            room,
+            device,
+            battery: Some(50),
+            signal_dbm: None,
+        }
+        .apply(&mut home)
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SmartHomeError::WrongDeviceKind {
+                found: "socket",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_structural_commands() {
+        let mut home = make_home();
+        Command::AddRoom {
+            room: "hall".into(),
+            data: Room::new("Hall"),
+        }
+        .apply(&mut home)
+        .unwrap();
+        Command::AddDevice {
+            room: "hall".into(),
+            device: "lamp".into(),
+            data: Socket::new("Lamp", 40.0).into(),
+        }
+        .apply(&mut home)
+        .unwrap();
+        assert!(home.get_socket("hall", "lamp").is_ok());
+
+        Command::RemoveDevice {
+            room: "hall".into(),
+            device: "lamp".into(),
+        }
+        .apply(&mut home)
+        .unwrap();
+        let event = Command::RemoveRoom {
+            room: "hall".into(),
+        }
+        .apply(&mut home)
+        .unwrap();
+        assert!(matches!(event, HomeEvent::RoomRemoved { .. }));
+        assert!(matches!(
+            Command::RemoveRoom {
+                room: "hall".into()
+            }
+            .apply(&mut home),
+            Err(SmartHomeError::RoomNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_inverse_restores_state() {
+        let commands = [
+            Command::RemoveRoom {
+                room: "kitchen".into(),
+            },
+            Command::RemoveDevice {
+                room: "kitchen".into(),
+                device: "kettle".into(),
+            },
+            Command::Toggle {
+                room: "kitchen".into(),
+                device: "kettle".into(),
+            },
+            Command::SetTemperature {
+                room: "kitchen".into(),
+                device: "sensor".into(),
+                temperature: 30.0,
+            },
+            Command::UpdateWireless {
+                room: "kitchen".into(),
+                device: "sensor".into(),
+                battery: Some(40),
+                signal_dbm: Some(-60),
+            },
+            Command::RenameRoom {
+                room: "kitchen".into(),
+                to: "galley".into(),
+            },
+            Command::RenameDevice {
+                room: "kitchen".into(),
+                device: "kettle".into(),
+                to: "boiler".into(),
+            },
+            Command::MoveDevice {
+                room: "kitchen".into(),
+                device: "sensor".into(),
+                to_room: "kitchen".into(),
+                to_device: "thermo".into(),
+            },
+            Command::SetFloor {
+                room: "kitchen".into(),
+                floor: Some("ground".into()),
+            },
+            Command::AddToZone {
+                room: "kitchen".into(),
+                zone: "heating".into(),
+            },
+            Command::AddGroup {
+                group: "lamps".into(),
+                data: Group::new(),
+            },
+            Command::AddToGroup {
+                group: "all".into(),
+                room: "kitchen".into(),
+                device: "sensor".into(),
+            },
+            Command::RemoveFromGroup {
+                group: "all".into(),
+                room: "kitchen".into(),
+                device: "kettle".into(),
+            },
+            Command::RemoveGroup {
+                group: "all".into(),
+            },
+            Command::Tag {
+                room: "kitchen".into(),
+                device: None,
+                tag: "downstairs".into(),
+            },
+            Command::Untag {
+                room: "kitchen".into(),
+                device: Some("kettle".into()),
+                tag: "critical".into(),
+            },
+            Command::SetAttribute {
+                room: "kitchen".into(),
+                device: Some("kettle".into()),
+                key: "model".into(),
+                value: "KE 3000".into(),
+            },
+            Command::UnsetAttribute {
+                room: "kitchen".into(),
+                device: Some("kettle".into()),
+                key: "model".into(),
+            },
+            Command::ReplaceHome {
+                data: SmartHome::new("Other"),
+            },
+        ];
+        let before = crate::storage::to_json(&make_home());
+        for cmd in commands {
+            let mut home = make_home();
+            let (_, inverse) = cmd.apply_reversible(&mut home).unwrap();
+            assert_ne!(crate::storage::to_json(&home), before);
+            inverse.apply(&mut home).unwrap();
+            assert_eq!(crate::storage::to_json(&home), before);
+        }
+
+        let cmd = Command::TurnOn {
+            room: "attic".into(),
+            device: "lamp".into(),
+        };
+        assert!(matches!(
+            cmd.inverse(&make_home()),
+            Err(SmartHomeError::RoomNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_event_round_trip() {
+        let mut home = make_home();
+        let mut replayed = make_home();
+        let (room, device) = path("kettle");
+        let event = Command::Toggle { room, device }.apply(&mut home).unwrap();
+        Command::from(event).apply(&mut replayed).unwrap();
+        assert!(replayed.get_socket("kitchen", "kettle").unwrap().is_on());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let cmd: Command = serde_json::from_str(
+            r#"{"command": "add_room", "room": "hall", "data": {"name": "Hall"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(cmd, Command::AddRoom { .. }));
+        let cmd: Command = serde_json::from_str(
+            r#"{"command": "set_temperature", "room": "kitchen", "device": "sensor", "temperature": 20}"#,
+        )
+        .unwrap();
+        assert!(matches!(cmd, Command::SetTemperature { .. }));
+    }
+}
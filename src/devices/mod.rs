@@ -2,6 +2,8 @@
 
 pub mod socket;
 pub mod thermometer;
+pub mod wireless;
 
 pub use socket::Socket;
 pub use thermometer::Thermometer;
+pub use wireless::Wireless;
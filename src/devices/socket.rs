@@ -1,11 +1,21 @@
 //! Smart socket.
 
+use crate::error::SmartHomeError;
+use crate::metadata::Metadata;
+use serde::{Deserialize, Serialize};
+
 /// Represents a smart socket.
-#[derive(Debug, Clone)]
+///
+/// Deserialization goes through [`Socket::try_new`], so invalid wattage in a file
+/// is rejected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SocketData")]
 pub struct Socket {
     name: String,
     is_on: bool,
     power_consumption: f32,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    meta: Metadata,
 }
 
 impl Socket {
@@ -29,7 +39,41 @@ impl Socket {
             name: name.into(),
             is_on: false,
             power_consumption,
+            meta: Metadata::default(),
+        }
+    }
+
+    /// Creates a new smart socket, validating the power consumption.
+    ///
+    /// Prefer this over [`Socket::new`] for values coming from files, the network or
+    /// device firmware.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if `power_consumption` is NaN,
+    /// infinite or negative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smart_home::Socket;
+    ///
+    /// assert!(Socket::try_new("Desk lamp", 60.0).is_ok());
+    /// assert!(Socket::try_new("Desk lamp", -60.0).is_err());
+    /// assert!(Socket::try_new("Desk lamp", f32::NAN).is_err());
+    /// ```
+    pub fn try_new(
+        name: impl Into<String>,
+        power_consumption: f32,
+    ) -> Result<Self, SmartHomeError> {
+        let name = name.into();
+        if !power_consumption.is_finite() || power_consumption < 0.0 {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "socket '{}': power consumption must be a non-negative number of watts, got {}",
+                name, power_consumption
+            )));
         }
+        Ok(Self::new(name, power_consumption))
     }
 
     /// Turns the socket on.
@@ -63,12 +107,44 @@ impl Socket {
         &self.name
     }
 
+    /// Returns the socket's tags and attributes.
+    pub fn metadata(&self) -> &Metadata {
+        &self.meta
+    }
+
+    /// Returns the socket's tags and attributes for editing.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.meta
+    }
+
     /// Returns the nominal power consumption.
     pub fn power_consumption(&self) -> f32 {
         self.power_consumption
     }
 }
 
+/// Unvalidated on-disk representation of a [`Socket`].
+#[derive(Deserialize)]
+struct SocketData {
+    name: String,
+    #[serde(default)]
+    is_on: bool,
+    power_consumption: f32,
+    #[serde(default)]
+    meta: Metadata,
+}
+
+impl TryFrom<SocketData> for Socket {
+    type Error = SmartHomeError;
+
+    fn try_from(data: SocketData) -> Result<Self, Self::Error> {
+        let mut socket = Socket::try_new(data.name, data.power_consumption)?;
+        socket.is_on = data.is_on;
+        socket.meta = data.meta;
+        Ok(socket)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +170,36 @@ mod tests {
         assert_eq!(socket.power(), 0.0);
     }
 
+    #[test]
+    fn test_socket_try_new_validation() {
+        assert_eq!(
+            Socket::try_new("Fridge", 800.0)
+                .unwrap()
+                .power_consumption(),
+            800.0
+        );
+        assert!(Socket::try_new("Fridge", 0.0).is_ok());
+        for bad in [-1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                Socket::try_new("Fridge", bad),
+                Err(SmartHomeError::InvalidValue(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_socket_deserialize_validates() {
+        let socket: Socket =
+            serde_json::from_str(r#"{"name":"Lamp","is_on":true,"power_consumption":60.0}"#)
+                .unwrap();
+        assert!(socket.is_on());
+        assert_eq!(socket.power(), 60.0);
+
+        assert!(
+            serde_json::from_str::<Socket>(r#"{"name":"Lamp","power_consumption":-60.0}"#).is_err()
+        );
+    }
+
     #[test]
     fn test_socket_power_consumption() {
         let socket = Socket::new("Fridge".to_string(), 800.0);
@@ -1,10 +1,29 @@
 //! Smart thermometer.
 
+use crate::devices::wireless::Wireless;
+use crate::error::SmartHomeError;
+use crate::metadata::Metadata;
+use serde::{Deserialize, Serialize};
+
+/// Absolute zero in degrees Celsius — the lowest physically possible temperature.
+pub const ABSOLUTE_ZERO: f32 = -273.15;
+
+/// Default upper bound of the valid temperature range in degrees Celsius.
+pub const DEFAULT_MAX_TEMPERATURE: f32 = 1000.0;
+
 /// Represents a smart thermometer.
-#[derive(Debug, Clone)]
+///
+/// Deserialization goes through [`Thermometer::try_new`] and
+/// [`Thermometer::with_valid_range`], so impossible readings in a file are rejected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "ThermometerData", try_from = "ThermometerData")]
 pub struct Thermometer {
     name: String,
     current_temperature: f32,
+    min_temperature: f32,
+    max_temperature: f32,
+    meta: Metadata,
+    wireless: Wireless,
 }
 
 impl Thermometer {
@@ -27,7 +46,76 @@ impl Thermometer {
         Self {
             name: name.into(),
             current_temperature,
+            min_temperature: ABSOLUTE_ZERO,
+            max_temperature: DEFAULT_MAX_TEMPERATURE,
+            meta: Metadata::default(),
+            wireless: Wireless::default(),
+        }
+    }
+
+    /// Creates a new smart thermometer, validating the temperature against the
+    /// default range ([`ABSOLUTE_ZERO`] to [`DEFAULT_MAX_TEMPERATURE`]).
+    ///
+    /// Prefer this over [`Thermometer::new`] for values coming from files, the
+    /// network or device firmware.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if the temperature is NaN or out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smart_home::Thermometer;
+    ///
+    /// assert!(Thermometer::try_new("Living room", 22.5).is_ok());
+    /// assert!(Thermometer::try_new("Living room", -500.0).is_err());
+    /// assert!(Thermometer::try_new("Living room", f32::NAN).is_err());
+    /// ```
+    pub fn try_new(
+        name: impl Into<String>,
+        current_temperature: f32,
+    ) -> Result<Self, SmartHomeError> {
+        let mut thermometer = Self::new(name, ABSOLUTE_ZERO);
+        thermometer.try_set_temperature(current_temperature)?;
+        Ok(thermometer)
+    }
+
+    /// Restricts the accepted temperatures to `min..=max` (in Celsius).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if the bounds are not finite, if
+    /// `min > max`, or if the current temperature lies outside the new range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smart_home::Thermometer;
+    ///
+    /// let mut fridge = Thermometer::try_new("Fridge", 4.0)
+    ///     .unwrap()
+    ///     .with_valid_range(-30.0, 30.0)
+    ///     .unwrap();
+    /// assert!(fridge.try_set_temperature(45.0).is_err());
+    /// assert_eq!(fridge.temperature(), 4.0);
+    /// ```
+    pub fn with_valid_range(mut self, min: f32, max: f32) -> Result<Self, SmartHomeError> {
+        if !min.is_finite() || !max.is_finite() || min > max {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "thermometer '{}': invalid range {}..={} °C",
+                self.name, min, max
+            )));
         }
+        self.min_temperature = min;
+        self.max_temperature = max;
+        self.validate(self.current_temperature)?;
+        Ok(self)
+    }
+
+    /// Returns the accepted temperature range as `(min, max)` in Celsius.
+    pub fn valid_range(&self) -> (f32, f32) {
+        (self.min_temperature, self.max_temperature)
     }
 
     /// Returns the current temperature.
@@ -40,10 +128,104 @@ impl Thermometer {
         &self.name
     }
 
+    /// Returns the thermometer's tags and attributes.
+    pub fn metadata(&self) -> &Metadata {
+        &self.meta
+    }
+
+    /// Returns the thermometer's tags and attributes for editing.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.meta
+    }
+
+    /// Returns the battery level and signal strength last reported.
+    pub fn wireless(&self) -> &Wireless {
+        &self.wireless
+    }
+
+    /// Returns the battery level and signal strength for updating.
+    pub fn wireless_mut(&mut self) -> &mut Wireless {
+        &mut self.wireless
+    }
+
     /// Updates the current temperature.
     pub fn set_temperature(&mut self, temperature: f32) {
         self.current_temperature = temperature;
     }
+
+    /// Updates the current temperature if it is within the valid range.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if `temperature` is NaN or out of
+    /// range; the previous reading is kept.
+    pub fn try_set_temperature(&mut self, temperature: f32) -> Result<(), SmartHomeError> {
+        self.validate(temperature)?;
+        self.current_temperature = temperature;
+        Ok(())
+    }
+
+    fn validate(&self, temperature: f32) -> Result<(), SmartHomeError> {
+        // `contains` is false for NaN, so this also rejects NaN readings.
+        if (self.min_temperature..=self.max_temperature).contains(&temperature) {
+            Ok(())
+        } else {
+            Err(SmartHomeError::InvalidValue(format!(
+                "thermometer '{}': temperature {} °C is outside {}..={} °C",
+                self.name, temperature, self.min_temperature, self.max_temperature
+            )))
+        }
+    }
+}
+
+/// On-disk representation of a [`Thermometer`].
+#[derive(Serialize, Deserialize)]
+struct ThermometerData {
+    name: String,
+    temperature: f32,
+    #[serde(default = "default_min_temperature")]
+    min_temperature: f32,
+    #[serde(default = "default_max_temperature")]
+    max_temperature: f32,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    meta: Metadata,
+    #[serde(flatten)]
+    wireless: Wireless,
+}
+
+fn default_min_temperature() -> f32 {
+    ABSOLUTE_ZERO
+}
+
+fn default_max_temperature() -> f32 {
+    DEFAULT_MAX_TEMPERATURE
+}
+
+impl From<Thermometer> for ThermometerData {
+    fn from(t: Thermometer) -> Self {
+        Self {
+            name: t.name,
+            temperature: t.current_temperature,
+            min_temperature: t.min_temperature,
+            max_temperature: t.max_temperature,
+            meta: t.meta,
+            wireless: t.wireless,
+        }
+    }
+}
+
+impl TryFrom<ThermometerData> for Thermometer {
+    type Error = SmartHomeError;
+
+    fn try_from(data: ThermometerData) -> Result<Self, Self::Error> {
+        let mut thermometer = Thermometer::new(data.name, data.min_temperature)
+            .with_valid_range(data.min_temperature, data.max_temperature)?;
+        thermometer.try_set_temperature(data.temperature)?;
+        thermometer.meta = data.meta;
+        data.wireless.validate()?;
+        thermometer.wireless = data.wireless;
+        Ok(thermometer)
+    }
 }
 
 #[cfg(test)]
@@ -63,4 +245,85 @@ mod tests {
         thermometer.set_temperature(25.5);
         assert_eq!(thermometer.temperature(), 25.5);
     }
+
+    #[test]
+    fn test_thermometer_try_new_validation() {
+        assert_eq!(
+            Thermometer::try_new("T", -40.0).unwrap().temperature(),
+            -40.0
+        );
+        for bad in [f32::NAN, -500.0, f32::INFINITY, 5000.0] {
+            assert!(matches!(
+                Thermometer::try_new("T", bad),
+                Err(SmartHomeError::InvalidValue(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_thermometer_try_set_temperature_keeps_old_value() {
+        let mut thermometer = Thermometer::try_new("T", 20.0).unwrap();
+        assert!(thermometer.try_set_temperature(f32::NAN).is_err());
+        assert_eq!(thermometer.temperature(), 20.0);
+        thermometer.try_set_temperature(21.5).unwrap();
+        assert_eq!(thermometer.temperature(), 21.5);
+    }
+
+    #[test]
+    fn test_thermometer_serde_roundtrip() {
+        let thermometer = Thermometer::try_new("Fridge", 4.0)
+            .unwrap()
+            .with_valid_range(-30.0, 30.0)
+            .unwrap();
+        let json = serde_json::to_string(&thermometer).unwrap();
+        let back: Thermometer = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.temperature(), 4.0);
+        assert_eq!(back.valid_range(), (-30.0, 30.0));
+    }
+
+    #[test]
+    fn test_thermometer_deserialize_validates() {
+        let json = r#"{"name":"T","temperature":-500.0}"#;
+        assert!(serde_json::from_str::<Thermometer>(json).is_err());
+        let json =
+            r#"{"name":"T","temperature":40.0,"min_temperature":0.0,"max_temperature":30.0}"#;
+        assert!(serde_json::from_str::<Thermometer>(json).is_err());
+    }
+
+    #[test]
+    fn test_thermometer_wireless_serde() {
+        let mut thermometer = Thermometer::new("Bedroom", 19.0);
+        let plain = serde_json::to_string(&thermometer).unwrap();
+        assert!(!plain.contains("battery") && !plain.contains("low_battery"));
+
+        thermometer.wireless_mut().set_battery(Some(55)).unwrap();
+        thermometer.wireless_mut().set_signal_dbm(Some(-71));
+        thermometer
+            .wireless_mut()
+            .set_low_battery_threshold(30)
+            .unwrap();
+        let json = serde_json::to_string(&thermometer).unwrap();
+        assert!(json.contains(r#""battery":55,"signal_dbm":-71,"low_battery":30"#));
+        let back: Thermometer = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.wireless(), thermometer.wireless());
+
+        let json = r#"{"name":"T","temperature":20.0,"battery":120}"#;
+        assert!(serde_json::from_str::<Thermometer>(json).is_err());
+    }
+
+    #[test]
+    fn test_thermometer_custom_range() {
+        let thermometer = Thermometer::try_new("Outdoor", 10.0)
+            .unwrap()
+            .with_valid_range(-50.0, 60.0)
+            .unwrap();
+        assert_eq!(thermometer.valid_range(), (-50.0, 60.0));
+
+        assert!(Thermometer::new("T", 10.0)
+            .with_valid_range(20.0, 10.0)
+            .is_err());
+        assert!(Thermometer::new("T", 10.0)
+            .with_valid_range(15.0, 30.0)
+            .is_err());
+    }
 }
@@ -0,0 +1,187 @@
+//! Battery and radio state of wireless devices.
+
+use crate::error::SmartHomeError;
+use serde::{Deserialize, Serialize};
+
+/// Default battery level, in percent, at or below which a battery counts as low.
+pub const DEFAULT_LOW_BATTERY: u8 = 20;
+
+/// Battery level and signal strength last reported by a wireless device.
+///
+/// Both are `None` until the device reports them (mains-powered devices never
+/// report a battery level).  Stored next to the device fields as `battery`,
+/// `signal_dbm` and `low_battery`, each left out when unset or default.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::Thermometer;
+///
+/// let mut sensor = Thermometer::new("Bedroom", 19.0);
+/// sensor.wireless_mut().set_battery(Some(12))?;
+/// assert!(sensor.wireless().is_battery_low());
+/// assert_eq!(sensor.wireless().warning().as_deref(), Some("battery low (12%)"));
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wireless {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    battery: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signal_dbm: Option<i16>,
+    #[serde(
+        default = "default_low_battery",
+        skip_serializing_if = "is_default_low_battery"
+    )]
+    low_battery: u8,
+}
+
+impl Wireless {
+    /// Returns the battery level in percent, if known.
+    pub fn battery(&self) -> Option<u8> {
+        self.battery
+    }
+
+    /// Sets the battery level in percent, or `None` if unknown.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if the level is above 100.
+    pub fn set_battery(&mut self, percent: Option<u8>) -> Result<(), SmartHomeError> {
+        if let Some(percent) = percent {
+            check_percent("battery level", percent)?;
+        }
+        self.battery = percent;
+        Ok(())
+    }
+
+    /// Returns the received signal strength in dBm, if known.
+    pub fn signal_dbm(&self) -> Option<i16> {
+        self.signal_dbm
+    }
+
+    /// Sets the received signal strength in dBm, or `None` if unknown.
+    pub fn set_signal_dbm(&mut self, dbm: Option<i16>) {
+        self.signal_dbm = dbm;
+    }
+
+    /// Returns the battery level, in percent, at or below which the battery is low.
+    pub fn low_battery_threshold(&self) -> u8 {
+        self.low_battery
+    }
+
+    /// Sets the low-battery threshold in percent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if the threshold is above 100.
+    pub fn set_low_battery_threshold(&mut self, percent: u8) -> Result<(), SmartHomeError> {
+        check_percent("low-battery threshold", percent)?;
+        self.low_battery = percent;
+        Ok(())
+    }
+
+    /// Returns `true` if the battery level is known and at or below the threshold.
+    pub fn is_battery_low(&self) -> bool {
+        self.battery.is_some_and(|level| level <= self.low_battery)
+    }
+
+    /// Returns a warning to show the user, if any.
+    pub fn warning(&self) -> Option<String> {
+        match self.battery {
+            Some(level) if self.is_battery_low() => Some(format!("battery low ({}%)", level)),
+            _ => None,
+        }
+    }
+
+    /// Returns e.g. `" (battery: 80%, signal: -67 dBm)"`, or an empty string
+    /// if nothing is known, followed by `" [LOW BATTERY]"` when low.
+    pub(crate) fn report_suffix(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(level) = self.battery {
+            parts.push(format!("battery: {}%", level));
+        }
+        if let Some(dbm) = self.signal_dbm {
+            parts.push(format!("signal: {} dBm", dbm));
+        }
+        let mut s = String::new();
+        if !parts.is_empty() {
+            s = format!(" ({})", parts.join(", "));
+        }
+        if self.is_battery_low() {
+            s.push_str(" [LOW BATTERY]");
+        }
+        s
+    }
+
+    /// Checks values that came from a file.
+    pub(crate) fn validate(&self) -> Result<(), SmartHomeError> {
+        if let Some(level) = self.battery {
+            check_percent("battery level", level)?;
+        }
+        check_percent("low-battery threshold", self.low_battery)
+    }
+}
+
+impl Default for Wireless {
+    /// Nothing reported yet, with the [default threshold][DEFAULT_LOW_BATTERY].
+    fn default() -> Self {
+        Self {
+            battery: None,
+            signal_dbm: None,
+            low_battery: DEFAULT_LOW_BATTERY,
+        }
+    }
+}
+
+fn check_percent(what: &str, percent: u8) -> Result<(), SmartHomeError> {
+    if percent > 100 {
+        return Err(SmartHomeError::InvalidValue(format!(
+            "{} {}% is above 100%",
+            what, percent
+        )));
+    }
+    Ok(())
+}
+
+fn default_low_battery() -> u8 {
+    DEFAULT_LOW_BATTERY
+}
+
+fn is_default_low_battery(percent: &u8) -> bool {
+    *percent == DEFAULT_LOW_BATTERY
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_low_battery() {
+        let mut wireless = Wireless::default();
+        assert!(!wireless.is_battery_low());
+        assert_eq!(wireless.report_suffix(), "");
+
+        wireless.set_battery(Some(20)).unwrap();
+        wireless.set_signal_dbm(Some(-80));
+        assert!(wireless.is_battery_low());
+        assert_eq!(
+            wireless.report_suffix(),
+            " (battery: 20%, signal: -80 dBm) [LOW BATTERY]"
+        );
+
+        wireless.set_low_battery_threshold(10).unwrap();
+        assert_eq!(wireless.warning(), None);
+        assert_eq!(wireless.report_suffix(), " (battery: 20%, signal: -80 dBm)");
+    }
+
+    #[test]
+    fn test_rejects_percentages_above_100() {
+        let mut wireless = Wireless::default();
+        assert!(wireless.set_battery(Some(101)).is_err());
+        assert!(wireless.set_low_battery_threshold(150).is_err());
+        assert_eq!(wireless, Wireless::default());
+    }
+}
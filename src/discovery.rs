@@ -0,0 +1,313 @@
+//! Device discovery on the local network over UDP broadcast.
+//!
+//! Device servers run an [`Announcer`], which periodically sends a JSON
+//! [`Announcement`] to the broadcast address (or any other target) on
+//! [`DISCOVERY_PORT`]:
+//!
+//! ```text
+//! {"service": "smart_home", "id": "kettle-01", "endpoint": "192.168.1.20:7000",
+//!  "device": {"kind": "socket", "name": "Kettle", ...}}
+//! ```
+//!
+//! A [`DiscoveryListener`] collects announcements into [`Candidate`]s, which
+//! can be assigned to a room with [`Candidate::into_command`].
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::smart_device::SmartDevice;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// UDP port announcements are sent to by default.
+pub const DISCOVERY_PORT: u16 = 47800;
+
+/// Value of the `service` field; datagrams with another value are ignored.
+const SERVICE: &str = "smart_home";
+
+/// Largest announcement a listener accepts, in bytes.
+const MAX_DATAGRAM: usize = 64 * 1024;
+
+/// Returns the LAN broadcast target for [`DISCOVERY_PORT`].
+pub fn broadcast_addr() -> SocketAddr {
+    (Ipv4Addr::BROADCAST, DISCOVERY_PORT).into()
+}
+
+/// What a device server says about itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Announcement {
+    service: String,
+    /// Stable identifier of the device server, e.g. a serial number.
+    pub id: String,
+    /// Address the device server accepts connections on.
+    pub endpoint: SocketAddr,
+    /// The device with its current state.
+    pub device: SmartDevice,
+}
+
+impl Announcement {
+    /// Creates an announcement for `device` served at `endpoint`.
+    pub fn new(
+        id: impl Into<String>,
+        endpoint: SocketAddr,
+        device: impl Into<SmartDevice>,
+    ) -> Self {
+        Self {
+            service: SERVICE.to_string(),
+            id: id.into(),
+            endpoint,
+            device: device.into(),
+        }
+    }
+}
+
+/// A device found on the network, not yet part of any home.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// The device's announcement.
+    pub announcement: Announcement,
+    /// Address the announcement came from.
+    pub source: SocketAddr,
+}
+
+impl Candidate {
+    /// Returns the command that adds this device to `room` under `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smart_home::discovery::{Announcement, Candidate};
+    /// use smart_home::{Room, SmartHome, Socket};
+    ///
+    /// let mut home = SmartHome::new("Flat");
+    /// home.add_room("hall", Room::new("Hall"))?;
+    ///
+    /// let addr = "192.168.1.20:7000".parse().unwrap();
+    /// let candidate = Candidate {
+    ///     announcement: Announcement::new("lamp-01", addr, Socket::new("Lamp", 60.0)),
+    ///     source: addr,
+    /// };
+    /// candidate.into_command("hall", "lamp").apply(&mut home)?;
+    /// assert!(home.get_socket("hall", "lamp").is_ok());
+    /// # Ok::<(), smart_home::SmartHomeError>(())
+    /// ```
+    pub fn into_command(self, room: impl Into<String>, key: impl Into<String>) -> Command {
+        Command::AddDevice {
+            room: room.into(),
+            device: key.into(),
+            data: self.announcement.device,
+        }
+    }
+}
+
+// ── Listener ──────────────────────────────────────────────────────────────────
+
+/// Collects announcements sent to one UDP address.
+pub struct DiscoveryListener {
+    socket: UdpSocket,
+}
+
+impl DiscoveryListener {
+    /// Listens on `addr`; use `("0.0.0.0", DISCOVERY_PORT)` to hear LAN broadcasts.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the address cannot be bound.
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self, SmartHomeError> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+        })
+    }
+
+    /// Returns the address the listener is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.socket
+            .local_addr()
+            .expect("socket is bound to an address")
+    }
+
+    /// Listens for `duration` and returns every device heard, sorted by ID.
+    ///
+    /// A device announcing itself more than once is listed once, with its
+    /// latest announcement.  Datagrams that are not announcements are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if receiving fails.
+    pub fn collect(&self, duration: Duration) -> Result<Vec<Candidate>, SmartHomeError> {
+        let deadline = Instant::now() + duration;
+        let mut found = BTreeMap::new();
+        let mut buf = vec![0; MAX_DATAGRAM];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            self.socket.set_read_timeout(Some(left))?;
+            let (len, source) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+                Err(e) => return Err(e.into()),
+            };
+            let Ok(announcement) = serde_json::from_slice::<Announcement>(&buf[..len]) else {
+                continue;
+            };
+            if announcement.service == SERVICE {
+                found.insert(
+                    announcement.id.clone(),
+                    Candidate {
+                        announcement,
+                        source,
+                    },
+                );
+            }
+        }
+        Ok(found.into_values().collect())
+    }
+}
+
+// ── Announcer ─────────────────────────────────────────────────────────────────
+
+/// Sends a device's [`Announcement`] to a discovery target.
+pub struct Announcer {
+    socket: UdpSocket,
+    announcement: Announcement,
+    target: SocketAddr,
+}
+
+impl Announcer {
+    /// Prepares announcements to `target`, usually [`broadcast_addr`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if no UDP socket can be opened.
+    pub fn new(announcement: Announcement, target: SocketAddr) -> Result<Self, SmartHomeError> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        Ok(Self {
+            socket,
+            announcement,
+            target,
+        })
+    }
+
+    /// Sends the announcement once.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if sending fails.
+    pub fn announce(&self) -> Result<(), SmartHomeError> {
+        let payload = serde_json::to_vec(&self.announcement)?;
+        self.socket.send_to(&payload, self.target)?;
+        Ok(())
+    }
+
+    /// Announces every `interval` on a background thread until the handle is
+    /// shut down or dropped.
+    pub fn spawn(self, interval: Duration) -> AnnouncerHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            while !flag.load(Ordering::SeqCst) {
+                // A lost datagram is simply repeated on the next round.
+                let _ = self.announce();
+                thread::park_timeout(interval);
+            }
+        });
+        AnnouncerHandle {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Handle to an announcer started with [`Announcer::spawn`].
+pub struct AnnouncerHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AnnouncerHandle {
+    /// Stops announcing and waits for the thread to exit.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for AnnouncerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Socket, Thermometer};
+
+    fn endpoint(port: u16) -> SocketAddr {
+        (Ipv4Addr::LOCALHOST, port).into()
+    }
+
+    #[test]
+    fn test_collects_and_deduplicates() {
+        let listener = DiscoveryListener::bind("127.0.0.1:0").unwrap();
+        let target = listener.local_addr();
+        let kettle = Announcer::new(
+            Announcement::new("kettle-01", endpoint(7001), Socket::new("Kettle", 2000.0)),
+            target,
+        )
+        .unwrap();
+        let sensor = Announcer::new(
+            Announcement::new(
+                "sensor-01",
+                endpoint(7002),
+                Thermometer::new("Sensor", 21.0),
+            ),
+            target,
+        )
+        .unwrap();
+        kettle.announce().unwrap();
+        sensor.announce().unwrap();
+        kettle.announce().unwrap();
+
+        let found = listener.collect(Duration::from_millis(200)).unwrap();
+        let ids: Vec<_> = found.iter().map(|c| c.announcement.id.as_str()).collect();
+        assert_eq!(ids, ["kettle-01", "sensor-01"]);
+        assert_eq!(found[1].announcement.device.kind(), "thermometer");
+        assert_eq!(found[0].announcement.endpoint, endpoint(7001));
+    }
+
+    #[test]
+    fn test_ignores_foreign_datagrams() {
+        let listener = DiscoveryListener::bind("127.0.0.1:0").unwrap();
+        let other = UdpSocket::bind("127.0.0.1:0").unwrap();
+        other.send_to(b"hello", listener.local_addr()).unwrap();
+        let mut foreign =
+            serde_json::to_value(Announcement::new("x", endpoint(1), Socket::new("X", 1.0)))
+                .unwrap();
+        foreign["service"] = "other".into();
+        other
+            .send_to(foreign.to_string().as_bytes(), listener.local_addr())
+            .unwrap();
+        assert!(listener
+            .collect(Duration::from_millis(100))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,23 +1,136 @@
 //! Error types for the smart home library.
 
 use std::fmt;
+use std::io;
 
-/// Errors that can occur when accessing rooms or devices in a smart home.
+/// Errors that can occur when accessing or modifying rooms and devices in a smart home.
 #[derive(Debug)]
 pub enum SmartHomeError {
     /// The requested room was not found.
     RoomNotFound(String),
-    /// The requested device was not found.
-    DeviceNotFound(String),
+    /// The requested device was not found in the given room.
+    DeviceNotFound {
+        /// Key of the room that was searched.
+        room: String,
+        /// Key of the missing device.
+        device: String,
+    },
+    /// A room with this key already exists.
+    DuplicateRoom(String),
+    /// A device with this key already exists in the room.
+    DuplicateDevice {
+        /// Key of the room.  Empty when reported by
+        /// [`Room::add_device`][crate::Room::add_device], since a room does not
+        /// know the key it is stored under; [`SmartHome`][crate::SmartHome]
+        /// methods fill it in.
+        room: String,
+        /// The device key that is taken.
+        device: String,
+    },
+    /// The device exists but is of a different kind than requested.
+    WrongDeviceKind {
+        /// Key of the device.
+        device: String,
+        /// Kind that was requested (e.g. `"socket"`).
+        expected: &'static str,
+        /// Kind the device actually is.
+        found: &'static str,
+    },
+    /// The requested device group was not found.
+    GroupNotFound(String),
+    /// A device group with this name already exists.
+    DuplicateGroup(String),
+    /// The requested home was not found in a portfolio.
+    HomeNotFound(String),
+    /// A home with this key already exists in the portfolio.
+    DuplicateHome(String),
+    /// No alert has this number.
+    AlertNotFound(u64),
+    /// The user is not allowed to perform the operation.
+    PermissionDenied {
+        /// Name of the user.
+        user: String,
+        /// Name of the missing permission (e.g. `"operate"`).
+        permission: &'static str,
+        /// Path of the room or device, or the home name.
+        target: String,
+    },
+    /// A remote peer could not be authenticated, or sent a frame that was
+    /// altered or replayed.
+    AuthenticationFailed(String),
+    /// A remote server reported that a command failed; holds its message.
+    Remote(String),
+    /// A value failed validation (NaN, negative power, impossible temperature, ...).
+    InvalidValue(String),
+    /// An I/O error occurred while talking to a file or a remote device.
+    Io(io::Error),
 }
 
 impl fmt::Display for SmartHomeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SmartHomeError::RoomNotFound(name) => write!(f, "Room '{}' not found", name),
-            SmartHomeError::DeviceNotFound(name) => write!(f, "Device '{}' not found", name),
+            SmartHomeError::DeviceNotFound { room, device } => {
+                write!(f, "Device '{}' not found in room '{}'", device, room)
+            }
+            SmartHomeError::DuplicateRoom(name) => write!(f, "Room '{}' already exists", name),
+            SmartHomeError::DuplicateDevice { room, device } if room.is_empty() => {
+                write!(f, "Device '{}' already exists in the room", device)
+            }
+            SmartHomeError::DuplicateDevice { room, device } => {
+                write!(f, "Device '{}' already exists in room '{}'", device, room)
+            }
+            SmartHomeError::WrongDeviceKind {
+                device,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Device '{}' is a {}, expected a {}",
+                device, found, expected
+            ),
+            SmartHomeError::GroupNotFound(name) => write!(f, "Group '{}' not found", name),
+            SmartHomeError::DuplicateGroup(name) => write!(f, "Group '{}' already exists", name),
+            SmartHomeError::HomeNotFound(key) => write!(f, "Home '{}' not found", key),
+            SmartHomeError::DuplicateHome(key) => write!(f, "Home '{}' already exists", key),
+            SmartHomeError::AlertNotFound(id) => write!(f, "Alert #{} not found", id),
+            SmartHomeError::PermissionDenied {
+                user,
+                permission,
+                target,
+            } => write!(
+                f,
+                "User '{}' lacks the '{}' permission on '{}'",
+                user, permission, target
+            ),
+            SmartHomeError::AuthenticationFailed(reason) => {
+                write!(f, "Authentication failed: {}", reason)
+            }
+            SmartHomeError::Remote(msg) => write!(f, "Remote error: {}", msg),
+            SmartHomeError::InvalidValue(msg) => write!(f, "Invalid value: {}", msg),
+            SmartHomeError::Io(err) => write!(f, "I/O error: {}", err),
         }
     }
 }
 
-impl std::error::Error for SmartHomeError {}
+impl std::error::Error for SmartHomeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SmartHomeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SmartHomeError {
+    fn from(err: io::Error) -> Self {
+        SmartHomeError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SmartHomeError {
+    /// Parse errors become [`SmartHomeError::Io`] with [`io::ErrorKind::InvalidData`].
+    fn from(err: serde_json::Error) -> Self {
+        SmartHomeError::Io(io::Error::from(err))
+    }
+}
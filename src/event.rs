@@ -0,0 +1,355 @@
+//! State-change events and a simple broadcast bus.
+
+use crate::group::Group;
+use crate::room::Room;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Something that changed in a home.
+///
+/// Serialized with an `"event"` tag, e.g.
+/// `{"event": "socket_switched", "room": "kitchen", "device": "kettle", "on": true, "power": 2000.0}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HomeEvent {
+    /// A room was added.
+    RoomAdded {
+        /// Room key.
+        room: String,
+        /// The new room with its devices.
+        data: Room,
+    },
+    /// A room was removed together with its devices.
+    RoomRemoved {
+        /// Room key.
+        room: String,
+    },
+    /// A device was added to a room.
+    DeviceAdded {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// The new device.
+        data: SmartDevice,
+    },
+    /// A device was removed from a room.
+    DeviceRemoved {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+    },
+    /// A room's key changed.
+    RoomRenamed {
+        /// Previous room key.
+        room: String,
+        /// New room key.
+        to: String,
+    },
+    /// A device's key changed within its room.
+    DeviceRenamed {
+        /// Room key.
+        room: String,
+        /// Previous device key.
+        device: String,
+        /// New device key.
+        to: String,
+    },
+    /// A device moved to another room and key.
+    DeviceMoved {
+        /// Previous room key.
+        room: String,
+        /// Previous device key.
+        device: String,
+        /// New room key.
+        to_room: String,
+        /// New device key.
+        to_device: String,
+    },
+    /// A room was placed on a floor, or on none.
+    FloorChanged {
+        /// Room key.
+        room: String,
+        /// New floor name, if any.
+        floor: Option<String>,
+    },
+    /// A room was added to a zone or taken out of it.
+    ZoneChanged {
+        /// Room key.
+        room: String,
+        /// Zone name.
+        zone: String,
+        /// Whether the room is now in the zone.
+        in_zone: bool,
+    },
+    /// A device group was added.
+    GroupAdded {
+        /// Group name.
+        group: String,
+        /// The new group with its members.
+        data: Group,
+    },
+    /// A device group was removed.
+    GroupRemoved {
+        /// Group name.
+        group: String,
+    },
+    /// A device was added to a group or taken out of it.
+    GroupMembershipChanged {
+        /// Group name.
+        group: String,
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// Whether the device is now a member.
+        member: bool,
+    },
+    /// A tag was set on a room or device, or cleared from it.
+    TagChanged {
+        /// Room key.
+        room: String,
+        /// Device key, or `None` for the room itself.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        /// The tag.
+        tag: String,
+        /// Whether the tag is now set.
+        tagged: bool,
+    },
+    /// An attribute of a room or device was set or removed.
+    AttributeChanged {
+        /// Room key.
+        room: String,
+        /// Device key, or `None` for the room itself.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        /// Attribute name.
+        key: String,
+        /// New value, or `None` if the attribute was removed.
+        value: Option<String>,
+    },
+    /// The whole home was replaced, or changed in ways not described by
+    /// other events.
+    HomeReplaced {
+        /// The home as it is now.
+        data: SmartHome,
+    },
+    /// A socket was switched on or off.
+    SocketSwitched {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// New state.
+        on: bool,
+        /// Power draw in the new state, in watts.
+        power: f32,
+    },
+    /// A thermometer reported a new temperature.
+    TemperatureChanged {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// New reading in Celsius.
+        temperature: f32,
+    },
+    /// A wireless device reported its battery level and signal strength.
+    WirelessUpdated {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// Battery level in percent, if known.
+        battery: Option<u8>,
+        /// Signal strength in dBm, if known.
+        signal_dbm: Option<i16>,
+        /// Whether the battery is at or below the device's low-battery threshold.
+        battery_low: bool,
+    },
+}
+
+impl HomeEvent {
+    /// Returns the key of the room the event concerns, or `None` for group
+    /// and whole-home events.
+    pub fn room(&self) -> Option<&str> {
+        let room = match self {
+            HomeEvent::GroupAdded { .. }
+            | HomeEvent::GroupRemoved { .. }
+            | HomeEvent::HomeReplaced { .. } => return None,
+            HomeEvent::RoomAdded { room, .. }
+            | HomeEvent::RoomRemoved { room }
+            | HomeEvent::DeviceAdded { room, .. }
+            | HomeEvent::DeviceRemoved { room, .. }
+            | HomeEvent::RoomRenamed { room, .. }
+            | HomeEvent::DeviceRenamed { room, .. }
+            | HomeEvent::DeviceMoved { room, .. }
+            | HomeEvent::FloorChanged { room, .. }
+            | HomeEvent::ZoneChanged { room, .. }
+            | HomeEvent::GroupMembershipChanged { room, .. }
+            | HomeEvent::TagChanged { room, .. }
+            | HomeEvent::AttributeChanged { room, .. }
+            | HomeEvent::SocketSwitched { room, .. }
+            | HomeEvent::TemperatureChanged { room, .. }
+            | HomeEvent::WirelessUpdated { room, .. } => room,
+        };
+        Some(room)
+    }
+
+    /// Returns the key of the device the event concerns, or `None` for room,
+    /// group and whole-home events.
+    pub fn device(&self) -> Option<&str> {
+        match self {
+            HomeEvent::RoomAdded { .. }
+            | HomeEvent::RoomRemoved { .. }
+            | HomeEvent::RoomRenamed { .. }
+            | HomeEvent::FloorChanged { .. }
+            | HomeEvent::ZoneChanged { .. }
+            | HomeEvent::GroupAdded { .. }
+            | HomeEvent::GroupRemoved { .. }
+            | HomeEvent::HomeReplaced { .. } => None,
+            HomeEvent::TagChanged { device, .. } | HomeEvent::AttributeChanged { device, .. } => {
+                device.as_deref()
+            }
+            HomeEvent::DeviceAdded { device, .. }
+            | HomeEvent::DeviceRemoved { device, .. }
+            | HomeEvent::DeviceRenamed { device, .. }
+            | HomeEvent::DeviceMoved { device, .. }
+            | HomeEvent::GroupMembershipChanged { device, .. }
+            | HomeEvent::SocketSwitched { device, .. }
+            | HomeEvent::TemperatureChanged { device, .. }
+            | HomeEvent::WirelessUpdated { device, .. } => Some(device),
+        }
+    }
+
+    /// Returns `"room/device"`, just `"room"` for room events,
+    /// `"group:<name>"` for group events or `"home"` for whole-home events.
+    pub fn path(&self) -> String {
+        match (self.room(), self.device(), self.group()) {
+            (Some(room), Some(device), _) => format!("{}/{}", room, device),
+            (Some(room), None, _) => room.to_string(),
+            (None, _, Some(group)) => format!("group:{}", group),
+            (None, _, None) => "home".to_string(),
+        }
+    }
+
+    /// Returns the name of the group the event concerns, if any.
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            HomeEvent::GroupAdded { group, .. }
+            | HomeEvent::GroupRemoved { group }
+            | HomeEvent::GroupMembershipChanged { group, .. } => Some(group),
+            _ => None,
+        }
+    }
+}
+
+/// Broadcasts [`HomeEvent`]s to any number of subscribers.
+///
+/// Each subscriber gets its own channel; subscribers that dropped their
+/// receiver are forgotten on the next publish.  Clones share the subscriber list.
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<HomeEvent>>>>,
+}
+
+impl EventBus {
+    /// Creates a bus without subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a receiver for all events published from now on.
+    pub fn subscribe(&self) -> Receiver<HomeEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.lock().push(tx);
+        rx
+    }
+
+    /// Sends `event` to every live subscriber.
+    pub fn publish(&self, event: &HomeEvent) {
+        self.lock().retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// Returns the number of subscribers that were alive at the last publish.
+    pub fn subscriber_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Sender<HomeEvent>>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn switched(on: bool) -> HomeEvent {
+        HomeEvent::SocketSwitched {
+            room: "kitchen".into(),
+            device: "kettle".into(),
+            on,
+            power: if on { 2000.0 } else { 0.0 },
+        }
+    }
+
+    #[test]
+    fn test_publish_reaches_all_subscribers() {
+        let bus = EventBus::new();
+        let a = bus.subscribe();
+        let b = bus.clone().subscribe();
+        bus.publish(&switched(true));
+        assert!(matches!(
+            a.try_recv(),
+            Ok(HomeEvent::SocketSwitched { on: true, .. })
+        ));
+        assert!(matches!(
+            b.try_recv(),
+            Ok(HomeEvent::SocketSwitched { on: true, .. })
+        ));
+    }
+
+    #[test]
+    fn test_dropped_subscribers_are_pruned() {
+        let bus = EventBus::new();
+        let a = bus.subscribe();
+        drop(bus.subscribe());
+        bus.publish(&switched(false));
+        assert_eq!(bus.subscriber_count(), 1);
+        assert!(a.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_serialized_tag_and_accessors() {
+        let event = switched(true);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "socket_switched");
+        assert_eq!(json["room"], "kitchen");
+        assert_eq!(event.room(), Some("kitchen"));
+        assert_eq!(event.device(), Some("kettle"));
+        assert_eq!(event.path(), "kitchen/kettle");
+        assert_eq!(HomeEvent::RoomRemoved { room: "x".into() }.device(), None);
+        assert_eq!(HomeEvent::RoomRemoved { room: "x".into() }.path(), "x");
+        let removed = HomeEvent::GroupRemoved {
+            group: "lamps".into(),
+        };
+        assert_eq!(
+            (removed.room(), removed.path()),
+            (None, "group:lamps".into())
+        );
+        let replaced = HomeEvent::HomeReplaced {
+            data: SmartHome::new("Flat"),
+        };
+        assert_eq!(replaced.path(), "home");
+    }
+}
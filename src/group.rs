@@ -0,0 +1,330 @@
+//! Named groups of devices spanning rooms.
+//!
+//! A [`Group`] lists devices by room and device key, e.g. "all lamps" or "all
+//! heaters", and is stored in the home with [`SmartHome::add_group`].  Bulk
+//! operations on a group act on every member they can and return a
+//! [`GroupOutcome`] listing the members they could not act on, such as devices
+//! that were removed since they were added to the group or devices of the
+//! wrong kind.
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::report::Report;
+use crate::smart_home::SmartHome;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A device in a group, identified by its room and device keys.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Member {
+    /// Room key.
+    pub room: String,
+    /// Device key.
+    pub device: String,
+}
+
+impl Member {
+    /// Creates a member for the device at `room`/`device`.
+    pub fn new(room: impl Into<String>, device: impl Into<String>) -> Self {
+        Self {
+            room: room.into(),
+            device: device.into(),
+        }
+    }
+}
+
+impl fmt::Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.room, self.device)
+    }
+}
+
+/// A member a bulk operation could not act on.
+#[derive(Debug)]
+pub struct MemberFailure {
+    /// The member.
+    pub member: Member,
+    /// Why the operation failed for it.
+    pub error: SmartHomeError,
+}
+
+/// Result of a bulk operation on a [`Group`].
+#[derive(Debug)]
+pub struct GroupOutcome<T> {
+    /// The result over all members the operation succeeded for.
+    pub value: T,
+    /// Members the operation failed for, in member order.
+    pub failures: Vec<MemberFailure>,
+}
+
+impl<T> GroupOutcome<T> {
+    /// Returns `true` if the operation succeeded for every member.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl<T> Report for GroupOutcome<T> {
+    /// Lists the failed members, one per line.
+    fn report(&self) -> String {
+        if self.failures.is_empty() {
+            return "All members succeeded\n".to_string();
+        }
+        let mut s = format!("{} member(s) failed:\n", self.failures.len());
+        for failure in &self.failures {
+            s.push_str(&format!("  [{}] {}\n", failure.member, failure.error));
+        }
+        s
+    }
+}
+
+/// A named set of devices, possibly in different rooms.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::group::Group;
+/// use smart_home::{room, SmartHome, Socket};
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+/// home.add_room("study", room!("Study", "lamp" => Socket::new("Desk lamp", 40.0)))?;
+/// home.add_group("lamps", Group::new())?;
+/// home.add_to_group("lamps", "hall", "lamp")?;
+/// home.add_to_group("lamps", "study", "lamp")?;
+///
+/// let lamps = home.get_group("lamps").unwrap().clone();
+/// for command in lamps.switch_sockets(&home, true).value {
+///     command.apply(&mut home)?;
+/// }
+/// assert_eq!(lamps.power(&home).value, 100.0);
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Group {
+    members: BTreeSet<Member>,
+}
+
+impl Group {
+    /// Creates an empty group.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the members sorted by room and device key.
+    pub fn members(&self) -> impl Iterator<Item = &Member> {
+        self.members.iter()
+    }
+
+    /// Returns the number of members.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns `true` if the group has no members.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Returns `true` if the device at `room`/`device` is a member.
+    pub fn contains(&self, room: &str, device: &str) -> bool {
+        self.members.contains(&Member::new(room, device))
+    }
+
+    /// Adds a member without checking that the device exists; returns `false`
+    /// if it already was a member.
+    ///
+    /// [`SmartHome::add_to_group`] checks the device first.
+    pub fn insert(&mut self, member: Member) -> bool {
+        self.members.insert(member)
+    }
+
+    /// Removes a member; returns `false` if it was not a member.
+    pub fn remove(&mut self, room: &str, device: &str) -> bool {
+        self.members.remove(&Member::new(room, device))
+    }
+
+    /// Returns the commands that switch every socket in the group on or off.
+    ///
+    /// Members that are missing or not sockets are reported as failures.
+    pub fn switch_sockets(&self, home: &SmartHome, on: bool) -> GroupOutcome<Vec<Command>> {
+        self.for_each_member(|member| {
+            home.get_socket(&member.room, &member.device)?;
+            let (room, device) = (member.room.clone(), member.device.clone());
+            Ok(if on {
+                Command::TurnOn { room, device }
+            } else {
+                Command::TurnOff { room, device }
+            })
+        })
+    }
+
+    /// Returns the total power drawn by the group's sockets, in watts.
+    ///
+    /// Members that are missing or not sockets are reported as failures.
+    pub fn power(&self, home: &SmartHome) -> GroupOutcome<f32> {
+        let outcome = self
+            .for_each_member(|member| Ok(home.get_socket(&member.room, &member.device)?.power()));
+        GroupOutcome {
+            value: outcome.value.iter().fold(0.0, |total, power| total + power),
+            failures: outcome.failures,
+        }
+    }
+
+    /// Returns the mean reading of the group's thermometers, or `None` if
+    /// none could be read.
+    ///
+    /// Members that are missing or not thermometers are reported as failures.
+    pub fn average_temperature(&self, home: &SmartHome) -> GroupOutcome<Option<f32>> {
+        let outcome = self.for_each_member(|member| {
+            Ok(home
+                .get_thermometer(&member.room, &member.device)?
+                .temperature())
+        });
+        let readings = &outcome.value;
+        let value =
+            (!readings.is_empty()).then(|| readings.iter().sum::<f32>() / readings.len() as f32);
+        GroupOutcome {
+            value,
+            failures: outcome.failures,
+        }
+    }
+
+    /// Replaces every member for which `f` returns a new one; used when keys change.
+    pub(crate) fn rekey(&mut self, mut f: impl FnMut(&Member) -> Option<Member>) {
+        self.members = std::mem::take(&mut self.members)
+            .into_iter()
+            .map(|member| f(&member).unwrap_or(member))
+            .collect();
+    }
+
+    fn for_each_member<T>(
+        &self,
+        mut f: impl FnMut(&Member) -> Result<T, SmartHomeError>,
+    ) -> GroupOutcome<Vec<T>> {
+        let mut outcome = GroupOutcome {
+            value: Vec::new(),
+            failures: Vec::new(),
+        };
+        for member in &self.members {
+            match f(member) {
+                Ok(value) => outcome.value.push(value),
+                Err(error) => outcome.failures.push(MemberFailure {
+                    member: member.clone(),
+                    error,
+                }),
+            }
+        }
+        outcome
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "kettle" => Socket::new("Kettle", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 22.0),
+            ),
+        )
+        .unwrap();
+        home.add_room(
+            "bedroom",
+            room!(
+                "Bedroom",
+                "heater" => Socket::new("Heater", 1000.0),
+                "sensor" => Thermometer::new("Sensor", 18.0),
+            ),
+        )
+        .unwrap();
+        home
+    }
+
+    fn group(members: &[(&str, &str)]) -> Group {
+        let mut group = Group::new();
+        for (room, device) in members {
+            group.insert(Member::new(*room, *device));
+        }
+        group
+    }
+
+    #[test]
+    fn test_switch_and_power() {
+        let mut home = make_home();
+        let group = group(&[
+            ("kitchen", "kettle"),
+            ("bedroom", "heater"),
+            ("bedroom", "lamp"),
+            ("kitchen", "sensor"),
+        ]);
+
+        let outcome = group.switch_sockets(&home, true);
+        assert_eq!(outcome.value.len(), 2);
+        for command in outcome.value {
+            command.apply(&mut home).unwrap();
+        }
+        let failed: Vec<String> = outcome
+            .failures
+            .iter()
+            .map(|f| f.member.to_string())
+            .collect();
+        assert_eq!(failed, ["bedroom/lamp", "kitchen/sensor"]);
+        assert!(matches!(
+            outcome.failures[0].error,
+            SmartHomeError::DeviceNotFound { .. }
+        ));
+        assert!(matches!(
+            outcome.failures[1].error,
+            SmartHomeError::WrongDeviceKind { .. }
+        ));
+
+        let power = group.power(&home);
+        assert_eq!(power.value, 3000.0);
+        assert!(!power.is_complete());
+    }
+
+    #[test]
+    fn test_average_temperature() {
+        let home = make_home();
+        let sensors = group(&[("kitchen", "sensor"), ("bedroom", "sensor")]);
+        let outcome = sensors.average_temperature(&home);
+        assert_eq!(outcome.value, Some(20.0));
+        assert!(outcome.is_complete());
+
+        let none = group(&[("attic", "sensor")]).average_temperature(&home);
+        assert_eq!(none.value, None);
+        assert_eq!(none.failures.len(), 1);
+    }
+
+    #[test]
+    fn test_report_lists_failures() {
+        let home = make_home();
+        let outcome = group(&[("kitchen", "kettle"), ("garage", "charger")]).power(&home);
+        let report = outcome.report();
+        assert!(report.starts_with("1 member(s) failed:"));
+        assert!(report.contains("[garage/charger] Room 'garage' not found"));
+        assert_eq!(
+            group(&[("kitchen", "kettle")]).power(&home).report(),
+            "All members succeeded\n"
+        );
+    }
+
+    #[test]
+    fn test_serializes_as_member_list() {
+        let group = group(&[("kitchen", "kettle")]);
+        let json = serde_json::to_string(&group).unwrap();
+        assert_eq!(json, r#"[{"room":"kitchen","device":"kettle"}]"#);
+        assert_eq!(serde_json::from_str::<Group>(&json).unwrap(), group);
+    }
+}
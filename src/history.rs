@@ -0,0 +1,322 @@
+//! Undo/redo history of home edits.
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::smart_home::SmartHome;
+
+/// Default number of edits that can be undone.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// Commands applied together, with the commands that revert them in the order
+/// they must be applied.
+#[derive(Debug, Clone)]
+struct Edit {
+    commands: Vec<Command>,
+    inverses: Vec<Command>,
+}
+
+/// Undo and redo stacks for [`Command`]s applied to one [`SmartHome`].
+///
+/// Every edit must go through [`History::execute`]; changing the home in other
+/// ways can make older edits impossible to undo, which is then reported as an
+/// error and leaves both stacks as they were.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::history::History;
+/// use smart_home::{room, Command, SmartHome, Socket};
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+/// let mut history = History::new();
+///
+/// history.execute(&mut home, Command::RemoveRoom { room: "hall".into() })?;
+/// assert_eq!(home.room_count(), 0);
+///
+/// assert_eq!(history.undo(&mut home)?.len(), 1);
+/// assert!(home.get_socket("hall", "lamp").is_ok());
+///
+/// history.redo(&mut home)?;
+/// assert_eq!(home.room_count(), 0);
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::with_limit(DEFAULT_LIMIT)
+    }
+}
+
+impl History {
+    /// Creates an empty history keeping up to [`DEFAULT_LIMIT`] edits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty history keeping up to `limit` edits; older ones are
+    /// forgotten.
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Applies `command` to `home` and remembers how to undo it.
+    ///
+    /// Clears the redo stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`Command::apply`]; the history is unchanged then.
+    pub fn execute(
+        &mut self,
+        home: &mut SmartHome,
+        command: Command,
+    ) -> Result<HomeEvent, SmartHomeError> {
+        let (event, inverse) = command.clone().apply_reversible(home)?;
+        self.push(Edit {
+            commands: vec![command],
+            inverses: vec![inverse],
+        });
+        Ok(event)
+    }
+
+    /// Applies `commands` as one [transaction](SmartHome::transaction) that is
+    /// undone and redone as a single edit.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first command that fails; the home and the
+    /// history are unchanged then.
+    pub fn execute_all(
+        &mut self,
+        home: &mut SmartHome,
+        commands: Vec<Command>,
+    ) -> Result<Vec<HomeEvent>, SmartHomeError> {
+        let (events, mut inverses) = home.transaction(|staged| {
+            commands
+                .iter()
+                .map(|command| command.clone().apply_reversible(staged))
+                .collect::<Result<(Vec<_>, Vec<_>), _>>()
+        })?;
+        inverses.reverse();
+        self.push(Edit { commands, inverses });
+        Ok(events)
+    }
+
+    /// Reverts the most recent edit and returns its events; the list is empty
+    /// if there is nothing to undo.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from applying the inverse commands; the home and the
+    /// history are unchanged then.
+    pub fn undo(&mut self, home: &mut SmartHome) -> Result<Vec<HomeEvent>, SmartHomeError> {
+        Self::step(&mut self.undo, &mut self.redo, home, |edit| &edit.inverses)
+    }
+
+    /// Re-applies the most recently undone edit and returns its events; the
+    /// list is empty if there is nothing to redo.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from applying the commands; the home and the history
+    /// are unchanged then.
+    pub fn redo(&mut self, home: &mut SmartHome) -> Result<Vec<HomeEvent>, SmartHomeError> {
+        Self::step(&mut self.redo, &mut self.undo, home, |edit| &edit.commands)
+    }
+
+    /// Returns `true` if there is an edit to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns `true` if there is an edit to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets all edits, e.g. after the home was replaced.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push(&mut self, edit: Edit) {
+        self.undo.push(edit);
+        if self.undo.len() > self.limit {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    fn step(
+        from: &mut Vec<Edit>,
+        to: &mut Vec<Edit>,
+        home: &mut SmartHome,
+        pick: impl Fn(&Edit) -> &[Command],
+    ) -> Result<Vec<HomeEvent>, SmartHomeError> {
+        let Some(edit) = from.last() else {
+            return Ok(Vec::new());
+        };
+        let events = home.transaction(|staged| {
+            pick(edit)
+                .iter()
+                .map(|command| command.clone().apply(staged))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        to.extend(from.pop());
+        Ok(events)
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, storage, Socket, Thermometer};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "kettle" => Socket::new("Kettle", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 22.0),
+            ),
+        )
+        .unwrap();
+        home
+    }
+
+    fn toggle() -> Command {
+        Command::Toggle {
+            room: "kitchen".into(),
+            device: "kettle".into(),
+        }
+    }
+
+    #[test]
+    fn test_undo_redo_sequence() {
+        let mut home = make_home();
+        let original = storage::to_json(&home);
+        let mut history = History::new();
+
+        history.execute(&mut home, toggle()).unwrap();
+        history
+            .execute(
+                &mut home,
+                Command::SetTemperature {
+                    room: "kitchen".into(),
+                    device: "sensor".into(),
+                    temperature: 18.0,
+                },
+            )
+            .unwrap();
+        history
+            .execute(
+                &mut home,
+                Command::RemoveRoom {
+                    room: "kitchen".into(),
+                },
+            )
+            .unwrap();
+        let edited = storage::to_json(&home);
+
+        while !history.undo(&mut home).unwrap().is_empty() {}
+        assert_eq!(storage::to_json(&home), original);
+        assert!(!history.can_undo());
+
+        while !history.redo(&mut home).unwrap().is_empty() {}
+        assert_eq!(storage::to_json(&home), edited);
+    }
+
+    #[test]
+    fn test_new_edit_clears_redo() {
+        let mut home = make_home();
+        let mut history = History::new();
+        history.execute(&mut home, toggle()).unwrap();
+        history.undo(&mut home).unwrap();
+        assert!(history.can_redo());
+        history.execute(&mut home, toggle()).unwrap();
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_failures_leave_history_unchanged() {
+        let mut home = make_home();
+        let mut history = History::new();
+        assert!(history
+            .execute(
+                &mut home,
+                Command::RemoveRoom {
+                    room: "attic".into()
+                }
+            )
+            .is_err());
+        assert!(!history.can_undo());
+
+        history.execute(&mut home, toggle()).unwrap();
+        // Changed behind the history's back: the edit can no longer be undone.
+        home.remove_room("kitchen");
+        assert!(history.undo(&mut home).is_err());
+        assert!(history.can_undo());
+    }
+
+    #[test]
+    fn test_batch_is_one_edit() {
+        let mut home = make_home();
+        let mut history = History::new();
+        let batch = vec![
+            toggle(),
+            Command::RemoveDevice {
+                room: "kitchen".into(),
+                device: "kettle".into(),
+            },
+        ];
+        assert_eq!(history.execute_all(&mut home, batch).unwrap().len(), 2);
+        assert!(home.get_device("kitchen", "kettle").is_err());
+
+        assert_eq!(history.undo(&mut home).unwrap().len(), 2);
+        assert!(!home.get_socket("kitchen", "kettle").unwrap().is_on());
+        assert!(!history.can_undo());
+
+        // A failing batch leaves both the home and the history alone.
+        let failing = vec![
+            toggle(),
+            toggle(),
+            Command::RemoveRoom {
+                room: "attic".into(),
+            },
+        ];
+        assert!(history.execute_all(&mut home, failing).is_err());
+        assert!(!home.get_socket("kitchen", "kettle").unwrap().is_on());
+        assert!(history.can_redo());
+    }
+
+    #[test]
+    fn test_limit() {
+        let mut home = make_home();
+        let mut history = History::with_limit(2);
+        for _ in 0..3 {
+            history.execute(&mut home, toggle()).unwrap();
+        }
+        assert_eq!(history.undo(&mut home).unwrap().len(), 1);
+        assert_eq!(history.undo(&mut home).unwrap().len(), 1);
+        assert!(history.undo(&mut home).unwrap().is_empty());
+        // Three toggles, two undone: the socket is on.
+        assert!(home.get_socket("kitchen", "kettle").unwrap().is_on());
+    }
+}
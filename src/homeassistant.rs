@@ -0,0 +1,352 @@
+//! Home Assistant MQTT discovery.
+//!
+//! Available with the `mqtt` feature.  [`Discovery`] builds the retained config
+//! messages that make Home Assistant create entities for the topics published
+//! by [`MqttBridge`][crate::mqtt::MqttBridge]:
+//!
+//! * a [`Socket`][crate::Socket] becomes a `switch` plus a `sensor` for its power draw;
+//! * a [`Thermometer`][crate::Thermometer] becomes a temperature `sensor`.
+//!
+//! Each of our devices is one Home Assistant device, suggested to the area
+//! named after its room.  Unique IDs are derived from the home name and the
+//! room and device keys only, so changing a device's display name keeps its
+//! entity history.  Moving or re-keying a device (e.g. with
+//! [`SmartHome::rename_device`]) gives it new IDs; the old entities are
+//! removed and new ones created.
+//!
+//! The usual way to use it is [`MqttBridge::enable_discovery`][crate::mqtt::MqttBridge::enable_discovery],
+//! which also announces devices added later and removes deleted ones.
+
+use crate::mqtt::{availability_topic, set_topic, state_topic, Message, DEFAULT_PREFIX};
+use crate::room::Room;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+/// Topic prefix Home Assistant listens on by default.
+pub const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// Every entity type a device can have: `(component, entity suffix)`.
+const ENTITIES: [(&str, &str); 3] = [
+    ("switch", "switch"),
+    ("sensor", "power"),
+    ("sensor", "temperature"),
+];
+
+/// Generates Home Assistant discovery configs for a home's devices.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::homeassistant::Discovery;
+/// use smart_home::{room, SmartHome, Socket};
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+///
+/// let messages = Discovery::default().home(&home);
+/// assert_eq!(messages.len(), 2); // switch + power sensor
+/// assert_eq!(messages[0].topic, "homeassistant/switch/smart_home/Flat-hall-lamp-switch/config");
+/// assert!(messages.iter().all(|m| m.retain));
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    discovery_prefix: String,
+    topic_prefix: String,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self::new(DEFAULT_DISCOVERY_PREFIX, DEFAULT_PREFIX)
+    }
+}
+
+impl Discovery {
+    /// Creates a generator publishing configs under `discovery_prefix` for a
+    /// bridge using `topic_prefix`.
+    pub fn new(discovery_prefix: impl Into<String>, topic_prefix: impl Into<String>) -> Self {
+        Self {
+            discovery_prefix: discovery_prefix.into(),
+            topic_prefix: topic_prefix.into(),
+        }
+    }
+
+    /// Returns config messages for every device in `home`.
+    pub fn home(&self, home: &SmartHome) -> Vec<Message> {
+        home.rooms()
+            .flat_map(|(room_key, room)| self.room(home.name(), room_key, room))
+            .collect()
+    }
+
+    /// Returns config messages for every device in `room`.
+    pub fn room(&self, home_name: &str, room_key: &str, room: &Room) -> Vec<Message> {
+        room.devices()
+            .flat_map(|(device_key, device)| {
+                self.device(home_name, room_key, room.name(), device_key, device)
+            })
+            .collect()
+    }
+
+    /// Returns config messages for one device.
+    pub fn device(
+        &self,
+        home_name: &str,
+        room_key: &str,
+        room_name: &str,
+        device_key: &str,
+        device: &SmartDevice,
+    ) -> Vec<Message> {
+        let id = object_id(home_name, room_key, device_key);
+        let state_topic = state_topic(&self.topic_prefix, room_key, device_key);
+        let command_topic = set_topic(&self.topic_prefix, room_key, device_key);
+        let (name, entities) = match device {
+            SmartDevice::Socket(s) => (
+                s.name(),
+                vec![
+                    (
+                        "switch",
+                        "switch",
+                        json!({
+                            "name": null,
+                            "command_topic": command_topic,
+                            "value_template": "{{ value_json.state }}",
+                            "payload_on": "ON",
+                            "payload_off": "OFF",
+                            "state_on": "ON",
+                            "state_off": "OFF",
+                        }),
+                    ),
+                    (
+                        "sensor",
+                        "power",
+                        json!({
+                            "name": "Power",
+                            "device_class": "power",
+                            "state_class": "measurement",
+                            "unit_of_measurement": "W",
+                            "value_template": "{{ value_json.power }}",
+                        }),
+                    ),
+                ],
+            ),
+            SmartDevice::Thermometer(t) => (
+                t.name(),
+                vec![(
+                    "sensor",
+                    "temperature",
+                    json!({
+                        "name": null,
+                        "device_class": "temperature",
+                        "state_class": "measurement",
+                        "unit_of_measurement": "°C",
+                        "value_template": "{{ value_json.temperature }}",
+                    }),
+                )],
+            ),
+        };
+        entities
+            .into_iter()
+            .map(|(component, suffix, mut config)| {
+                let fields = config.as_object_mut().expect("config is an object");
+                fields.insert(
+                    "unique_id".into(),
+                    format!("smart_home-{}-{}", id, suffix).into(),
+                );
+                fields.insert("state_topic".into(), state_topic.clone().into());
+                fields.insert(
+                    "availability_topic".into(),
+                    availability_topic(&self.topic_prefix).into(),
+                );
+                fields.insert("payload_available".into(), "online".into());
+                fields.insert("payload_not_available".into(), "offline".into());
+                fields.insert(
+                    "device".into(),
+                    json!({
+                        "identifiers": [format!("smart_home-{}", id)],
+                        "name": name,
+                        "model": device.kind(),
+                        "manufacturer": "smart_home",
+                        "suggested_area": room_name,
+                    }),
+                );
+                self.config_message(component, &id, suffix, &config)
+            })
+            .collect()
+    }
+
+    /// Returns messages that remove a device's entities from Home Assistant.
+    ///
+    /// The device kind is not needed: configs for every entity type are cleared.
+    pub fn remove_device(&self, home_name: &str, room_key: &str, device_key: &str) -> Vec<Message> {
+        let id = object_id(home_name, room_key, device_key);
+        ENTITIES
+            .iter()
+            .map(|(component, suffix)| {
+                Message::new(self.config_topic(component, &id, suffix), "", true)
+            })
+            .collect()
+    }
+
+    /// Returns messages that follow a device to a new room or key: the old
+    /// configs are removed and configs for the new place published.
+    pub fn move_device(
+        &self,
+        home_name: &str,
+        from: (&str, &str),
+        to: (&str, &str),
+        room_name: &str,
+        device: &SmartDevice,
+    ) -> Vec<Message> {
+        let mut messages = self.remove_device(home_name, from.0, from.1);
+        messages.extend(self.device(home_name, to.0, room_name, to.1, device));
+        messages
+    }
+
+    fn config_message(&self, component: &str, id: &str, suffix: &str, config: &Value) -> Message {
+        Message::new(
+            self.config_topic(component, id, suffix),
+            config.to_string(),
+            true,
+        )
+    }
+
+    fn config_topic(&self, component: &str, id: &str, suffix: &str) -> String {
+        format!(
+            "{}/{}/smart_home/{}-{}/config",
+            self.discovery_prefix, component, id, suffix
+        )
+    }
+}
+
+/// Joins escaped keys into an ID that is valid in discovery topics and
+/// distinct for distinct keys.
+fn object_id(home_name: &str, room_key: &str, device_key: &str) -> String {
+    format!(
+        "{}-{}-{}",
+        escape(home_name),
+        escape(room_key),
+        escape(device_key)
+    )
+}
+
+/// Keeps ASCII letters and digits and writes every other byte as `_xx`.
+fn escape(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        if byte.is_ascii_alphanumeric() {
+            out.push(byte as char);
+        } else {
+            let _ = write!(out, "_{:02x}", byte);
+        }
+    }
+    out
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("My Home");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "kettle" => Socket::new("Kettle", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 22.0),
+            ),
+        )
+        .unwrap();
+        home
+    }
+
+    fn config(messages: &[Message], topic: &str) -> Value {
+        let message = messages.iter().find(|m| m.topic == topic).unwrap();
+        serde_json::from_str(&message.payload).unwrap()
+    }
+
+    #[test]
+    fn test_escape_is_injective_and_topic_safe() {
+        assert_eq!(escape("My Home"), "My_20Home");
+        assert_ne!(escape("a b"), escape("a_b"));
+        assert_ne!(object_id("h", "a-b", "c"), object_id("h", "a", "b-c"));
+        assert!(escape("кухня/#+")
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_'));
+    }
+
+    #[test]
+    fn test_socket_configs() {
+        let messages = Discovery::default().home(&make_home());
+        assert_eq!(messages.len(), 3);
+
+        let switch = config(
+            &messages,
+            "homeassistant/switch/smart_home/My_20Home-kitchen-kettle-switch/config",
+        );
+        assert_eq!(
+            switch["unique_id"],
+            "smart_home-My_20Home-kitchen-kettle-switch"
+        );
+        assert_eq!(switch["state_topic"], "home/kitchen/kettle/state");
+        assert_eq!(switch["command_topic"], "home/kitchen/kettle/set");
+        assert_eq!(switch["availability_topic"], "home/status");
+        assert_eq!(switch["device"]["suggested_area"], "Kitchen");
+
+        let power = config(
+            &messages,
+            "homeassistant/sensor/smart_home/My_20Home-kitchen-kettle-power/config",
+        );
+        assert_eq!(power["device_class"], "power");
+        assert_eq!(
+            power["device"]["identifiers"],
+            switch["device"]["identifiers"]
+        );
+    }
+
+    #[test]
+    fn test_thermometer_config() {
+        let messages = Discovery::new("ha", "flat").home(&make_home());
+        let temp = config(
+            &messages,
+            "ha/sensor/smart_home/My_20Home-kitchen-sensor-temperature/config",
+        );
+        assert_eq!(temp["device_class"], "temperature");
+        assert_eq!(temp["unit_of_measurement"], "°C");
+        assert_eq!(temp["state_topic"], "flat/kitchen/sensor/state");
+        assert!(temp.get("command_topic").is_none());
+    }
+
+    #[test]
+    fn test_move_device() {
+        let home = make_home();
+        let kettle = home.get_device("kitchen", "kettle").unwrap();
+        let messages = Discovery::default().move_device(
+            "My Home",
+            ("kitchen", "kettle"),
+            ("galley", "boiler"),
+            "Galley",
+            kettle,
+        );
+        assert_eq!(messages.len(), 5);
+        assert!(messages[..3].iter().all(|m| m.payload.is_empty()));
+        let switch = config(
+            &messages,
+            "homeassistant/switch/smart_home/My_20Home-galley-boiler-switch/config",
+        );
+        assert_eq!(switch["state_topic"], "home/galley/boiler/state");
+        assert_eq!(switch["device"]["suggested_area"], "Galley");
+    }
+
+    #[test]
+    fn test_remove_device() {
+        let messages = Discovery::default().remove_device("My Home", "kitchen", "kettle");
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|m| m.retain && m.payload.is_empty()));
+    }
+}
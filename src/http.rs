@@ -0,0 +1,670 @@
+//! REST API over HTTP for a [`SharedHome`].
+//!
+//! Available with the `http` feature.  Resources and JSON bodies:
+//!
+//! | Method   | Path                                                   | Body                               |
+//! |----------|--------------------------------------------------------|------------------------------------|
+//! | `GET`    | `/`                                                    | whole home                         |
+//! | `GET`    | `/report`                                              | text report (`text/plain`)         |
+//! | `GET`    | `/rooms`                                               | `[{key, name, device_count}]`      |
+//! | `POST`   | `/rooms`                                               | `{"key": .., "name": ..}`          |
+//! | `GET`    | `/rooms/{room}`                                        | room                               |
+//! | `DELETE` | `/rooms/{room}`                                        | `room_removed` event               |
+//! | `GET`    | `/rooms/{room}/devices`                                | `{key: device}`                    |
+//! | `POST`   | `/rooms/{room}/devices`                                | `{"key": .., "kind": .., ...}`     |
+//! | `GET`    | `/rooms/{room}/devices/{device}`                       | device                             |
+//! | `DELETE` | `/rooms/{room}/devices/{device}`                       | `device_removed` event             |
+//! | `POST`   | `/rooms/{room}/devices/{device}/socket/{on,off,toggle}`| device                             |
+//! | `PUT`    | `/rooms/{room}/devices/{device}/thermometer/temperature` | `{"temperature": ..}`            |
+//! | `POST`   | `/batch`                                               | `[command, ..]`, returns events    |
+//!
+//! A batch is applied with [`SharedHome::execute_all`]: if any command fails,
+//! none of them takes effect.
+//!
+//! Errors are returned as `{"error": .., "message": ..}` with the status code
+//! given by [`status_code`].  Bodies over 1 MiB are refused with `413`.  All changes go through [`SharedHome::execute`]
+//! or [`SharedHome::execute_all`], so they reach event subscribers such as the
+//! WebSocket server.
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::report::Report;
+use crate::room::Room;
+use crate::shared::SharedHome;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Largest request body the server accepts, in bytes.
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// Callback run after every successful mutating request.
+type ChangeHook = Arc<dyn Fn(&SmartHome) + Send + Sync>;
+
+/// A response produced by [`handle`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// HTTP status code.
+    pub status: u16,
+    /// Value of the `Content-Type` header.
+    pub content_type: &'static str,
+    /// Response body.
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_string_pretty(value).expect("home data is always serializable"),
+        }
+    }
+
+    fn text(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body,
+        }
+    }
+
+    fn error(status: u16, error: &str, message: impl Into<String>) -> Self {
+        Self::json(
+            status,
+            &ErrorBody {
+                error,
+                message: message.into(),
+            },
+        )
+    }
+
+    fn from_error(err: &SmartHomeError) -> Self {
+        Self::error(status_code(err), error_name(err), err.to_string())
+    }
+
+    /// Returns `true` for 2xx responses.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct RoomSummary<'a> {
+    key: &'a str,
+    name: &'a str,
+    device_count: usize,
+}
+
+#[derive(Deserialize)]
+struct NewRoom {
+    key: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NewDevice {
+    key: String,
+    #[serde(flatten)]
+    device: SmartDevice,
+}
+
+#[derive(Deserialize)]
+struct NewTemperature {
+    temperature: f32,
+}
+
+/// Maps a [`SmartHomeError`] to the HTTP status code reported for it.
+///
+/// | Error                                       | Status |
+/// |---------------------------------------------|--------|
+/// | `RoomNotFound`, `DeviceNotFound`,           | 404    |
+/// | `GroupNotFound`, `HomeNotFound`,            |        |
+/// | `AlertNotFound`                             |        |
+/// | `DuplicateRoom`, `DuplicateDevice`,         | 409    |
+/// | `DuplicateGroup`, `DuplicateHome`           |        |
+/// | `WrongDeviceKind`                           | 400    |
+/// | `AuthenticationFailed`                      | 401    |
+/// | `PermissionDenied`                          | 403    |
+/// | `InvalidValue`                              | 422    |
+/// | `Io`                                        | 500    |
+/// | `Remote`                                    | 502    |
+pub fn status_code(err: &SmartHomeError) -> u16 {
+    match err {
+        SmartHomeError::RoomNotFound(_)
+        | SmartHomeError::DeviceNotFound { .. }
+        | SmartHomeError::GroupNotFound(_)
+        | SmartHomeError::HomeNotFound(_)
+        | SmartHomeError::AlertNotFound(_) => 404,
+        SmartHomeError::DuplicateRoom(_)
+        | SmartHomeError::DuplicateDevice { .. }
+        | SmartHomeError::DuplicateGroup(_)
+        | SmartHomeError::DuplicateHome(_) => 409,
+        SmartHomeError::WrongDeviceKind { .. } => 400,
+        SmartHomeError::PermissionDenied { .. } => 403,
+        SmartHomeError::AuthenticationFailed(_) => 401,
+        SmartHomeError::InvalidValue(_) => 422,
+        SmartHomeError::Io(_) => 500,
+        SmartHomeError::Remote(_) => 502,
+    }
+}
+
+pub(crate) fn error_name(err: &SmartHomeError) -> &'static str {
+    match err {
+        SmartHomeError::RoomNotFound(_) => "room_not_found",
+        SmartHomeError::DeviceNotFound { .. } => "device_not_found",
+        SmartHomeError::DuplicateRoom(_) => "duplicate_room",
+        SmartHomeError::DuplicateDevice { .. } => "duplicate_device",
+        SmartHomeError::WrongDeviceKind { .. } => "wrong_device_kind",
+        SmartHomeError::GroupNotFound(_) => "group_not_found",
+        SmartHomeError::DuplicateGroup(_) => "duplicate_group",
+        SmartHomeError::HomeNotFound(_) => "home_not_found",
+        SmartHomeError::AlertNotFound(_) => "alert_not_found",
+        SmartHomeError::PermissionDenied { .. } => "permission_denied",
+        SmartHomeError::AuthenticationFailed(_) => "authentication_failed",
+        SmartHomeError::Remote(_) => "remote_error",
+        SmartHomeError::DuplicateHome(_) => "duplicate_home",
+        SmartHomeError::InvalidValue(_) => "invalid_value",
+        SmartHomeError::Io(_) => "io",
+    }
+}
+
+/// Handles one request against `shared` without any networking.
+///
+/// `path` may include a query string, which is ignored.  Path segments are
+/// percent-decoded, so keys with spaces can be addressed as `%20`.
+pub fn handle(shared: &SharedHome, method: &str, path: &str, body: &str) -> Response {
+    let path = path.split('?').next().unwrap_or_default();
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    route(shared, method, path, &segments, body).unwrap_or_else(|e| e)
+}
+
+/// Dispatches a request; `Err` carries an error response.
+fn route(
+    shared: &SharedHome,
+    method: &str,
+    path: &str,
+    segments: &[&str],
+    body: &str,
+) -> Result<Response, Response> {
+    match (method, segments) {
+        ("GET", []) => shared.read(|h| Ok(Response::json(200, h))),
+        ("GET", ["report"]) => shared.read(|h| Ok(Response::text(200, h.report()))),
+        ("GET", ["rooms"]) => shared.read(|h| {
+            let mut rooms: Vec<_> = h
+                .rooms()
+                .map(|(key, room)| RoomSummary {
+                    key,
+                    name: room.name(),
+                    device_count: room.device_count(),
+                })
+                .collect();
+            rooms.sort_by_key(|r| r.key);
+            Ok(Response::json(200, &rooms))
+        }),
+        ("POST", ["rooms"]) => parse_body(body).and_then(|new: NewRoom| {
+            let name = new.name.unwrap_or_else(|| new.key.clone());
+            let room = Room::new(name);
+            shared.execute(Command::AddRoom {
+                room: new.key,
+                data: room.clone(),
+            })?;
+            Ok(Response::json(201, &room))
+        }),
+        ("GET", ["rooms", room]) => shared.read(|h| {
+            let room = h
+                .get_room(room)
+                .ok_or_else(|| SmartHomeError::RoomNotFound(room.to_string()))?;
+            Ok(Response::json(200, room))
+        }),
+        ("DELETE", ["rooms", room]) => {
+            let event = shared.execute(Command::RemoveRoom {
+                room: room.to_string(),
+            })?;
+            Ok(Response::json(200, &event))
+        }
+        ("GET", ["rooms", room, "devices"]) => shared.read(|h| {
+            let room = h
+                .get_room(room)
+                .ok_or_else(|| SmartHomeError::RoomNotFound(room.to_string()))?;
+            Ok(Response::json(
+                200,
+                &room.devices().collect::<BTreeMap<_, _>>(),
+            ))
+        }),
+        ("POST", ["rooms", room, "devices"]) => parse_body(body).and_then(|new: NewDevice| {
+            shared.execute(Command::AddDevice {
+                room: room.to_string(),
+                device: new.key,
+                data: new.device.clone(),
+            })?;
+            Ok(Response::json(201, &new.device))
+        }),
+        ("GET", ["rooms", room, "devices", device]) => {
+            shared.read(|h| Ok(Response::json(200, h.get_device(room, device)?)))
+        }
+        ("DELETE", ["rooms", room, "devices", device]) => {
+            let event = shared.execute(Command::RemoveDevice {
+                room: room.to_string(),
+                device: device.to_string(),
+            })?;
+            Ok(Response::json(200, &event))
+        }
+        (
+            "POST",
+            ["rooms", room, "devices", device, "socket", action @ ("on" | "off" | "toggle")],
+        ) => {
+            let (room, device) = (room.to_string(), device.to_string());
+            let command = match *action {
+                "on" => Command::TurnOn { room, device },
+                "off" => Command::TurnOff { room, device },
+                _ => Command::Toggle { room, device },
+            };
+            device_response(shared, shared.execute(command)?)
+        }
+        ("PUT", ["rooms", room, "devices", device, "thermometer", "temperature"]) => {
+            parse_body(body).and_then(|new: NewTemperature| {
+                let event = shared.execute(Command::SetTemperature {
+                    room: room.to_string(),
+                    device: device.to_string(),
+                    temperature: new.temperature,
+                })?;
+                device_response(shared, event)
+            })
+        }
+        ("POST", ["batch"]) => parse_body(body).and_then(|commands: Vec<Command>| {
+            Ok(Response::json(200, &shared.execute_all(commands)?))
+        }),
+        (_, segs) if is_known_path(segs) => Ok(Response::error(
+            405,
+            "method_not_allowed",
+            format!("{} is not supported on {}", method, path),
+        )),
+        _ => Ok(Response::error(
+            404,
+            "not_found",
+            format!("no resource at {}", path),
+        )),
+    }
+}
+
+/// Responds with the current state of the device an event refers to.
+fn device_response(shared: &SharedHome, event: HomeEvent) -> Result<Response, Response> {
+    let room = event.room().unwrap_or_default();
+    let device = event.device().unwrap_or_default();
+    shared.read(|h| Ok(Response::json(200, h.get_device(room, device)?)))
+}
+
+/// Returns `true` if some method is routed for these path segments.
+fn is_known_path(segments: &[&str]) -> bool {
+    matches!(
+        segments,
+        [] | ["report"]
+            | ["batch"]
+            | ["rooms"]
+            | ["rooms", _]
+            | ["rooms", _, "devices"]
+            | ["rooms", _, "devices", _]
+            | ["rooms", _, "devices", _, "socket", "on" | "off" | "toggle"]
+            | ["rooms", _, "devices", _, "thermometer", "temperature"]
+    )
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T, Response> {
+    serde_json::from_str(body).map_err(|e| Response::error(400, "bad_request", e.to_string()))
+}
+
+impl From<SmartHomeError> for Response {
+    fn from(err: SmartHomeError) -> Self {
+        Response::from_error(&err)
+    }
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// ── Server ────────────────────────────────────────────────────────────────────
+
+/// A blocking HTTP server exposing a [`SharedHome`] through [`handle`].
+///
+/// Each request is served on its own thread.
+pub struct HttpServer {
+    server: Arc<tiny_http::Server>,
+    shared: SharedHome,
+    on_change: Option<ChangeHook>,
+}
+
+impl HttpServer {
+    /// Binds the server to `addr` (use port 0 to pick a free port).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the address cannot be bound.
+    pub fn bind(addr: impl ToSocketAddrs, shared: SharedHome) -> Result<Self, SmartHomeError> {
+        let server = tiny_http::Server::http(addr).map_err(io::Error::other)?;
+        Ok(Self {
+            server: Arc::new(server),
+            shared,
+            on_change: None,
+        })
+    }
+
+    /// Sets a callback run with the updated home after every successful
+    /// mutating request, e.g. to save it to a file.
+    pub fn on_change(mut self, hook: impl Fn(&SmartHome) + Send + Sync + 'static) -> Self {
+        self.on_change = Some(Arc::new(hook));
+        self
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server
+            .server_addr()
+            .to_ip()
+            .expect("server is bound to an IP address")
+    }
+
+    /// Serves requests until the server is shut down through a [`ServerHandle`].
+    pub fn run(&self) {
+        for request in self.server.incoming_requests() {
+            let shared = self.shared.clone();
+            let on_change = self.on_change.clone();
+            thread::spawn(move || serve_request(request, &shared, on_change.as_deref()));
+        }
+    }
+
+    /// Runs the server on a background thread.
+    pub fn spawn(self) -> ServerHandle {
+        let addr = self.local_addr();
+        let server = Arc::clone(&self.server);
+        let thread = thread::spawn(move || self.run());
+        ServerHandle {
+            addr,
+            server,
+            thread: Some(thread),
+        }
+    }
+}
+
+fn serve_request(
+    mut request: tiny_http::Request,
+    shared: &SharedHome,
+    on_change: Option<&(dyn Fn(&SmartHome) + Send + Sync)>,
+) {
+    let mut body = String::new();
+    // One byte more than allowed tells an oversized body from one that fits.
+    let read = request
+        .as_reader()
+        .take(MAX_BODY + 1)
+        .read_to_string(&mut body);
+    let response = match read {
+        Ok(len) if len as u64 > MAX_BODY => Response::error(
+            413,
+            "payload_too_large",
+            format!("request bodies are limited to {} bytes", MAX_BODY),
+        ),
+        Ok(_) => {
+            let method = request.method().as_str().to_string();
+            let response = handle(shared, &method, request.url(), &body);
+            if response.is_success() && method != "GET" {
+                if let Some(hook) = on_change {
+                    shared.read(hook);
+                }
+            }
+            response
+        }
+        Err(e) => Response::error(400, "bad_request", e.to_string()),
+    };
+
+    let content_type =
+        tiny_http::Header::from_bytes("Content-Type", response.content_type).expect("valid header");
+    let reply = tiny_http::Response::from_string(response.body)
+        .with_status_code(response.status)
+        .with_header(content_type);
+    // The client may have gone away; there is nobody left to report that to.
+    let _ = request.respond(reply);
+}
+
+/// Handle to a server started with [`HttpServer::spawn`].
+pub struct ServerHandle {
+    addr: SocketAddr,
+    server: Arc<tiny_http::Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stops accepting requests and waits for the server thread to exit.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, storage, Socket, Thermometer};
+
+    fn make_shared() -> SharedHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "living room",
+            room!(
+                "Living room",
+                "lamp"   => Socket::new("Lamp", 60.0),
+                "sensor" => Thermometer::new("Sensor", 21.0),
+            ),
+        )
+        .unwrap();
+        SharedHome::new(home)
+    }
+
+    fn json(response: &Response) -> serde_json::Value {
+        serde_json::from_str(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_list_rooms() {
+        let r = handle(&make_shared(), "GET", "/rooms", "");
+        assert_eq!(r.status, 200);
+        assert_eq!(json(&r)[0]["key"], "living room");
+        assert_eq!(json(&r)[0]["device_count"], 2);
+    }
+
+    #[test]
+    fn test_get_device_with_escaped_key() {
+        let r = handle(
+            &make_shared(),
+            "GET",
+            "/rooms/living%20room/devices/lamp",
+            "",
+        );
+        assert_eq!(r.status, 200);
+        assert_eq!(json(&r)["kind"], "socket");
+    }
+
+    #[test]
+    fn test_create_room_and_device() {
+        let shared = make_shared();
+        let r = handle(
+            &shared,
+            "POST",
+            "/rooms",
+            r#"{"key":"kitchen","name":"Kitchen"}"#,
+        );
+        assert_eq!(r.status, 201);
+
+        let r = handle(
+            &shared,
+            "POST",
+            "/rooms/kitchen/devices",
+            r#"{"key":"kettle","kind":"socket","name":"Kettle","power_consumption":2000}"#,
+        );
+        assert_eq!(r.status, 201, "{}", r.body);
+        assert!(shared.read(|h| h.get_socket("kitchen", "kettle").is_ok()));
+
+        let r = handle(&shared, "POST", "/rooms", r#"{"key":"kitchen"}"#);
+        assert_eq!(r.status, 409);
+        assert_eq!(json(&r)["error"], "duplicate_room");
+    }
+
+    #[test]
+    fn test_socket_and_temperature() {
+        let shared = make_shared();
+        let r = handle(
+            &shared,
+            "POST",
+            "/rooms/living%20room/devices/lamp/socket/on",
+            "",
+        );
+        assert_eq!(r.status, 200);
+        assert_eq!(json(&r)["is_on"], true);
+
+        let r = handle(
+            &shared,
+            "PUT",
+            "/rooms/living%20room/devices/sensor/thermometer/temperature",
+            r#"{"temperature": 23.5}"#,
+        );
+        assert_eq!(r.status, 200);
+        assert_eq!(json(&r)["temperature"], 23.5);
+    }
+
+    #[test]
+    fn test_error_statuses() {
+        let shared = make_shared();
+        let cases = [
+            ("GET", "/rooms/attic", "", 404),
+            ("GET", "/rooms/living%20room/devices/tv", "", 404),
+            (
+                "POST",
+                "/rooms/living%20room/devices/sensor/socket/on",
+                "",
+                400,
+            ),
+            (
+                "PUT",
+                "/rooms/living%20room/devices/sensor/thermometer/temperature",
+                r#"{"temperature": -500}"#,
+                422,
+            ),
+            ("POST", "/rooms", "not json", 400),
+            ("PATCH", "/rooms", "", 405),
+            ("GET", "/nowhere", "", 404),
+        ];
+        for (method, path, body, status) in cases {
+            let r = handle(&shared, method, path, body);
+            assert_eq!(r.status, status, "{} {}: {}", method, path, r.body);
+        }
+    }
+
+    #[test]
+    fn test_delete() {
+        let shared = make_shared();
+        let r = handle(&shared, "DELETE", "/rooms/living%20room/devices/lamp", "");
+        assert_eq!(r.status, 200);
+        let r = handle(&shared, "DELETE", "/rooms/living%20room", "");
+        assert_eq!(r.status, 200);
+        assert_eq!(shared.read(|h| h.room_count()), 0);
+    }
+
+    #[test]
+    fn test_batch_is_atomic() {
+        let shared = make_shared();
+        let lamp = r#"{"command": "turn_on", "room": "living room", "device": "lamp"}"#;
+        let missing = r#"{"command": "turn_on", "room": "living room", "device": "tv"}"#;
+        let r = handle(
+            &shared,
+            "POST",
+            "/batch",
+            &format!("[{}, {}]", lamp, missing),
+        );
+        assert_eq!(r.status, 404);
+        assert!(!shared.read(|h| h.get_socket("living room", "lamp").unwrap().is_on()));
+
+        let r = handle(&shared, "POST", "/batch", &format!("[{}]", lamp));
+        assert_eq!(r.status, 200);
+        assert!(r.body.contains("socket_switched"));
+        assert!(shared.read(|h| h.get_socket("living room", "lamp").unwrap().is_on()));
+    }
+
+    #[test]
+    fn test_status_code_mapping() {
+        assert_eq!(status_code(&SmartHomeError::RoomNotFound("x".into())), 404);
+        assert_eq!(
+            status_code(&SmartHomeError::DuplicateDevice {
+                room: "x".into(),
+                device: "y".into()
+            }),
+            409
+        );
+        assert_eq!(status_code(&SmartHomeError::InvalidValue("x".into())), 422);
+        assert_eq!(status_code(&io::Error::other("x").into()), 500);
+    }
+
+    #[test]
+    fn test_report_and_home() {
+        let shared = make_shared();
+        let r = handle(&shared, "GET", "/report", "");
+        assert!(r.content_type.starts_with("text/plain"));
+        assert!(r.body.contains("Living room"));
+        let r = handle(&shared, "GET", "/", "");
+        assert_eq!(storage::from_json(&r.body).unwrap().name(), "Flat");
+    }
+}
@@ -4,17 +4,50 @@
 //! organised into rooms and a house.  All types implement the [`Report`] trait
 //! so their state can be inspected at any level of the hierarchy.
 
+pub mod access;
+pub mod alert;
+pub mod area;
+pub mod audit;
+pub mod availability;
+pub mod clock;
+pub mod command;
 pub mod devices;
+pub mod discovery;
 pub mod error;
+pub mod event;
+pub mod group;
+pub mod history;
+#[cfg(feature = "mqtt")]
+pub mod homeassistant;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod metadata;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod portfolio;
+#[cfg(feature = "remote")]
+pub mod remote;
 pub mod report;
 pub mod room;
+pub mod shared;
+pub mod simulation;
 pub mod smart_device;
 pub mod smart_home;
+pub mod storage;
+pub mod trace;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "ws")]
+pub mod ws;
 
-pub use devices::{Socket, Thermometer};
+pub use clock::{AcceleratedClock, Clock, ManualClock, SystemClock};
+pub use command::Command;
+pub use devices::{Socket, Thermometer, Wireless};
 pub use error::SmartHomeError;
+pub use event::{EventBus, HomeEvent};
 pub use report::Report;
 pub use room::Room;
+pub use shared::SharedHome;
 pub use smart_device::SmartDevice;
 pub use smart_home::SmartHome;
 
@@ -36,6 +69,10 @@ pub use smart_home::SmartHome;
 /// assert_eq!(r.name(), "Living room");
 /// assert_eq!(r.device_count(), 2);
 /// ```
+///
+/// # Panics
+///
+/// Panics if the same device key is listed twice.
 #[macro_export]
 macro_rules! room {
     // No devices — just create an empty room.
@@ -46,7 +83,9 @@ macro_rules! room {
     ($name:expr, $($key:expr => $device:expr),+ $(,)?) => {{
         let mut room = $crate::Room::new($name);
         $(
-            room.add_device($key, $device);
+            if let Err(e) = room.add_device($key, $device) {
+                panic!("room!: {}", e);
+            }
         )+
         room
     }};
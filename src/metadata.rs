@@ -0,0 +1,257 @@
+//! Free-form tags and attributes on rooms and devices.
+//!
+//! Every [`Room`][crate::Room] and device carries [`Metadata`]: a set of tags
+//! such as `critical` or `never_auto_shed`, and string attributes such as
+//! `manufacturer` or `install_date`.  The library attaches no meaning to
+//! either; they are stored with the home and can be searched with a [`Query`].
+
+use crate::error::SmartHomeError;
+use crate::smart_device::SmartDevice;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+/// Tags and key/value attributes.
+///
+/// Serialized as `{"tags": [..], "attributes": {..}}`, leaving out empty parts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    /// Returns `true` if there are no tags and no attributes.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.attributes.is_empty()
+    }
+
+    /// Returns the tags in alphabetical order.
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+
+    /// Returns `true` if `tag` is set.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// Sets `tag`; returns `false` if it was already set.
+    pub fn add_tag(&mut self, tag: impl Into<String>) -> bool {
+        self.tags.insert(tag.into())
+    }
+
+    /// Clears `tag`; returns `false` if it was not set.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        self.tags.remove(tag)
+    }
+
+    /// Returns `(key, value)` attribute pairs sorted by key.
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the value of attribute `key`, or `None`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+
+    /// Sets attribute `key` to `value`, returning the previous value.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.attributes.insert(key.into(), value.into())
+    }
+
+    /// Removes attribute `key`, returning its value.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.attributes.remove(key)
+    }
+}
+
+/// A search for rooms or devices by metadata and device kind.
+///
+/// All criteria must match.  Parsed from whitespace-separated terms (see
+/// [`Query::term`]): `tag:<tag>`, `<key>=<value>` and `kind:<kind>`.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::metadata::Query;
+/// use smart_home::{room, SmartHome, Socket};
+///
+/// let mut fridge = Socket::new("Fridge", 150.0);
+/// fridge.metadata_mut().add_tag("never_auto_shed");
+/// fridge.metadata_mut().set("model", "KG39");
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("kitchen", room!("Kitchen", "fridge" => fridge))?;
+///
+/// let query: Query = "kind:socket tag:never_auto_shed".parse()?;
+/// let found = home.find_devices(&query);
+/// assert_eq!(found[0].1, "fridge");
+/// assert_eq!(found[0].2.metadata().get("model"), Some("KG39"));
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    tags: Vec<String>,
+    attributes: Vec<(String, String)>,
+    kind: Option<String>,
+}
+
+impl Query {
+    /// Creates a query matching everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also requires `tag` to be set.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Also requires attribute `key` to equal `value`.
+    pub fn attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.push((key.into(), value.into()));
+        self
+    }
+
+    /// Also requires a device of `kind` (e.g. `"socket"`); rooms never match
+    /// a query with a kind.
+    pub fn kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = Some(kind.into());
+        self
+    }
+
+    /// Adds one criterion written as `tag:<tag>`, `kind:<kind>` or
+    /// `<key>=<value>`; the value may contain spaces.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if `term` has none of these forms.
+    pub fn term(self, term: &str) -> Result<Self, SmartHomeError> {
+        match (term.split_once(':'), term.split_once('=')) {
+            (Some(("tag", tag)), _) if !tag.is_empty() => Ok(self.tag(tag)),
+            (Some(("kind", kind)), _) if !kind.is_empty() => Ok(self.kind(kind)),
+            (_, Some((key, value))) if !key.is_empty() => Ok(self.attribute(key, value)),
+            _ => Err(SmartHomeError::InvalidValue(format!(
+                "'{}' is not a query term; expected tag:<tag>, kind:<kind> or <key>=<value>",
+                term
+            ))),
+        }
+    }
+
+    /// Returns `true` if `metadata` has all required tags and attributes.
+    /// The kind is not checked.
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        self.tags.iter().all(|tag| metadata.has_tag(tag))
+            && self
+                .attributes
+                .iter()
+                .all(|(key, value)| metadata.get(key) == Some(value.as_str()))
+    }
+
+    /// Returns `true` if `device` matches all criteria.
+    pub fn matches_device(&self, device: &SmartDevice) -> bool {
+        self.kind
+            .as_deref()
+            .is_none_or(|kind| kind == device.kind())
+            && self.matches(device.metadata())
+    }
+
+    /// Returns `true` if a room with `metadata` matches all criteria.
+    pub fn matches_room(&self, metadata: &Metadata) -> bool {
+        self.kind.is_none() && self.matches(metadata)
+    }
+}
+
+impl FromStr for Query {
+    type Err = SmartHomeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_whitespace().try_fold(Query::new(), Query::term)
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Socket, Thermometer};
+
+    fn fridge() -> SmartDevice {
+        let mut socket = Socket::new("Fridge", 150.0);
+        let meta = socket.metadata_mut();
+        meta.add_tag("critical");
+        meta.set("manufacturer", "Bosch");
+        socket.into()
+    }
+
+    #[test]
+    fn test_tags_and_attributes() {
+        let mut meta = Metadata::default();
+        assert!(meta.is_empty());
+        assert!(meta.add_tag("critical"));
+        assert!(!meta.add_tag("critical"));
+        assert_eq!(meta.set("model", "A1"), None);
+        assert_eq!(meta.set("model", "A2").as_deref(), Some("A1"));
+        assert_eq!(meta.attributes().collect::<Vec<_>>(), [("model", "A2")]);
+        assert!(meta.remove_tag("critical"));
+        assert_eq!(meta.remove("model").as_deref(), Some("A2"));
+        assert!(meta.is_empty());
+    }
+
+    #[test]
+    fn test_query_matching() {
+        let fridge = fridge();
+        let sensor = SmartDevice::from(Thermometer::new("Sensor", 20.0));
+        let query = Query::new()
+            .tag("critical")
+            .attribute("manufacturer", "Bosch");
+        assert!(query.matches_device(&fridge));
+        assert!(!query.matches_device(&sensor));
+        assert!(query.clone().kind("socket").matches_device(&fridge));
+        assert!(!query.clone().kind("thermometer").matches_device(&fridge));
+        assert!(!Query::new().kind("socket").matches_room(fridge.metadata()));
+        assert!(Query::new().matches_device(&sensor));
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query: Query = "tag:critical  manufacturer=Bosch kind:socket"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            query,
+            Query::new()
+                .tag("critical")
+                .attribute("manufacturer", "Bosch")
+                .kind("socket")
+        );
+        assert_eq!("".parse::<Query>().unwrap(), Query::new());
+        for bad in ["critical", "tag:", "=x"] {
+            assert!(matches!(
+                bad.parse::<Query>(),
+                Err(SmartHomeError::InvalidValue(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_serialization_is_preserved() {
+        let json = serde_json::to_string(&fridge()).unwrap();
+        assert!(
+            json.contains(r#""meta":{"tags":["critical"],"attributes":{"manufacturer":"Bosch"}}"#)
+        );
+        let back: SmartDevice = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.metadata(), fridge().metadata());
+
+        let plain = serde_json::to_string(&Socket::new("Lamp", 60.0)).unwrap();
+        assert!(!plain.contains("meta"));
+    }
+}
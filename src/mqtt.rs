@@ -0,0 +1,829 @@
+//! MQTT bridge publishing devices as topics.
+//!
+//! Available with the `mqtt` feature.  With the default `home` prefix the
+//! bridge uses these topics:
+//!
+//! | Topic                          | Direction | Payload                                   |
+//! |--------------------------------|-----------|-------------------------------------------|
+//! | `home/status`                  | out       | `online` / `offline` (retained, last will) |
+//! | `home/<room>/<device>/state`   | out       | `{"state": "ON", "power": 2000.0}` or `{"temperature": 21.5, "battery": 80}` (retained) |
+//! | `home/<room>/<device>/set`     | in        | `ON`, `OFF`, `TOGGLE` for sockets; a number for thermometers |
+//! | `home/<room>/<device>/error`   | out       | message for a rejected `set`              |
+//! | `home/<room>/<device>/availability` | out  | `online` / `stale` / `offline` (retained), see [`MqttBridge::enable_availability`] |
+//!
+//! State and availability topics of removed devices are cleared with an empty
+//! retained message.
+//! Room and device keys are used verbatim, so they should not contain `/`, `+`
+//! or `#`.
+//!
+//! The bridge talks to a broker through the [`Transport`] trait.
+//! [`RumqttTransport`] connects to a real broker; [`MemoryBroker`] is an
+//! in-process stand-in for tests and demos.  Home Assistant can pick the
+//! devices up automatically, see [`MqttBridge::enable_discovery`].
+
+use crate::availability::{AvailabilityEvent, AvailabilityTracker};
+use crate::clock::{Clock, SystemClock};
+use crate::command::Command;
+use crate::devices::Thermometer;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::homeassistant::Discovery;
+use crate::shared::SharedHome;
+use crate::smart_device::SmartDevice;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Default topic prefix.
+pub const DEFAULT_PREFIX: &str = "home";
+
+/// One MQTT application message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// Topic name.
+    pub topic: String,
+    /// UTF-8 payload; empty payloads clear retained messages.
+    pub payload: String,
+    /// Whether the broker keeps the message for future subscribers.
+    pub retain: bool,
+}
+
+impl Message {
+    /// Creates a message.
+    pub fn new(topic: impl Into<String>, payload: impl Into<String>, retain: bool) -> Self {
+        Self {
+            topic: topic.into(),
+            payload: payload.into(),
+            retain,
+        }
+    }
+}
+
+/// A connection to an MQTT broker.
+///
+/// The last will is part of the connection setup and therefore given to the
+/// transport's constructor, not to this trait.
+pub trait Transport {
+    /// Publishes `message`.
+    fn publish(&mut self, message: Message) -> io::Result<()>;
+
+    /// Subscribes to `filter`, which may contain `+` and `#` wildcards.
+    fn subscribe(&mut self, filter: &str) -> io::Result<()>;
+
+    /// Waits up to `timeout` for an incoming message.
+    fn poll(&mut self, timeout: Duration) -> io::Result<Option<Message>>;
+}
+
+/// Returns `true` if `topic` matches the subscription `filter`.
+fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut topic = topic.split('/');
+    for level in filter.split('/') {
+        match (level, topic.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (level, Some(name)) if level == name => {}
+            _ => return false,
+        }
+    }
+    topic.next().is_none()
+}
+
+/// Returns the topic announcing whether the bridge is `online`.
+pub fn availability_topic(prefix: &str) -> String {
+    format!("{}/status", prefix)
+}
+
+/// Returns the topic a device's state is published on.
+pub fn state_topic(prefix: &str, room: &str, device: &str) -> String {
+    format!("{}/{}/{}/state", prefix, room, device)
+}
+
+/// Returns the topic a device's availability is published on.
+pub fn device_availability_topic(prefix: &str, room: &str, device: &str) -> String {
+    format!("{}/{}/{}/availability", prefix, room, device)
+}
+
+/// Returns the topic a device accepts commands on.
+pub fn set_topic(prefix: &str, room: &str, device: &str) -> String {
+    format!("{}/{}/{}/set", prefix, room, device)
+}
+
+/// Returns the last will a bridge's transport should be connected with, so the
+/// broker marks the home `offline` if the bridge disappears.
+pub fn availability_will(prefix: &str) -> Message {
+    Message::new(availability_topic(prefix), "offline", true)
+}
+
+// ── Bridge ────────────────────────────────────────────────────────────────────
+
+/// Mirrors a [`SharedHome`] to MQTT topics and applies `set` messages to it.
+///
+/// Every change announced by the [`SharedHome`] is published; a replaced home
+/// republishes all devices.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::mqtt::{availability_will, MemoryBroker, MqttBridge, DEFAULT_PREFIX};
+/// use smart_home::{room, SharedHome, SmartHome, Socket};
+/// use std::time::Duration;
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+/// let shared = SharedHome::new(home);
+///
+/// let broker = MemoryBroker::new();
+/// let transport = broker.connect(Some(availability_will(DEFAULT_PREFIX)));
+/// let mut bridge = MqttBridge::start(transport, shared.clone(), DEFAULT_PREFIX)?;
+/// assert_eq!(broker.retained("home/status").as_deref(), Some("online"));
+///
+/// let mut remote = broker.connect(None);
+/// remote.publish_str("home/hall/lamp/set", "ON");
+/// bridge.poll(Duration::ZERO)?;
+/// assert!(shared.read(|h| h.get_socket("hall", "lamp").map(|s| s.is_on()))?);
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+pub struct MqttBridge<T, C = SystemClock> {
+    transport: T,
+    shared: SharedHome,
+    events: Receiver<HomeEvent>,
+    prefix: String,
+    published: BTreeSet<(String, String)>,
+    /// Home Assistant discovery and the home name its IDs are derived from.
+    discovery: Option<(Discovery, String)>,
+    availability: Option<AvailabilityTracker<C>>,
+}
+
+impl<T: Transport> MqttBridge<T> {
+    /// Announces the home as `online`, publishes the state of every device and
+    /// subscribes to `set` topics.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the transport fails.
+    pub fn start(
+        transport: T,
+        shared: SharedHome,
+        prefix: impl Into<String>,
+    ) -> Result<Self, SmartHomeError> {
+        let (home, events) = shared.snapshot_and_subscribe();
+        let mut bridge = Self {
+            transport,
+            shared,
+            events,
+            prefix: prefix.into(),
+            published: BTreeSet::new(),
+            discovery: None,
+            availability: None,
+        };
+        bridge.publish_status("online")?;
+        for (room_key, room) in home.rooms() {
+            for (device_key, device) in room.devices() {
+                bridge.publish_state(room_key, device_key, state_payload(device))?;
+            }
+        }
+        let filter = format!("{}/+/+/set", bridge.prefix);
+        bridge.transport.subscribe(&filter)?;
+        Ok(bridge)
+    }
+}
+
+impl<T: Transport, C: Clock> MqttBridge<T, C> {
+    /// Publishes Home Assistant discovery configs for all devices, and from now
+    /// on for added devices too; removed devices are removed from Home Assistant.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the transport fails.
+    pub fn enable_discovery(&mut self, discovery: Discovery) -> Result<(), SmartHomeError> {
+        let home = self.shared.snapshot();
+        for message in discovery.home(&home) {
+            self.transport.publish(message)?;
+        }
+        self.discovery = Some((discovery, home.name().to_string()));
+        Ok(())
+    }
+
+    /// Publishes each device's availability as `online`, `stale` or `offline`
+    /// from now on, as `tracker` classifies it from the changes the bridge
+    /// sees.  Every device in the home counts as seen now.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the transport fails.
+    pub fn enable_availability<D: Clock>(
+        self,
+        mut tracker: AvailabilityTracker<D>,
+    ) -> Result<MqttBridge<T, D>, SmartHomeError> {
+        let home = self.shared.snapshot();
+        tracker.track_all(&home);
+        let mut bridge = MqttBridge {
+            transport: self.transport,
+            shared: self.shared,
+            events: self.events,
+            prefix: self.prefix,
+            published: self.published,
+            discovery: self.discovery,
+            availability: Some(tracker),
+        };
+        for (room_key, room) in home.rooms() {
+            for (device_key, _) in room.devices() {
+                bridge.publish_availability(room_key, device_key)?;
+            }
+        }
+        Ok(bridge)
+    }
+
+    /// Publishes pending home events, then waits up to `timeout` for incoming
+    /// messages and applies them.
+    ///
+    /// Rejected `set` messages are reported on the device's `error` topic and
+    /// do not stop the bridge.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the transport fails.
+    pub fn poll(&mut self, timeout: Duration) -> Result<(), SmartHomeError> {
+        self.publish_events()?;
+        let mut wait = timeout;
+        while let Some(message) = self.transport.poll(wait)? {
+            self.handle_message(&message)?;
+            wait = Duration::ZERO;
+        }
+        self.publish_events()?;
+        let changes = self
+            .availability
+            .as_mut()
+            .map(AvailabilityTracker::check)
+            .unwrap_or_default();
+        Ok(self.publish_availability_changes(&changes)?)
+    }
+
+    /// Announces the home as `offline` and returns the transport, e.g. to
+    /// disconnect it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the transport fails.
+    pub fn stop(mut self) -> Result<T, SmartHomeError> {
+        self.publish_status("offline")?;
+        Ok(self.transport)
+    }
+
+    fn publish_events(&mut self) -> Result<(), SmartHomeError> {
+        while let Ok(event) = self.events.try_recv() {
+            self.publish_event(event)?;
+        }
+        Ok(())
+    }
+
+    fn publish_event(&mut self, event: HomeEvent) -> Result<(), SmartHomeError> {
+        if let Some(tracker) = &mut self.availability {
+            let changes = tracker.observe(&event);
+            self.publish_availability_changes(&changes)?;
+        }
+        match event {
+            HomeEvent::RoomAdded { room, data } => {
+                for (device_key, device) in data.devices() {
+                    self.announce(&room, data.name(), device_key, device)?;
+                    self.publish_state(&room, device_key, state_payload(device))?;
+                }
+            }
+            HomeEvent::RoomRemoved { room } => {
+                let devices: Vec<String> = self
+                    .published
+                    .iter()
+                    .filter(|(r, _)| *r == room)
+                    .map(|(_, d)| d.clone())
+                    .collect();
+                for device in devices {
+                    self.clear_state(&room, &device)?;
+                }
+            }
+            HomeEvent::DeviceAdded { room, device, data } => {
+                let room_name = self
+                    .shared
+                    .read(|h| h.get_room(&room).map(|r| r.name().to_string()))
+                    .unwrap_or_else(|| room.clone());
+                self.announce(&room, &room_name, &device, &data)?;
+                self.publish_state(&room, &device, state_payload(&data))?;
+            }
+            HomeEvent::DeviceRemoved { room, device } => self.clear_state(&room, &device)?,
+            HomeEvent::RoomRenamed { room, to } => {
+                let devices: Vec<String> = self
+                    .published
+                    .iter()
+                    .filter(|(r, _)| *r == room)
+                    .map(|(_, d)| d.clone())
+                    .collect();
+                for device in devices {
+                    self.move_device((&room, &device), (&to, &device))?;
+                }
+            }
+            HomeEvent::DeviceRenamed { room, device, to } => {
+                self.move_device((&room, &device), (&room, &to))?
+            }
+            HomeEvent::DeviceMoved {
+                room,
+                device,
+                to_room,
+                to_device,
+            } => self.move_device((&room, &device), (&to_room, &to_device))?,
+            HomeEvent::HomeReplaced { data } => {
+                let gone: Vec<(String, String)> = self
+                    .published
+                    .iter()
+                    .filter(|(room, device)| data.get_device(room, device).is_err())
+                    .cloned()
+                    .collect();
+                for (room, device) in gone {
+                    self.clear_state(&room, &device)?;
+                }
+                for (room_key, room) in data.rooms() {
+                    for (device_key, device) in room.devices() {
+                        self.announce(room_key, room.name(), device_key, device)?;
+                        self.publish_state(room_key, device_key, state_payload(device))?;
+                    }
+                }
+            }
+            HomeEvent::FloorChanged { .. }
+            | HomeEvent::ZoneChanged { .. }
+            | HomeEvent::GroupAdded { .. }
+            | HomeEvent::GroupRemoved { .. }
+            | HomeEvent::GroupMembershipChanged { .. }
+            | HomeEvent::TagChanged { .. }
+            | HomeEvent::AttributeChanged { .. } => {}
+            HomeEvent::SocketSwitched {
+                room,
+                device,
+                on,
+                power,
+            } => self.publish_state(&room, &device, socket_payload(on, power))?,
+            HomeEvent::TemperatureChanged { room, device, .. }
+            | HomeEvent::WirelessUpdated { room, device, .. } => {
+                self.publish_current_state(&room, &device)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Publishes the device's whole state as it is now, so a new reading does
+    /// not drop the battery level from the retained message or vice versa.
+    fn publish_current_state(&mut self, room: &str, device: &str) -> Result<(), SmartHomeError> {
+        let payload = self
+            .shared
+            .read(|h| h.get_device(room, device).ok().map(state_payload));
+        match payload {
+            Some(payload) => Ok(self.publish_state(room, device, payload)?),
+            // Removed since; its own event clears the state.
+            None => Ok(()),
+        }
+    }
+
+    fn handle_message(&mut self, message: &Message) -> Result<(), SmartHomeError> {
+        let Some((room, device)) = self.parse_set_topic(&message.topic) else {
+            return Ok(());
+        };
+        let result = set_command(&room, &device, message.payload.trim())
+            .and_then(|cmd| self.shared.execute(cmd));
+        if let Err(e) = result {
+            let topic = format!("{}/{}/{}/error", self.prefix, room, device);
+            self.transport
+                .publish(Message::new(topic, e.to_string(), false))?;
+        }
+        Ok(())
+    }
+
+    fn parse_set_topic(&self, topic: &str) -> Option<(String, String)> {
+        let rest = topic.strip_prefix(&self.prefix)?.strip_prefix('/')?;
+        let rest = rest.strip_suffix("/set")?;
+        let (room, device) = rest.split_once('/')?;
+        Some((room.to_string(), device.to_string()))
+    }
+
+    fn publish_status(&mut self, status: &str) -> io::Result<()> {
+        let topic = availability_topic(&self.prefix);
+        self.transport.publish(Message::new(topic, status, true))
+    }
+
+    fn publish_state(&mut self, room: &str, device: &str, payload: String) -> io::Result<()> {
+        let topic = state_topic(&self.prefix, room, device);
+        self.transport.publish(Message::new(topic, payload, true))?;
+        self.published
+            .insert((room.to_string(), device.to_string()));
+        Ok(())
+    }
+
+    fn clear_state(&mut self, room: &str, device: &str) -> io::Result<()> {
+        let topic = state_topic(&self.prefix, room, device);
+        self.transport.publish(Message::new(topic, "", true))?;
+        self.clear_availability(room, device)?;
+        self.published
+            .remove(&(room.to_string(), device.to_string()));
+        if let Some((discovery, home_name)) = &self.discovery {
+            for message in discovery.remove_device(home_name, room, device) {
+                self.transport.publish(message)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves a device's retained state and discovery configs to its new
+    /// topics, taking its current state from the home.
+    fn move_device(&mut self, from: (&str, &str), to: (&str, &str)) -> io::Result<()> {
+        let current = self.shared.read(|h| {
+            let room = h.get_room(to.0)?;
+            Some((room.name().to_string(), room.get_device(to.1)?.clone()))
+        });
+        let Some((room_name, data)) = current else {
+            // Removed since; only take down the old topics.
+            return self.clear_state(from.0, from.1);
+        };
+        let topic = state_topic(&self.prefix, from.0, from.1);
+        self.transport.publish(Message::new(topic, "", true))?;
+        self.clear_availability(from.0, from.1)?;
+        self.publish_availability(to.0, to.1)?;
+        self.published
+            .remove(&(from.0.to_string(), from.1.to_string()));
+        if let Some((discovery, home_name)) = &self.discovery {
+            for message in discovery.move_device(home_name, from, to, &room_name, &data) {
+                self.transport.publish(message)?;
+            }
+        }
+        self.publish_state(to.0, to.1, state_payload(&data))
+    }
+
+    /// Publishes a device's current availability, if availability is enabled
+    /// and the device is tracked.
+    fn publish_availability(&mut self, room: &str, device: &str) -> io::Result<()> {
+        let status = self
+            .availability
+            .as_ref()
+            .and_then(|tracker| tracker.status(room, device));
+        match status {
+            Some(status) => {
+                let topic = device_availability_topic(&self.prefix, room, device);
+                self.transport
+                    .publish(Message::new(topic, status.to_string(), true))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn publish_availability_changes(&mut self, changes: &[AvailabilityEvent]) -> io::Result<()> {
+        for change in changes {
+            let topic = device_availability_topic(&self.prefix, &change.room, &change.device);
+            self.transport
+                .publish(Message::new(topic, change.status.to_string(), true))?;
+        }
+        Ok(())
+    }
+
+    fn clear_availability(&mut self, room: &str, device: &str) -> io::Result<()> {
+        if self.availability.is_none() {
+            return Ok(());
+        }
+        let topic = device_availability_topic(&self.prefix, room, device);
+        self.transport.publish(Message::new(topic, "", true))
+    }
+
+    /// Publishes discovery configs for a new device, if discovery is enabled.
+    fn announce(
+        &mut self,
+        room: &str,
+        room_name: &str,
+        device: &str,
+        data: &SmartDevice,
+    ) -> io::Result<()> {
+        if let Some((discovery, home_name)) = &self.discovery {
+            for message in discovery.device(home_name, room, room_name, device, data) {
+                self.transport.publish(message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds the command for a `set` payload; the device kind is checked when the
+/// command is applied.
+fn set_command(room: &str, device: &str, payload: &str) -> Result<Command, SmartHomeError> {
+    let (room, device) = (room.to_string(), device.to_string());
+    match payload.to_ascii_uppercase().as_str() {
+        "ON" => Ok(Command::TurnOn { room, device }),
+        "OFF" => Ok(Command::TurnOff { room, device }),
+        "TOGGLE" => Ok(Command::Toggle { room, device }),
+        _ => match payload.parse::<f32>() {
+            Ok(temperature) => Ok(Command::SetTemperature {
+                room,
+                device,
+                temperature,
+            }),
+            Err(_) => Err(SmartHomeError::InvalidValue(format!(
+                "expected ON, OFF, TOGGLE or a temperature, got '{}'",
+                payload
+            ))),
+        },
+    }
+}
+
+fn state_payload(device: &SmartDevice) -> String {
+    match device {
+        SmartDevice::Socket(s) => socket_payload(s.is_on(), s.power()),
+        SmartDevice::Thermometer(t) => thermometer_payload(t),
+    }
+}
+
+fn socket_payload(on: bool, power: f32) -> String {
+    json!({"state": if on { "ON" } else { "OFF" }, "power": power}).to_string()
+}
+
+fn thermometer_payload(thermometer: &Thermometer) -> String {
+    let mut payload = json!({ "temperature": thermometer.temperature() });
+    let wireless = thermometer.wireless();
+    if let Some(battery) = wireless.battery() {
+        payload["battery"] = json!(battery);
+        payload["battery_low"] = json!(wireless.is_battery_low());
+    }
+    if let Some(dbm) = wireless.signal_dbm() {
+        payload["signal_dbm"] = json!(dbm);
+    }
+    payload.to_string()
+}
+
+// ── In-memory broker ──────────────────────────────────────────────────────────
+
+/// A minimal in-process MQTT broker.
+///
+/// Supports wildcard subscriptions, retained messages and last wills.  Clones
+/// refer to the same broker.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBroker {
+    state: Arc<Mutex<BrokerState>>,
+}
+
+#[derive(Debug, Default)]
+struct BrokerState {
+    retained: BTreeMap<String, String>,
+    next_id: u64,
+    subscriptions: Vec<(u64, String, Sender<Message>)>,
+}
+
+impl BrokerState {
+    fn route(&mut self, message: Message) {
+        if message.retain {
+            if message.payload.is_empty() {
+                self.retained.remove(&message.topic);
+            } else {
+                self.retained
+                    .insert(message.topic.clone(), message.payload.clone());
+            }
+        }
+        // Subscribers see the live message without the retain flag, as MQTT does.
+        let live = Message {
+            retain: false,
+            ..message
+        };
+        let mut delivered = BTreeSet::new();
+        for (id, filter, tx) in &self.subscriptions {
+            if topic_matches(filter, &live.topic) && delivered.insert(*id) {
+                let _ = tx.send(live.clone());
+            }
+        }
+    }
+}
+
+impl MemoryBroker {
+    /// Creates an empty broker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connects a client; `will` is published if the client is dropped without
+    /// calling [`MemoryClient::disconnect`].
+    pub fn connect(&self, will: Option<Message>) -> MemoryClient {
+        let id = {
+            let mut state = self.lock();
+            state.next_id += 1;
+            state.next_id
+        };
+        let (tx, rx) = mpsc::channel();
+        MemoryClient {
+            broker: self.clone(),
+            id,
+            tx,
+            rx,
+            will,
+        }
+    }
+
+    /// Returns the retained payload of `topic`, if any.
+    pub fn retained(&self, topic: &str) -> Option<String> {
+        self.lock().retained.get(topic).cloned()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BrokerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A client of a [`MemoryBroker`].
+#[derive(Debug)]
+pub struct MemoryClient {
+    broker: MemoryBroker,
+    id: u64,
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+    will: Option<Message>,
+}
+
+impl MemoryClient {
+    /// Publishes a non-retained text message; convenient in tests.
+    pub fn publish_str(&mut self, topic: &str, payload: &str) {
+        self.broker
+            .lock()
+            .route(Message::new(topic, payload, false));
+    }
+
+    /// Disconnects cleanly, so the last will is not published.
+    pub fn disconnect(mut self) {
+        self.will = None;
+    }
+}
+
+impl Transport for MemoryClient {
+    fn publish(&mut self, message: Message) -> io::Result<()> {
+        self.broker.lock().route(message);
+        Ok(())
+    }
+
+    fn subscribe(&mut self, filter: &str) -> io::Result<()> {
+        let mut state = self.broker.lock();
+        for (topic, payload) in &state.retained {
+            if topic_matches(filter, topic) {
+                let _ = self.tx.send(Message::new(topic, payload, true));
+            }
+        }
+        state
+            .subscriptions
+            .push((self.id, filter.to_string(), self.tx.clone()));
+        Ok(())
+    }
+
+    fn poll(&mut self, timeout: Duration) -> io::Result<Option<Message>> {
+        match self.rx.recv_timeout(timeout) {
+            Ok(message) => Ok(Some(message)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            // We hold a sender ourselves, so the channel never disconnects.
+            Err(RecvTimeoutError::Disconnected) => unreachable!(),
+        }
+    }
+}
+
+impl Drop for MemoryClient {
+    fn drop(&mut self) {
+        let mut state = self.broker.lock();
+        state.subscriptions.retain(|(id, _, _)| *id != self.id);
+        if let Some(will) = self.will.take() {
+            state.route(will);
+        }
+    }
+}
+
+// ── rumqttc transport ─────────────────────────────────────────────────────────
+
+/// A [`Transport`] over a network connection to a real broker.
+pub struct RumqttTransport {
+    client: rumqttc::Client,
+    connection: rumqttc::Connection,
+}
+
+impl RumqttTransport {
+    /// Connects to the broker at `host:port` as `client_id`, registering `will`
+    /// as the last will.
+    ///
+    /// The connection is established lazily by the first [`Transport::poll`].
+    pub fn connect(client_id: &str, host: &str, port: u16, will: Option<Message>) -> Self {
+        let mut options = rumqttc::MqttOptions::new(client_id, host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(will) = will {
+            options.set_last_will(rumqttc::LastWill::new(
+                will.topic,
+                will.payload,
+                rumqttc::QoS::AtLeastOnce,
+                will.retain,
+            ));
+        }
+        let (client, connection) = rumqttc::Client::new(options, 64);
+        Self { client, connection }
+    }
+}
+
+impl Transport for RumqttTransport {
+    fn publish(&mut self, message: Message) -> io::Result<()> {
+        self.client
+            .publish(
+                message.topic,
+                rumqttc::QoS::AtLeastOnce,
+                message.retain,
+                message.payload,
+            )
+            .map_err(io::Error::other)
+    }
+
+    fn subscribe(&mut self, filter: &str) -> io::Result<()> {
+        self.client
+            .subscribe(filter, rumqttc::QoS::AtLeastOnce)
+            .map_err(io::Error::other)
+    }
+
+    fn poll(&mut self, timeout: Duration) -> io::Result<Option<Message>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            // Polling also drives outgoing publishes, so give it a moment even
+            // with a zero timeout.
+            let event = match self
+                .connection
+                .recv_timeout(left.max(Duration::from_millis(1)))
+            {
+                Ok(event) => event.map_err(io::Error::other)?,
+                Err(rumqttc::RecvTimeoutError::Timeout) => return Ok(None),
+                Err(rumqttc::RecvTimeoutError::Disconnected) => {
+                    return Err(io::ErrorKind::NotConnected.into())
+                }
+            };
+            if let rumqttc::Event::Incoming(rumqttc::Packet::Publish(p)) = event {
+                let payload = String::from_utf8_lossy(&p.payload).into_owned();
+                return Ok(Some(Message::new(p.topic, payload, p.retain)));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("home/+/+/set", "home/kitchen/kettle/set"));
+        assert!(topic_matches("home/#", "home/kitchen/kettle/state"));
+        assert!(topic_matches("home/status", "home/status"));
+        assert!(!topic_matches("home/+/+/set", "home/kitchen/set"));
+        assert!(!topic_matches("home/+", "home/kitchen/kettle"));
+        assert!(!topic_matches("home/status", "home/status/x"));
+    }
+
+    #[test]
+    fn test_retained_delivered_on_subscribe() {
+        let broker = MemoryBroker::new();
+        let mut a = broker.connect(None);
+        a.publish(Message::new("t/1", "one", true)).unwrap();
+        a.publish(Message::new("t/2", "two", false)).unwrap();
+
+        let mut b = broker.connect(None);
+        b.subscribe("t/+").unwrap();
+        assert_eq!(
+            b.poll(Duration::ZERO).unwrap(),
+            Some(Message::new("t/1", "one", true))
+        );
+        assert_eq!(b.poll(Duration::ZERO).unwrap(), None);
+
+        a.publish(Message::new("t/1", "", true)).unwrap();
+        assert_eq!(broker.retained("t/1"), None);
+    }
+
+    #[test]
+    fn test_will_only_on_unclean_disconnect() {
+        let broker = MemoryBroker::new();
+        let will = Message::new("s", "offline", true);
+        broker.connect(Some(will.clone())).disconnect();
+        assert_eq!(broker.retained("s"), None);
+        drop(broker.connect(Some(will)));
+        assert_eq!(broker.retained("s").as_deref(), Some("offline"));
+    }
+
+    #[test]
+    fn test_set_command() {
+        assert!(matches!(
+            set_command("r", "d", "on"),
+            Ok(Command::TurnOn { .. })
+        ));
+        assert!(matches!(
+            set_command("r", "d", "21.5"),
+            Ok(Command::SetTemperature { temperature, .. }) if temperature == 21.5
+        ));
+        assert!(matches!(
+            set_command("r", "d", "maybe"),
+            Err(SmartHomeError::InvalidValue(_))
+        ));
+    }
+}
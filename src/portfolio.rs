@@ -0,0 +1,416 @@
+//! Many homes managed together.
+//!
+//! A [`Portfolio`] holds any number of [`SmartHome`]s under keys, e.g. the
+//! apartments of a building or the properties of a landlord.  Devices are
+//! addressed across homes with paths like `apt12/bedroom/heater`, and energy
+//! and temperature figures can be aggregated over the whole portfolio.
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::metadata::Query;
+use crate::report::Report;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+/// Splits a `home/room/device` path into its keys.
+///
+/// The device key is everything after the second `/`.
+///
+/// # Errors
+///
+/// Returns [`SmartHomeError::InvalidValue`] if a key is missing or empty.
+pub fn parse_path(path: &str) -> Result<(&str, &str, &str), SmartHomeError> {
+    let mut parts = path.splitn(3, '/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(home), Some(room), Some(device))
+            if !home.is_empty() && !room.is_empty() && !device.is_empty() =>
+        {
+            Ok((home, room, device))
+        }
+        _ => Err(SmartHomeError::InvalidValue(format!(
+            "'{}' is not a device path; expected home/room/device",
+            path
+        ))),
+    }
+}
+
+/// Key figures of one home in a portfolio.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HomeSummary {
+    /// Home key in the portfolio.
+    pub key: String,
+    /// Home name.
+    pub name: String,
+    /// Number of rooms.
+    pub rooms: usize,
+    /// Number of devices.
+    pub devices: usize,
+    /// Total power drawn by sockets, in watts.
+    pub power: f32,
+    /// Mean reading of all thermometers, or `None` without thermometers.
+    pub average_temperature: Option<f32>,
+}
+
+/// Homes stored under keys, sorted by key.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::portfolio::Portfolio;
+/// use smart_home::{room, Command, SmartHome, Socket};
+///
+/// let mut portfolio = Portfolio::new("Elm Street 5");
+/// for apartment in ["apt11", "apt12"] {
+///     let mut home = SmartHome::new(apartment);
+///     home.add_room("bedroom", room!("Bedroom", "heater" => Socket::new("Heater", 1500.0)))?;
+///     portfolio.add_home(apartment, home)?;
+/// }
+///
+/// portfolio.apply("apt12", Command::TurnOn { room: "bedroom".into(), device: "heater".into() })?;
+/// assert!(portfolio.get_device("apt12/bedroom/heater")?.as_socket().unwrap().is_on());
+/// assert_eq!(portfolio.power(), 1500.0);
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Portfolio {
+    name: String,
+    #[serde(default)]
+    homes: BTreeMap<String, SmartHome>,
+}
+
+impl Portfolio {
+    /// Creates an empty portfolio.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            homes: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the portfolio name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the number of homes.
+    pub fn home_count(&self) -> usize {
+        self.homes.len()
+    }
+
+    /// Returns `(key, home)` pairs sorted by key.
+    pub fn homes(&self) -> impl Iterator<Item = (&str, &SmartHome)> {
+        self.homes.iter().map(|(k, h)| (k.as_str(), h))
+    }
+
+    /// Adds a home under the given key.
+    ///
+    /// # Errors
+    ///
+    /// - [`SmartHomeError::InvalidValue`] if the key is empty or contains `/`,
+    ///   so device paths could not address the home.
+    /// - [`SmartHomeError::DuplicateHome`] if the key is taken.
+    pub fn add_home(
+        &mut self,
+        key: impl Into<String>,
+        home: SmartHome,
+    ) -> Result<(), SmartHomeError> {
+        let key = key.into();
+        if key.is_empty() || key.contains('/') {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "'{}' is not a valid home key; it must be non-empty and without '/'",
+                key
+            )));
+        }
+        match self.homes.entry(key) {
+            Entry::Occupied(e) => Err(SmartHomeError::DuplicateHome(e.key().clone())),
+            Entry::Vacant(e) => {
+                e.insert(home);
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes and returns the home with the given key, or `None` if absent.
+    pub fn remove_home(&mut self, key: &str) -> Option<SmartHome> {
+        self.homes.remove(key)
+    }
+
+    /// Returns the home with the given key, or `None`.
+    pub fn get_home(&self, key: &str) -> Option<&SmartHome> {
+        self.homes.get(key)
+    }
+
+    /// Returns the home with the given key for editing, or `None`.
+    pub fn get_home_mut(&mut self, key: &str) -> Option<&mut SmartHome> {
+        self.homes.get_mut(key)
+    }
+
+    /// Returns the device at a `home/room/device` path.
+    ///
+    /// # Errors
+    ///
+    /// - [`SmartHomeError::InvalidValue`] if `path` is not a device path.
+    /// - [`SmartHomeError::HomeNotFound`] if there is no such home.
+    /// - Same as [`SmartHome::get_device`] otherwise.
+    pub fn get_device(&self, path: &str) -> Result<&SmartDevice, SmartHomeError> {
+        let (home, room, device) = parse_path(path)?;
+        self.home(home)?.get_device(room, device)
+    }
+
+    /// Returns the device at a `home/room/device` path for editing.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Portfolio::get_device`].
+    pub fn get_device_mut(&mut self, path: &str) -> Result<&mut SmartDevice, SmartHomeError> {
+        let (home, room, device) = parse_path(path)?;
+        self.home_mut(home)?.get_device_mut(room, device)
+    }
+
+    /// Applies `command` to the home with key `home`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::HomeNotFound`] if there is no such home, or
+    /// the error from [`Command::apply`].
+    pub fn apply(&mut self, home: &str, command: Command) -> Result<HomeEvent, SmartHomeError> {
+        command.apply(self.home_mut(home)?)
+    }
+
+    /// Returns the `home/room/device` paths and devices matching `query` in
+    /// all homes, sorted by path.
+    pub fn find_devices(&self, query: &Query) -> Vec<(String, &SmartDevice)> {
+        self.homes()
+            .flat_map(|(key, home)| {
+                home.find_devices(query)
+                    .into_iter()
+                    .map(move |(room, device, d)| (format!("{}/{}/{}", key, room, device), d))
+            })
+            .collect()
+    }
+
+    /// Returns the total power drawn by sockets in all homes, in watts.
+    pub fn power(&self) -> f32 {
+        self.summaries()
+            .iter()
+            .map(|s| s.power)
+            .fold(0.0, |total, power| total + power)
+    }
+
+    /// Returns the mean reading of all thermometers in all homes, or `None`
+    /// if there are none.
+    pub fn average_temperature(&self) -> Option<f32> {
+        mean(self.homes.values().flat_map(readings))
+    }
+
+    /// Returns key figures for every home, sorted by key.
+    pub fn summaries(&self) -> Vec<HomeSummary> {
+        self.homes()
+            .map(|(key, home)| {
+                let devices = home.rooms().flat_map(|(_, room)| room.devices());
+                HomeSummary {
+                    key: key.to_string(),
+                    name: home.name().to_string(),
+                    rooms: home.room_count(),
+                    devices: devices.count(),
+                    power: home
+                        .rooms()
+                        .map(|(_, room)| room.power())
+                        .fold(0.0, |total, power| total + power),
+                    average_temperature: mean(readings(home)),
+                }
+            })
+            .collect()
+    }
+
+    fn home(&self, key: &str) -> Result<&SmartHome, SmartHomeError> {
+        self.homes
+            .get(key)
+            .ok_or_else(|| SmartHomeError::HomeNotFound(key.to_string()))
+    }
+
+    fn home_mut(&mut self, key: &str) -> Result<&mut SmartHome, SmartHomeError> {
+        self.homes
+            .get_mut(key)
+            .ok_or_else(|| SmartHomeError::HomeNotFound(key.to_string()))
+    }
+}
+
+fn readings(home: &SmartHome) -> impl Iterator<Item = f32> + '_ {
+    home.rooms()
+        .flat_map(|(_, room)| room.devices())
+        .filter_map(|(_, device)| device.as_thermometer())
+        .map(|thermometer| thermometer.temperature())
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
+
+// ── Report ────────────────────────────────────────────────────────────────────
+
+impl Report for Portfolio {
+    fn report(&self) -> String {
+        let sep = "=".repeat(50);
+        let mut s = format!(
+            "\n{}\nPortfolio '{}' ({} home(s), {} W):\n{}\n",
+            sep,
+            self.name,
+            self.homes.len(),
+            self.power(),
+            sep
+        );
+        for summary in self.summaries() {
+            let temperature = summary
+                .average_temperature
+                .map_or("no thermometers".to_string(), |t| format!("{:.1} °C", t));
+            s.push_str(&format!(
+                "  [{}] '{}': {} room(s), {} device(s), {} W, {}\n",
+                summary.key,
+                summary.name,
+                summary.rooms,
+                summary.devices,
+                summary.power,
+                temperature
+            ));
+        }
+        s.push_str(&format!("\n{}\n", sep));
+        s
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_portfolio() -> Portfolio {
+        let mut portfolio = Portfolio::new("Elm Street 5");
+        for (key, celsius) in [("apt11", 20.0), ("apt12", 23.0)] {
+            let mut home = SmartHome::new(key.to_uppercase());
+            home.add_room(
+                "bedroom",
+                room!(
+                    "Bedroom",
+                    "heater" => Socket::new("Heater", 1500.0),
+                    "sensor" => Thermometer::new("Sensor", celsius),
+                ),
+            )
+            .unwrap();
+            portfolio.add_home(key, home).unwrap();
+        }
+        portfolio
+    }
+
+    #[test]
+    fn test_routing_paths() {
+        let mut portfolio = make_portfolio();
+        portfolio
+            .get_device_mut("apt12/bedroom/heater")
+            .unwrap()
+            .as_socket_mut()
+            .unwrap()
+            .turn_on();
+        assert_eq!(portfolio.power(), 1500.0);
+
+        assert!(matches!(
+            portfolio.get_device("apt99/bedroom/heater"),
+            Err(SmartHomeError::HomeNotFound(ref key)) if key == "apt99"
+        ));
+        assert!(matches!(
+            portfolio.get_device("apt11/kitchen/kettle"),
+            Err(SmartHomeError::RoomNotFound(_))
+        ));
+        for bad in ["apt11", "apt11/bedroom", "apt11//heater", ""] {
+            assert!(matches!(
+                portfolio.get_device(bad),
+                Err(SmartHomeError::InvalidValue(_))
+            ));
+        }
+        assert_eq!(parse_path("a/b/c/d").unwrap(), ("a", "b", "c/d"));
+    }
+
+    #[test]
+    fn test_add_apply_and_remove() {
+        let mut portfolio = make_portfolio();
+        assert!(matches!(
+            portfolio.add_home("apt11", SmartHome::new("Again")),
+            Err(SmartHomeError::DuplicateHome(_))
+        ));
+        for key in ["", "apt/12"] {
+            assert!(matches!(
+                portfolio.add_home(key, SmartHome::new("Unreachable")),
+                Err(SmartHomeError::InvalidValue(_))
+            ));
+        }
+        assert_eq!(portfolio.home_count(), 2);
+        let event = portfolio
+            .apply(
+                "apt11",
+                Command::Toggle {
+                    room: "bedroom".into(),
+                    device: "heater".into(),
+                },
+            )
+            .unwrap();
+        assert!(matches!(event, HomeEvent::SocketSwitched { on: true, .. }));
+        assert!(portfolio
+            .apply("apt99", Command::RemoveRoom { room: "x".into() })
+            .is_err());
+        assert_eq!(portfolio.remove_home("apt11").unwrap().name(), "APT11");
+        assert_eq!(portfolio.home_count(), 1);
+    }
+
+    #[test]
+    fn test_aggregates_and_report() {
+        let mut portfolio = make_portfolio();
+        portfolio
+            .add_home("office", SmartHome::new("Office"))
+            .unwrap();
+        assert_eq!(portfolio.average_temperature(), Some(21.5));
+
+        let summaries = portfolio.summaries();
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[1].average_temperature, Some(23.0));
+        assert_eq!(summaries[1].devices, 2);
+        assert_eq!(summaries[2].average_temperature, None);
+
+        let report = portfolio.report();
+        assert!(report.contains("Portfolio 'Elm Street 5' (3 home(s), 0 W)"));
+        assert!(report.contains("[apt12] 'APT12': 1 room(s), 2 device(s), 0 W, 23.0 °C"));
+        assert!(report.contains("[office] 'Office': 0 room(s), 0 device(s), 0 W, no thermometers"));
+    }
+
+    #[test]
+    fn test_find_devices_across_homes() {
+        let mut portfolio = make_portfolio();
+        portfolio
+            .get_device_mut("apt11/bedroom/heater")
+            .unwrap()
+            .metadata_mut()
+            .add_tag("critical");
+        let found = portfolio.find_devices(&Query::new().kind("thermometer"));
+        let paths: Vec<_> = found.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["apt11/bedroom/sensor", "apt12/bedroom/sensor"]);
+        assert_eq!(
+            portfolio.find_devices(&Query::new().tag("critical"))[0].0,
+            "apt11/bedroom/heater"
+        );
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let portfolio = make_portfolio();
+        let json = serde_json::to_string(&portfolio).unwrap();
+        let back: Portfolio = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.home_count(), 2);
+        assert!(back.get_device("apt12/bedroom/sensor").is_ok());
+    }
+}
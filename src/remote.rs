@@ -0,0 +1,679 @@
+//! Authenticated, encrypted remote control of a home over TCP.
+//!
+//! Available with the `remote` feature.  A [`RemoteServer`] lets devices and
+//! controllers on the network execute [`Command`]s against a [`SharedHome`],
+//! but only if they hold the same [`PresharedKey`].  Every connection starts
+//! with a challenge-response handshake that proves both sides know the key
+//! without sending it:
+//!
+//! ```text
+//! client -> server   "SHR1" | client nonce (32)
+//! server -> client   server nonce (32) | HMAC(key, "server" | nonces)
+//! client -> server   HMAC(key, "client" | nonces)
+//! ```
+//!
+//! Both sides then derive a session key from the pre-shared key and the two
+//! nonces, and every further frame is encrypted and authenticated with
+//! ChaCha20-Poly1305.  Frames carry a per-direction counter that must increase,
+//! so a recorded frame is rejected when replayed, whether on the same
+//! connection or (because the session key differs) on another one.
+//!
+//! On the wire each frame is a big-endian `u32` length followed by that many
+//! bytes.  Decrypted requests are JSON [`Command`]s; replies are
+//! `{"event": {...}}` or `{"error": "..."}`.
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::shared::SharedHome;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// First bytes of a client hello; identifies the protocol and its version.
+const MAGIC: &[u8; 4] = b"SHR1";
+
+/// Length of handshake nonces, HMAC tags and keys, in bytes.
+const LEN: usize = 32;
+
+/// Largest frame accepted after the handshake, in bytes.
+const MAX_FRAME: usize = 1024 * 1024;
+
+/// Sizes of the handshake frames: hello, challenge and proof.  Frames read
+/// before the peer is authenticated are capped at these, so an unknown host
+/// cannot make the server allocate more.
+const HELLO_LEN: usize = MAGIC.len() + LEN;
+const CHALLENGE_LEN: usize = 2 * LEN;
+const PROOF_LEN: usize = LEN;
+
+/// How long either side waits for the other during the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn auth_error(reason: &str) -> SmartHomeError {
+    SmartHomeError::AuthenticationFailed(reason.to_string())
+}
+
+// ── Keys and handshake ────────────────────────────────────────────────────────
+
+/// A secret shared by the server and its clients.
+///
+/// Written as 64 hex digits in configuration files; `Debug` never shows it.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PresharedKey([u8; LEN]);
+
+impl PresharedKey {
+    /// Creates a key from 32 secret bytes.
+    pub fn from_bytes(bytes: [u8; LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Creates a new random key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the system random source fails.
+    pub fn generate() -> Result<Self, SmartHomeError> {
+        Ok(Self(random_bytes()?))
+    }
+
+    /// Returns the key as 64 lowercase hex digits.
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn mac(&self, label: &[u8], client_nonce: &[u8], server_nonce: &[u8]) -> HmacSha256 {
+        let mut mac =
+            <HmacSha256 as Mac>::new_from_slice(&self.0).expect("HMAC accepts any key length");
+        mac.update(label);
+        mac.update(client_nonce);
+        mac.update(server_nonce);
+        mac
+    }
+}
+
+impl FromStr for PresharedKey {
+    type Err = SmartHomeError;
+
+    /// Parses 64 hex digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SmartHomeError::InvalidValue("key must be 64 hex digits".to_string());
+        let s = s.trim();
+        if s.len() != LEN * 2 || !s.is_ascii() {
+            return Err(invalid());
+        }
+        let mut bytes = [0; LEN];
+        for (byte, pair) in bytes.iter_mut().zip(s.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Debug for PresharedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PresharedKey(..)")
+    }
+}
+
+fn random_bytes() -> Result<[u8; LEN], SmartHomeError> {
+    let mut bytes = [0; LEN];
+    getrandom::getrandom(&mut bytes).map_err(|e| SmartHomeError::Io(e.into()))?;
+    Ok(bytes)
+}
+
+/// The client side of a handshake in progress.
+pub struct ClientHandshake {
+    key: PresharedKey,
+    client_nonce: [u8; LEN],
+}
+
+impl ClientHandshake {
+    /// Starts a handshake and returns the hello frame to send to the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the system random source fails.
+    pub fn start(key: &PresharedKey) -> Result<(Self, Vec<u8>), SmartHomeError> {
+        let client_nonce = random_bytes()?;
+        let hello = [MAGIC.as_slice(), &client_nonce].concat();
+        let handshake = Self {
+            key: key.clone(),
+            client_nonce,
+        };
+        Ok((handshake, hello))
+    }
+
+    /// Checks the server's challenge and returns the session together with
+    /// the proof frame to send back.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::AuthenticationFailed`] if the server does not
+    /// know the key.
+    pub fn finish(self, challenge: &[u8]) -> Result<(Session, Vec<u8>), SmartHomeError> {
+        if challenge.len() != CHALLENGE_LEN {
+            return Err(auth_error("malformed challenge"));
+        }
+        let (server_nonce, server_proof) = challenge.split_at(LEN);
+        self.key
+            .mac(b"server", &self.client_nonce, server_nonce)
+            .verify_slice(server_proof)
+            .map_err(|_| auth_error("server does not know the key"))?;
+        let proof = self
+            .key
+            .mac(b"client", &self.client_nonce, server_nonce)
+            .finalize()
+            .into_bytes()
+            .to_vec();
+        let session = Session::new(&self.key, &self.client_nonce, server_nonce, Side::Client);
+        Ok((session, proof))
+    }
+}
+
+/// The server side of a handshake in progress.
+pub struct ServerHandshake {
+    key: PresharedKey,
+    client_nonce: [u8; LEN],
+    server_nonce: [u8; LEN],
+}
+
+impl ServerHandshake {
+    /// Answers a client hello and returns the challenge frame to send back.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::AuthenticationFailed`] if `hello` is not a
+    /// hello frame, or [`SmartHomeError::Io`] if the random source fails.
+    pub fn respond(key: &PresharedKey, hello: &[u8]) -> Result<(Self, Vec<u8>), SmartHomeError> {
+        let client_nonce = hello
+            .strip_prefix(MAGIC.as_slice())
+            .and_then(|nonce| <[u8; LEN]>::try_from(nonce).ok())
+            .ok_or_else(|| auth_error("malformed hello"))?;
+        let server_nonce = random_bytes()?;
+        let proof = key.mac(b"server", &client_nonce, &server_nonce).finalize();
+        let challenge = [server_nonce.as_slice(), &proof.into_bytes()].concat();
+        let handshake = Self {
+            key: key.clone(),
+            client_nonce,
+            server_nonce,
+        };
+        Ok((handshake, challenge))
+    }
+
+    /// Checks the client's proof and returns the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::AuthenticationFailed`] if the client does not
+    /// know the key.
+    pub fn finish(self, proof: &[u8]) -> Result<Session, SmartHomeError> {
+        self.key
+            .mac(b"client", &self.client_nonce, &self.server_nonce)
+            .verify_slice(proof)
+            .map_err(|_| auth_error("client does not know the key"))?;
+        Ok(Session::new(
+            &self.key,
+            &self.client_nonce,
+            &self.server_nonce,
+            Side::Server,
+        ))
+    }
+}
+
+// ── Session ───────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Client,
+    Server,
+}
+
+impl Side {
+    /// Nonce prefix for frames sent by this side, so the directions never
+    /// share a nonce.
+    fn prefix(self) -> [u8; 4] {
+        match self {
+            Side::Client => *b"clnt",
+            Side::Server => *b"srvr",
+        }
+    }
+
+    fn peer(self) -> Side {
+        match self {
+            Side::Client => Side::Server,
+            Side::Server => Side::Client,
+        }
+    }
+}
+
+/// An established connection's encryption state.
+///
+/// Frames are the 8-byte big-endian send counter followed by the ciphertext
+/// and its tag; the counter is also the nonce.
+pub struct Session {
+    cipher: ChaCha20Poly1305,
+    side: Side,
+    sent: u64,
+    /// Lowest counter still accepted from the peer.
+    next_received: u64,
+}
+
+impl Session {
+    fn new(key: &PresharedKey, client_nonce: &[u8], server_nonce: &[u8], side: Side) -> Self {
+        let session_key = key
+            .mac(b"session", client_nonce, server_nonce)
+            .finalize()
+            .into_bytes();
+        Self {
+            cipher: ChaCha20Poly1305::new(&session_key),
+            side,
+            sent: 0,
+            next_received: 0,
+        }
+    }
+
+    /// Encrypts `message` into a frame for the peer.
+    pub fn seal(&mut self, message: &[u8]) -> Vec<u8> {
+        let counter = self.sent;
+        self.sent += 1;
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce(self.side, counter), message)
+            .expect("messages are far below the ChaCha20 length limit");
+        [counter.to_be_bytes().as_slice(), &ciphertext].concat()
+    }
+
+    /// Decrypts a frame from the peer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::AuthenticationFailed`] if the frame was
+    /// altered, sealed with another session, or already received.
+    pub fn open(&mut self, frame: &[u8]) -> Result<Vec<u8>, SmartHomeError> {
+        if frame.len() < 8 {
+            return Err(auth_error("truncated frame"));
+        }
+        let (counter, ciphertext) = frame.split_at(8);
+        let counter = u64::from_be_bytes(counter.try_into().expect("split at 8 bytes"));
+        if counter < self.next_received {
+            return Err(auth_error("replayed frame"));
+        }
+        let message = self
+            .cipher
+            .decrypt(&nonce(self.side.peer(), counter), ciphertext)
+            .map_err(|_| auth_error("frame failed integrity check"))?;
+        self.next_received = counter + 1;
+        Ok(message)
+    }
+}
+
+fn nonce(sender: Side, counter: u64) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[..4].copy_from_slice(&sender.prefix());
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
+
+// ── Framing ───────────────────────────────────────────────────────────────────
+
+fn write_frame(stream: &mut impl Write, frame: &[u8]) -> Result<(), SmartHomeError> {
+    let len = u32::try_from(frame.len()).expect("frames are below MAX_FRAME");
+    stream.write_all(&len.to_be_bytes())?;
+    stream.write_all(frame)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Reads one frame of at most `limit` bytes.
+fn read_frame(stream: &mut impl Read, limit: usize) -> Result<Vec<u8>, SmartHomeError> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > limit {
+        return Err(SmartHomeError::InvalidValue(format!(
+            "frame of {} bytes exceeds the {} byte limit",
+            len, limit
+        )));
+    }
+    let mut frame = vec![0; len];
+    stream.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Reply {
+    Event(HomeEvent),
+    Error(String),
+}
+
+// ── Server ────────────────────────────────────────────────────────────────────
+
+/// Serves authenticated remote commands for a [`SharedHome`].
+///
+/// Each connection is served on its own thread.  Connections that fail the
+/// handshake or send a frame that does not decrypt are closed.
+pub struct RemoteServer {
+    listener: TcpListener,
+    shared: SharedHome,
+    key: PresharedKey,
+    stop: Arc<AtomicBool>,
+}
+
+impl RemoteServer {
+    /// Binds the server to `addr` (use port 0 to pick a free port).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the address cannot be bound.
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        shared: SharedHome,
+        key: PresharedKey,
+    ) -> Result<Self, SmartHomeError> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            shared,
+            key,
+            stop: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.listener
+            .local_addr()
+            .expect("listener is bound to an address")
+    }
+
+    /// Accepts connections until the server is shut down through a
+    /// [`RemoteHandle`].
+    pub fn run(&self) {
+        for stream in self.listener.incoming() {
+            if self.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(stream) = stream else { continue };
+            let shared = self.shared.clone();
+            let key = self.key.clone();
+            thread::spawn(move || {
+                // Errors end only this connection; the client sees it drop.
+                let _ = serve_connection(stream, &shared, &key);
+            });
+        }
+    }
+
+    /// Runs the server on a background thread.
+    pub fn spawn(self) -> RemoteHandle {
+        let addr = self.local_addr();
+        let stop = Arc::clone(&self.stop);
+        let thread = thread::spawn(move || self.run());
+        RemoteHandle {
+            addr,
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+fn serve_connection(
+    mut stream: TcpStream,
+    shared: &SharedHome,
+    key: &PresharedKey,
+) -> Result<(), SmartHomeError> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let hello = read_frame(&mut stream, HELLO_LEN)?;
+    let (handshake, challenge) = ServerHandshake::respond(key, &hello)?;
+    write_frame(&mut stream, &challenge)?;
+    let mut session = handshake.finish(&read_frame(&mut stream, PROOF_LEN)?)?;
+    // Tells the client its proof was accepted.
+    write_frame(&mut stream, &session.seal(b""))?;
+    stream.set_read_timeout(None)?;
+
+    loop {
+        let frame = match read_frame(&mut stream, MAX_FRAME) {
+            Ok(frame) => frame,
+            Err(SmartHomeError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let request = session.open(&frame)?;
+        let reply = serde_json::from_slice::<Command>(&request)
+            .map_err(|e| e.to_string())
+            .and_then(|command| shared.execute(command).map_err(|e| e.to_string()));
+        let reply = match reply {
+            Ok(event) => Reply::Event(event),
+            Err(message) => Reply::Error(message),
+        };
+        let json = serde_json::to_vec(&reply).expect("events are always serializable");
+        write_frame(&mut stream, &session.seal(&json))?;
+    }
+}
+
+/// Handle to a server started with [`RemoteServer::spawn`].
+pub struct RemoteHandle {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RemoteHandle {
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stops accepting connections and waits for the accept thread to exit.
+    /// Established connections stay open until their clients disconnect.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the blocking accept() so the loop sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for RemoteHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// ── Client ────────────────────────────────────────────────────────────────────
+
+/// An authenticated connection to a [`RemoteServer`].
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::remote::{PresharedKey, RemoteClient, RemoteServer};
+/// use smart_home::{room, Command, SharedHome, SmartHome, Socket};
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+/// let shared = SharedHome::new(home);
+/// let key = PresharedKey::generate()?;
+///
+/// let server = RemoteServer::bind("127.0.0.1:0", shared.clone(), key.clone())?.spawn();
+/// let mut client = RemoteClient::connect(server.local_addr(), &key)?;
+/// client.execute(Command::TurnOn { room: "hall".into(), device: "lamp".into() })?;
+/// assert!(shared.read(|home| home.get_socket("hall", "lamp").unwrap().is_on()));
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+pub struct RemoteClient {
+    stream: TcpStream,
+    session: Session,
+}
+
+impl RemoteClient {
+    /// Connects to the server at `addr` and authenticates with `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::AuthenticationFailed`] if either side does not
+    /// know the key, or [`SmartHomeError::Io`] if the connection fails.
+    pub fn connect(addr: impl ToSocketAddrs, key: &PresharedKey) -> Result<Self, SmartHomeError> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let (handshake, hello) = ClientHandshake::start(key)?;
+        write_frame(&mut stream, &hello)?;
+        let challenge = read_frame(&mut stream, CHALLENGE_LEN)?;
+        let (mut session, proof) = handshake.finish(&challenge)?;
+        write_frame(&mut stream, &proof)?;
+        // The server closes the connection instead of confirming a bad proof.
+        let confirmation = match read_frame(&mut stream, MAX_FRAME) {
+            Err(SmartHomeError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                return Err(auth_error("server rejected the key"))
+            }
+            frame => frame?,
+        };
+        session.open(&confirmation)?;
+        stream.set_read_timeout(None)?;
+        Ok(Self { stream, session })
+    }
+
+    /// Executes `command` on the server's home.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Remote`] with the server's message if the
+    /// command failed there, [`SmartHomeError::AuthenticationFailed`] if the
+    /// reply does not decrypt, or [`SmartHomeError::Io`] if the connection fails.
+    pub fn execute(&mut self, command: Command) -> Result<HomeEvent, SmartHomeError> {
+        let request = serde_json::to_vec(&command)?;
+        write_frame(&mut self.stream, &self.session.seal(&request))?;
+        let reply = self
+            .session
+            .open(&read_frame(&mut self.stream, MAX_FRAME)?)?;
+        match serde_json::from_slice(&reply)? {
+            Reply::Event(event) => Ok(event),
+            Reply::Error(message) => Err(SmartHomeError::Remote(message)),
+        }
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> PresharedKey {
+        PresharedKey::from_bytes([byte; LEN])
+    }
+
+    fn handshake(
+        client_key: &PresharedKey,
+        server_key: &PresharedKey,
+    ) -> Result<(Session, Session), SmartHomeError> {
+        let (client, hello) = ClientHandshake::start(client_key)?;
+        let (server, challenge) = ServerHandshake::respond(server_key, &hello)?;
+        let (client_session, proof) = client.finish(&challenge)?;
+        Ok((client_session, server.finish(&proof)?))
+    }
+
+    fn is_auth_error<T>(result: Result<T, SmartHomeError>, reason: &str) -> bool {
+        matches!(result, Err(SmartHomeError::AuthenticationFailed(r)) if r == reason)
+    }
+
+    #[test]
+    fn test_sessions_exchange_messages() {
+        let (mut client, mut server) = handshake(&key(1), &key(1)).unwrap();
+        let frame = client.seal(b"turn on");
+        assert!(!frame.windows(7).any(|w| w == b"turn on"));
+        assert_eq!(server.open(&frame).unwrap(), b"turn on");
+        assert_eq!(client.open(&server.seal(b"done")).unwrap(), b"done");
+        assert_eq!(server.open(&client.seal(b"again")).unwrap(), b"again");
+    }
+
+    #[test]
+    fn test_wrong_key_fails_handshake() {
+        assert!(is_auth_error(
+            handshake(&key(1), &key(2)),
+            "server does not know the key"
+        ));
+
+        // A client that skips verifying the server still cannot prove itself.
+        let (client, hello) = ClientHandshake::start(&key(2)).unwrap();
+        let (server, challenge) = ServerHandshake::respond(&key(1), &hello).unwrap();
+        let forged = client
+            .key
+            .mac(b"client", &client.client_nonce, &challenge[..LEN])
+            .finalize()
+            .into_bytes();
+        assert!(is_auth_error(
+            server.finish(&forged),
+            "client does not know the key"
+        ));
+        assert!(is_auth_error(
+            ServerHandshake::respond(&key(1), b"GET / HTTP/1.1"),
+            "malformed hello"
+        ));
+    }
+
+    #[test]
+    fn test_tampered_frames_are_rejected() {
+        let (mut client, mut server) = handshake(&key(1), &key(1)).unwrap();
+        let frame = client.seal(br#"{"command":"turn_off"}"#);
+        for index in [3, 8, frame.len() - 1] {
+            let mut tampered = frame.clone();
+            tampered[index] ^= 0x01;
+            assert!(server.open(&tampered).is_err());
+        }
+        assert!(is_auth_error(server.open(&frame[..5]), "truncated frame"));
+        // The untouched frame still goes through afterwards.
+        assert!(server.open(&frame).is_ok());
+    }
+
+    #[test]
+    fn test_replayed_frames_are_rejected() {
+        let (mut client, mut server) = handshake(&key(1), &key(1)).unwrap();
+        let first = client.seal(b"toggle");
+        let second = client.seal(b"toggle");
+        server.open(&first).unwrap();
+        assert!(is_auth_error(server.open(&first), "replayed frame"));
+        server.open(&second).unwrap();
+        assert!(is_auth_error(server.open(&first), "replayed frame"));
+
+        // A frame from another session does not decrypt in this one.
+        let (mut other_client, _) = handshake(&key(1), &key(1)).unwrap();
+        let (_, mut fresh_server) = handshake(&key(1), &key(1)).unwrap();
+        let foreign = other_client.seal(b"toggle");
+        assert!(is_auth_error(
+            fresh_server.open(&foreign),
+            "frame failed integrity check"
+        ));
+
+        // Nor can a client's frame be reflected back to it as a reply.
+        let own = client.seal(b"toggle");
+        assert!(client.open(&own).is_err());
+    }
+
+    #[test]
+    fn test_key_hex_round_trip() {
+        let key = PresharedKey::generate().unwrap();
+        assert_eq!(key.to_hex().parse::<PresharedKey>().unwrap(), key);
+        assert_eq!(format!("{:?}", key), "PresharedKey(..)");
+        for bad in ["", "abc", &"zz".repeat(32), &"é".repeat(64)] {
+            assert!(bad.parse::<PresharedKey>().is_err());
+        }
+    }
+}
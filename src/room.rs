@@ -1,13 +1,28 @@
 //! Smart home room.
 
+use crate::error::SmartHomeError;
+use crate::metadata::Metadata;
 use crate::report::Report;
 use crate::smart_device::SmartDevice;
-use std::collections::HashMap;
+use crate::storage::serialize_sorted;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
 
 /// A room that holds a named collection of smart devices.
-#[derive(Debug, Clone)]
+///
+/// A room can be placed on one floor and in any number of zones; see
+/// [`Area`][crate::area::Area] for working with those groupings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Room {
     name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    floor: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    zones: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    meta: Metadata,
+    #[serde(default, serialize_with = "serialize_sorted")]
     devices: HashMap<String, SmartDevice>,
 }
 
@@ -16,6 +31,9 @@ impl Room {
     pub fn new(name: impl Into<String>) -> Self {
         Self {
             name: name.into(),
+            floor: None,
+            zones: BTreeSet::new(),
+            meta: Metadata::default(),
             devices: HashMap::new(),
         }
     }
@@ -25,17 +43,101 @@ impl Room {
         &self.name
     }
 
+    /// Returns the floor the room is on, if set.
+    pub fn floor(&self) -> Option<&str> {
+        self.floor.as_deref()
+    }
+
+    /// Places the room on `floor`, or on no floor with `None`.
+    pub fn set_floor(&mut self, floor: Option<String>) {
+        self.floor = floor;
+    }
+
+    /// Returns the zones the room belongs to, in alphabetical order.
+    pub fn zones(&self) -> impl Iterator<Item = &str> {
+        self.zones.iter().map(String::as_str)
+    }
+
+    /// Returns `true` if the room belongs to `zone`.
+    pub fn in_zone(&self, zone: &str) -> bool {
+        self.zones.contains(zone)
+    }
+
+    /// Adds the room to `zone`; returns `false` if it already was in it.
+    pub fn add_zone(&mut self, zone: impl Into<String>) -> bool {
+        self.zones.insert(zone.into())
+    }
+
+    /// Removes the room from `zone`; returns `false` if it was not in it.
+    pub fn remove_zone(&mut self, zone: &str) -> bool {
+        self.zones.remove(zone)
+    }
+
+    /// Returns the room's tags and attributes.
+    pub fn metadata(&self) -> &Metadata {
+        &self.meta
+    }
+
+    /// Returns the room's tags and attributes for editing.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.meta
+    }
+
+    /// Returns the total power drawn by the room's sockets, in watts.
+    pub fn power(&self) -> f32 {
+        self.devices
+            .values()
+            .filter_map(SmartDevice::as_socket)
+            .map(|socket| socket.power())
+            .fold(0.0, |total, power| total + power)
+    }
+
     /// Returns the number of devices in the room.
     pub fn device_count(&self) -> usize {
         self.devices.len()
     }
 
+    /// Returns an iterator over `(key, device)` pairs in arbitrary order.
+    pub fn devices(&self) -> impl Iterator<Item = (&str, &SmartDevice)> {
+        self.devices.iter().map(|(k, d)| (k.as_str(), d))
+    }
+
     /// Adds a device to the room under the given key.
     ///
     /// Accepts any type that converts into [`SmartDevice`] (e.g. [`Socket`][crate::Socket]
     /// or [`Thermometer`][crate::Thermometer]).
-    pub fn add_device(&mut self, name: impl Into<String>, device: impl Into<SmartDevice>) {
-        self.devices.insert(name.into(), device.into());
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::DuplicateDevice`] with an empty room key if a
+    /// device with this key already exists; the existing device is left untouched.  Use [`Room::insert_or_replace`]
+    /// to overwrite on purpose.
+    pub fn add_device(
+        &mut self,
+        name: impl Into<String>,
+        device: impl Into<SmartDevice>,
+    ) -> Result<(), SmartHomeError> {
+        match self.devices.entry(name.into()) {
+            Entry::Occupied(e) => Err(SmartHomeError::DuplicateDevice {
+                room: String::new(),
+                device: e.key().clone(),
+            }),
+            Entry::Vacant(e) => {
+                e.insert(device.into());
+                Ok(())
+            }
+        }
+    }
+
+    /// Inserts a device under the given key, replacing any existing one.
+    ///
+    /// Returns the replaced device, if there was one.
+    pub fn insert_or_replace(
+        &mut self,
+        name: impl Into<String>,
+        device: impl Into<SmartDevice>,
+    ) -> Option<SmartDevice> {
+        self.devices.insert(name.into(), device.into())
     }
 
     /// Removes and returns the device with the given key, or `None` if absent.
@@ -56,18 +158,37 @@ impl Room {
 
 // ── Report ────────────────────────────────────────────────────────────────────
 
-impl Report for Room {
-    fn report(&self) -> String {
+impl Room {
+    /// Builds the report with `note(device_key)` appended to each device line.
+    pub(crate) fn report_with(&self, note: impl Fn(&str) -> String) -> String {
         let mut s = format!("Room '{}' ({} device(s)):\n", self.name, self.devices.len());
+        if let Some(floor) = &self.floor {
+            s.push_str(&format!("  Floor: {}\n", floor));
+        }
+        if !self.zones.is_empty() {
+            let zones: Vec<&str> = self.zones().collect();
+            s.push_str(&format!("  Zones: {}\n", zones.join(", ")));
+        }
         let mut keys: Vec<&String> = self.devices.keys().collect();
         keys.sort();
         for key in keys {
-            s.push_str(&format!("  [{}] {}\n", key, self.devices[key].report()));
+            s.push_str(&format!(
+                "  [{}] {}{}\n",
+                key,
+                self.devices[key].report(),
+                note(key)
+            ));
         }
         s
     }
 }
 
+impl Report for Room {
+    fn report(&self) -> String {
+        self.report_with(|_| String::new())
+    }
+}
+
 // ── Tests ─────────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -85,15 +206,17 @@ mod tests {
     #[test]
     fn test_add_and_count() {
         let mut room = Room::new("Bedroom");
-        room.add_device("sensor", Thermometer::new("Sensor", 20.0));
-        room.add_device("lamp", Socket::new("Lamp", 100.0));
+        room.add_device("sensor", Thermometer::new("Sensor", 20.0))
+            .unwrap();
+        room.add_device("lamp", Socket::new("Lamp", 100.0)).unwrap();
         assert_eq!(room.device_count(), 2);
     }
 
     #[test]
     fn test_get_device() {
         let mut room = Room::new("Bedroom");
-        room.add_device("sensor", Thermometer::new("Sensor", 20.0));
+        room.add_device("sensor", Thermometer::new("Sensor", 20.0))
+            .unwrap();
 
         assert!(room
             .get_device("sensor")
@@ -106,7 +229,7 @@ mod tests {
     #[test]
     fn test_get_device_mut() {
         let mut room = Room::new("Kitchen");
-        room.add_device("lamp", Socket::new("Lamp", 100.0));
+        room.add_device("lamp", Socket::new("Lamp", 100.0)).unwrap();
 
         room.get_device_mut("lamp")
             .and_then(|d| d.as_socket_mut())
@@ -124,7 +247,8 @@ mod tests {
     #[test]
     fn test_remove_device() {
         let mut room = Room::new("Bathroom");
-        room.add_device("light", Socket::new("Light", 60.0));
+        room.add_device("light", Socket::new("Light", 60.0))
+            .unwrap();
         assert_eq!(room.device_count(), 1);
 
         assert!(room.remove_device("light").is_some());
@@ -132,10 +256,78 @@ mod tests {
         assert!(room.remove_device("light").is_none());
     }
 
+    #[test]
+    fn test_add_duplicate_device_keeps_original() {
+        let mut room = Room::new("Kitchen");
+        room.add_device("lamp", Socket::new("Lamp", 100.0)).unwrap();
+
+        let err = room
+            .add_device("lamp", Thermometer::new("Sensor", 20.0))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            SmartHomeError::DuplicateDevice { ref room, ref device }
+                if room.is_empty() && device == "lamp"
+        ));
+        assert_eq!(err.to_string(), "Device 'lamp' already exists in the room");
+        assert!(room.get_device("lamp").unwrap().as_socket().is_some());
+    }
+
+    #[test]
+    fn test_insert_or_replace() {
+        let mut room = Room::new("Kitchen");
+        assert!(room
+            .insert_or_replace("lamp", Socket::new("Lamp", 100.0))
+            .is_none());
+
+        let old = room.insert_or_replace("lamp", Thermometer::new("Sensor", 20.0));
+        assert!(old.unwrap().as_socket().is_some());
+        assert!(room.get_device("lamp").unwrap().as_thermometer().is_some());
+        assert_eq!(room.device_count(), 1);
+    }
+
+    #[test]
+    fn test_floor_zones_and_power() {
+        let mut room = Room::new("Kitchen");
+        room.add_device("kettle", Socket::new("Kettle", 2000.0))
+            .unwrap();
+        room.add_device("lamp", Socket::new("Lamp", 60.0)).unwrap();
+        room.get_device_mut("kettle")
+            .and_then(|d| d.as_socket_mut())
+            .unwrap()
+            .turn_on();
+        assert_eq!(room.power(), 2000.0);
+
+        room.set_floor(Some("ground".into()));
+        assert!(room.add_zone("heating_a"));
+        assert!(!room.add_zone("heating_a"));
+        room.add_zone("cooking");
+        assert_eq!(room.zones().collect::<Vec<_>>(), ["cooking", "heating_a"]);
+        assert!(room.remove_zone("cooking"));
+        assert!(room.in_zone("heating_a") && !room.in_zone("cooking"));
+
+        let r = room.report();
+        assert!(r.contains("Floor: ground"));
+        assert!(r.contains("Zones: heating_a"));
+    }
+
+    #[test]
+    fn test_grouping_is_optional_in_json() {
+        let room = Room::new("Hall");
+        let json = serde_json::to_string(&room).unwrap();
+        assert_eq!(json, r#"{"name":"Hall","devices":{}}"#);
+
+        let room: Room =
+            serde_json::from_str(r#"{"name":"Hall","floor":"1","zones":["north"]}"#).unwrap();
+        assert_eq!(room.floor(), Some("1"));
+        assert!(room.in_zone("north"));
+    }
+
     #[test]
     fn test_report_contains_name_and_key() {
         let mut room = Room::new("Hall");
-        room.add_device("sensor", Thermometer::new("Sensor", 22.5));
+        room.add_device("sensor", Thermometer::new("Sensor", 22.5))
+            .unwrap();
         let r = room.report();
         assert!(r.contains("Hall"));
         assert!(r.contains("sensor"));
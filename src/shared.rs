@@ -0,0 +1,359 @@
+//! Thread-safe handle to a [`SmartHome`] shared between front-ends.
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::{EventBus, HomeEvent};
+use crate::smart_home::SmartHome;
+use std::fmt;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Records a change before it is announced; see [`SharedHome::audited`].
+type Auditor = Arc<dyn Fn(&HomeEvent) -> Result<(), SmartHomeError> + Send + Sync>;
+
+/// A cloneable, thread-safe handle to one [`SmartHome`].
+///
+/// All clones refer to the same home, so a dashboard, a server and a background
+/// poller can work on it at once.  Access goes through short closures to keep
+/// the lock held only as long as needed.
+///
+/// Every change is announced to everyone who called [`SharedHome::subscribe`].
+/// Prefer [`SharedHome::execute`] and [`SharedHome::execute_all`], which
+/// describe each change; [`SharedHome::write`] and [`SharedHome::replace`]
+/// can only announce the whole new state as a [`HomeEvent::HomeReplaced`].
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::{room, SharedHome, SmartHome, Socket};
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+///
+/// let shared = SharedHome::new(home);
+/// let other = shared.clone();
+/// other.write(|h| h.get_socket_mut("hall", "lamp").map(|s| s.turn_on()))?;
+/// assert!(shared.read(|h| h.get_socket("hall", "lamp").map(|s| s.is_on()))?);
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Clone)]
+pub struct SharedHome {
+    home: Arc<Mutex<SmartHome>>,
+    events: EventBus,
+    auditor: Option<Auditor>,
+}
+
+impl SharedHome {
+    /// Wraps a home so it can be shared.
+    pub fn new(home: SmartHome) -> Self {
+        Self {
+            home: Arc::new(Mutex::new(home)),
+            events: EventBus::new(),
+            auditor: None,
+        }
+    }
+
+    /// Returns a handle to the same home that passes every change made with
+    /// it to `audit` before announcing it.  A change `audit` fails on is
+    /// rolled back.
+    ///
+    /// Clones of the returned handle are audited too; other handles are not.
+    /// [`AuditLog::attach`][crate::audit::AuditLog::attach] builds one that
+    /// records changes in an audit log.
+    pub fn audited(
+        &self,
+        audit: impl Fn(&HomeEvent) -> Result<(), SmartHomeError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            auditor: Some(Arc::new(audit)),
+            ..self.clone()
+        }
+    }
+
+    /// Runs `f` with shared access to the home.
+    pub fn read<R>(&self, f: impl FnOnce(&SmartHome) -> R) -> R {
+        f(&self.lock())
+    }
+
+    /// Runs `f` with exclusive access to the home and announces the new state
+    /// as a [`HomeEvent::HomeReplaced`] if `f` changed anything.
+    ///
+    /// `f` works on a copy that only replaces the home once it succeeded, as
+    /// with [`SmartHome::transaction`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `f` or from the auditor; the home is then
+    /// unchanged and nothing is announced.
+    pub fn write<R>(
+        &self,
+        f: impl FnOnce(&mut SmartHome) -> Result<R, SmartHomeError>,
+    ) -> Result<R, SmartHomeError> {
+        let mut home = self.lock();
+        let mut staged = home.clone();
+        let result = f(&mut staged)?;
+        if staged != *home {
+            let event = HomeEvent::HomeReplaced {
+                data: staged.clone(),
+            };
+            self.audit(&event)?;
+            *home = staged;
+            self.events.publish(&event);
+        }
+        Ok(result)
+    }
+
+    /// Applies `command` and announces the resulting event to all subscribers.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`Command::apply`]; nothing is announced then.
+    pub fn execute(&self, command: Command) -> Result<HomeEvent, SmartHomeError> {
+        if let Some(audit) = &self.auditor {
+            return self.execute_checked(command, |event| audit(event));
+        }
+        let mut home = self.lock();
+        let event = command.apply(&mut home)?;
+        // Publish while still holding the lock so subscribers see events in the
+        // order the changes were made.
+        self.events.publish(&event);
+        Ok(event)
+    }
+
+    /// Applies `command` and runs `check` on the resulting event before
+    /// announcing it; if `check` fails the change is rolled back.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`Command::apply`] or from `check`; the home is
+    /// then unchanged and nothing is announced.
+    pub fn execute_checked(
+        &self,
+        command: Command,
+        check: impl FnOnce(&HomeEvent) -> Result<(), SmartHomeError>,
+    ) -> Result<HomeEvent, SmartHomeError> {
+        let mut home = self.lock();
+        let event = home.transaction(|staged| {
+            let event = command.apply(staged)?;
+            check(&event)?;
+            Ok(event)
+        })?;
+        self.events.publish(&event);
+        Ok(event)
+    }
+
+    /// Applies `commands` in order as one transaction and announces their
+    /// events once all of them succeeded.
+    ///
+    /// Other users of the home never see a partially applied batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first command that fails; the home is then
+    /// unchanged and nothing is announced.
+    pub fn execute_all(
+        &self,
+        commands: impl IntoIterator<Item = Command>,
+    ) -> Result<Vec<HomeEvent>, SmartHomeError> {
+        let mut home = self.lock();
+        let events = home.transaction(|staged| {
+            let events = commands
+                .into_iter()
+                .map(|command| command.apply(staged))
+                .collect::<Result<Vec<_>, _>>()?;
+            events.iter().try_for_each(|event| self.audit(event))?;
+            Ok(events)
+        })?;
+        for event in &events {
+            self.events.publish(event);
+        }
+        Ok(events)
+    }
+
+    /// Returns a receiver for all changes made from now on.
+    pub fn subscribe(&self) -> Receiver<HomeEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns a copy of the current state together with a receiver for all
+    /// changes made after that copy was taken.
+    pub fn snapshot_and_subscribe(&self) -> (SmartHome, Receiver<HomeEvent>) {
+        let home = self.lock();
+        (home.clone(), self.events.subscribe())
+    }
+
+    /// Returns a copy of the current state of the home.
+    pub fn snapshot(&self) -> SmartHome {
+        self.lock().clone()
+    }
+
+    /// Replaces the whole home, returning the previous one, and announces it
+    /// as a [`HomeEvent::HomeReplaced`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from the auditor; the home is then unchanged and
+    /// nothing is announced.
+    pub fn replace(&self, home: SmartHome) -> Result<SmartHome, SmartHomeError> {
+        let mut current = self.lock();
+        let event = HomeEvent::HomeReplaced { data: home.clone() };
+        self.audit(&event)?;
+        self.events.publish(&event);
+        Ok(std::mem::replace(&mut current, home))
+    }
+
+    fn audit(&self, event: &HomeEvent) -> Result<(), SmartHomeError> {
+        match &self.auditor {
+            Some(audit) => audit(event),
+            None => Ok(()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SmartHome> {
+        // A panic in another user of the home cannot leave it half-updated in a
+        // way that matters more than losing access to it entirely.
+        self.home.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for SharedHome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedHome")
+            .field("home", &self.home)
+            .field("events", &self.events)
+            .field("audited", &self.auditor.is_some())
+            .finish()
+    }
+}
+
+impl From<SmartHome> for SharedHome {
+    fn from(home: SmartHome) -> Self {
+        Self::new(home)
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::Room;
+    use std::thread;
+
+    #[test]
+    fn test_clones_share_state() {
+        let shared = SharedHome::new(SmartHome::new("Home"));
+        let other = shared.clone();
+        other
+            .write(|h| h.add_room("kitchen", Room::new("Kitchen")))
+            .unwrap();
+        assert_eq!(shared.read(|h| h.room_count()), 1);
+    }
+
+    #[test]
+    fn test_write_from_threads() {
+        let shared = SharedHome::new(SmartHome::new("Home"));
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    shared
+                        .write(|h| h.add_room(format!("room{}", i), Room::new("Room")))
+                        .unwrap()
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(shared.snapshot().room_count(), 4);
+    }
+
+    #[test]
+    fn test_execute_publishes_events() {
+        let shared = SharedHome::new(SmartHome::new("Home"));
+        let (snapshot, events) = shared.snapshot_and_subscribe();
+        assert_eq!(snapshot.room_count(), 0);
+
+        shared
+            .execute(Command::AddRoom {
+                room: "kitchen".into(),
+                data: Room::new("Kitchen"),
+            })
+            .unwrap();
+        assert!(shared
+            .execute(Command::RemoveRoom {
+                room: "attic".into()
+            })
+            .is_err());
+
+        assert!(matches!(
+            events.try_recv(),
+            Ok(HomeEvent::RoomAdded { ref room, .. }) if room == "kitchen"
+        ));
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_execute_all_is_atomic() {
+        let shared = SharedHome::new(SmartHome::new("Home"));
+        let events = shared.subscribe();
+        let add = |room: &str| Command::AddRoom {
+            room: room.into(),
+            data: Room::new("Room"),
+        };
+
+        assert!(shared
+            .execute_all([add("kitchen"), add("hall"), add("kitchen")])
+            .is_err());
+        assert_eq!(shared.read(|h| h.room_count()), 0);
+        assert!(events.try_recv().is_err());
+
+        let applied = shared.execute_all([add("kitchen"), add("hall")]).unwrap();
+        assert_eq!(applied.len(), 2);
+        assert_eq!(shared.read(|h| h.room_count()), 2);
+        assert_eq!(events.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_replace() {
+        let shared = SharedHome::new(SmartHome::new("Old"));
+        let events = shared.subscribe();
+        let old = shared.replace(SmartHome::new("New")).unwrap();
+        assert_eq!(old.name(), "Old");
+        assert_eq!(shared.read(|h| h.name().to_string()), "New");
+        assert!(matches!(
+            events.try_recv(),
+            Ok(HomeEvent::HomeReplaced { ref data }) if data.name() == "New"
+        ));
+    }
+
+    #[test]
+    fn test_write_announces_changes() {
+        let shared = SharedHome::new(SmartHome::new("Home"));
+        let events = shared.subscribe();
+        assert!(shared
+            .write(|h| Ok(h.remove_room("attic")))
+            .unwrap()
+            .is_none());
+        assert!(events.try_recv().is_err());
+
+        shared
+            .write(|h| h.add_room("kitchen", Room::new("Kitchen")))
+            .unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(HomeEvent::HomeReplaced { ref data }) if data.room_count() == 1
+        ));
+
+        // A failing write leaves no trace, even of the changes made before
+        // the error.
+        let result = shared.write(|h| {
+            h.add_room("hall", Room::new("Hall"))?;
+            h.add_room("kitchen", Room::new("Kitchen"))
+        });
+        assert!(matches!(result, Err(SmartHomeError::DuplicateRoom(_))));
+        assert!(shared.read(|h| h.get_room("hall").is_none()));
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -0,0 +1,510 @@
+//! Thermal simulation of rooms on a virtual clock.
+//!
+//! Each simulated room is a single heat capacity losing heat to the outside
+//! through a fixed conductance.  Sockets can be tagged as heaters or coolers;
+//! while switched on they add or remove heat in proportion to their power
+//! draw.  [`Simulation::step`] advances virtual time and writes the new air
+//! temperature to every thermometer in the room, so automation rules see
+//! readings evolve as they would in a real house.
+//!
+//! Between steps the heat input is constant, so the temperature follows the
+//! exact exponential approach to equilibrium and large steps stay stable.
+//!
+//! Time can be advanced explicitly with [`Simulation::step`] or taken from a
+//! [`Clock`] with [`Simulation::sync`], e.g. a [`ManualClock`][crate::clock::ManualClock]
+//! in tests or an [`AcceleratedClock`][crate::AcceleratedClock] in demos.
+
+use crate::clock::Clock;
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::shared::SharedHome;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+
+/// Thermal properties of a room.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThermalModel {
+    heat_capacity: f32,
+    heat_loss: f32,
+}
+
+impl ThermalModel {
+    /// Creates a model.
+    ///
+    /// # Arguments
+    ///
+    /// * `heat_capacity` - Energy needed to warm the room by one degree, in J/K
+    /// * `heat_loss` - Heat lost to the outside per degree of difference, in W/K
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] unless both values are finite
+    /// and positive.
+    pub fn try_new(heat_capacity: f32, heat_loss: f32) -> Result<Self, SmartHomeError> {
+        for (what, value) in [("heat capacity", heat_capacity), ("heat loss", heat_loss)] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(SmartHomeError::InvalidValue(format!(
+                    "{} must be a positive number, got {}",
+                    what, value
+                )));
+            }
+        }
+        Ok(Self {
+            heat_capacity,
+            heat_loss,
+        })
+    }
+
+    /// Returns the heat capacity in J/K.
+    pub fn heat_capacity(&self) -> f32 {
+        self.heat_capacity
+    }
+
+    /// Returns the heat loss in W/K.
+    pub fn heat_loss(&self) -> f32 {
+        self.heat_loss
+    }
+
+    /// Returns the temperature after `dt` seconds starting from `temperature`,
+    /// with `heat` watts going in.
+    fn evolve(&self, temperature: f32, outside: f32, heat: f32, dt: f32) -> f32 {
+        let equilibrium = outside + heat / self.heat_loss;
+        let decay = (-self.heat_loss * dt / self.heat_capacity).exp();
+        equilibrium + (temperature - equilibrium) * decay
+    }
+}
+
+impl Default for ThermalModel {
+    /// A medium-sized, moderately insulated room: 2 MJ/K and 150 W/K.
+    fn default() -> Self {
+        Self {
+            heat_capacity: 2_000_000.0,
+            heat_loss: 150.0,
+        }
+    }
+}
+
+/// How a socket's power draw affects its room.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThermalEffect {
+    /// Adds `efficiency` × power as heat (1.0 for a resistive heater).
+    Heater {
+        /// Heat output per watt drawn.
+        efficiency: f32,
+    },
+    /// Removes `efficiency` × power as heat (the coefficient of performance).
+    Cooler {
+        /// Heat removed per watt drawn.
+        efficiency: f32,
+    },
+}
+
+impl ThermalEffect {
+    fn heat(self, power: f32) -> f32 {
+        match self {
+            ThermalEffect::Heater { efficiency } => efficiency * power,
+            ThermalEffect::Cooler { efficiency } => -efficiency * power,
+        }
+    }
+}
+
+/// Simulated air temperatures and energy use of a home.
+///
+/// The simulation does not own the home: each step reads the sockets and
+/// writes the thermometers of whatever home it is given.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::simulation::{Simulation, ThermalEffect, ThermalModel};
+/// use smart_home::{room, SmartHome, Socket, Thermometer};
+/// use std::time::Duration;
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("bedroom", room!(
+///     "Bedroom",
+///     "heater" => Socket::new("Heater", 2000.0),
+///     "sensor" => Thermometer::new("Sensor", 15.0),
+/// ))?;
+///
+/// let mut sim = Simulation::try_new(5.0)?;
+/// sim.add_room("bedroom", ThermalModel::default(), 15.0)?;
+/// sim.tag_socket("bedroom", "heater", ThermalEffect::Heater { efficiency: 1.0 });
+///
+/// home.get_socket_mut("bedroom", "heater")?.turn_on();
+/// sim.step(&mut home, Duration::from_secs(3600))?;
+///
+/// assert!(home.get_thermometer("bedroom", "sensor")?.temperature() > 15.0);
+/// assert_eq!(sim.energy_kwh(), 2.0);
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    outside_temperature: f32,
+    rooms: BTreeMap<String, SimulatedRoom>,
+    effects: BTreeMap<(String, String), ThermalEffect>,
+    elapsed: Duration,
+    energy_wh: f64,
+    synced_at: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone)]
+struct SimulatedRoom {
+    model: ThermalModel,
+    temperature: f32,
+}
+
+impl Simulation {
+    /// Creates a simulation with the given outside temperature in Celsius.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] unless the temperature is finite.
+    pub fn try_new(outside_temperature: f32) -> Result<Self, SmartHomeError> {
+        check_temperature("outside temperature", outside_temperature)?;
+        Ok(Self {
+            outside_temperature,
+            rooms: BTreeMap::new(),
+            effects: BTreeMap::new(),
+            elapsed: Duration::ZERO,
+            energy_wh: 0.0,
+            synced_at: None,
+        })
+    }
+
+    /// Simulates the room with key `room`, starting at `temperature`.
+    ///
+    /// Rooms that are not added keep their thermometer readings.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] unless the temperature is finite.
+    pub fn add_room(
+        &mut self,
+        room: impl Into<String>,
+        model: ThermalModel,
+        temperature: f32,
+    ) -> Result<(), SmartHomeError> {
+        check_temperature("room temperature", temperature)?;
+        self.rooms
+            .insert(room.into(), SimulatedRoom { model, temperature });
+        Ok(())
+    }
+
+    /// Tags a socket as a heater or cooler of its room.
+    pub fn tag_socket(
+        &mut self,
+        room: impl Into<String>,
+        device: impl Into<String>,
+        effect: ThermalEffect,
+    ) {
+        self.effects.insert((room.into(), device.into()), effect);
+    }
+
+    /// Returns the outside temperature in Celsius.
+    pub fn outside_temperature(&self) -> f32 {
+        self.outside_temperature
+    }
+
+    /// Changes the outside temperature, e.g. to follow a day/night profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] unless the temperature is
+    /// finite; the previous one is kept.
+    pub fn set_outside_temperature(&mut self, temperature: f32) -> Result<(), SmartHomeError> {
+        check_temperature("outside temperature", temperature)?;
+        self.outside_temperature = temperature;
+        Ok(())
+    }
+
+    /// Returns the simulated air temperature of a room.
+    pub fn room_temperature(&self, room: &str) -> Option<f32> {
+        self.rooms.get(room).map(|r| r.temperature)
+    }
+
+    /// Returns the virtual time simulated so far.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the energy drawn by all switched-on sockets so far, in kWh.
+    pub fn energy_kwh(&self) -> f64 {
+        self.energy_wh / 1000.0
+    }
+
+    /// Advances virtual time by `dt` and updates the thermometers in `home`.
+    ///
+    /// Socket states are taken as constant over the step, so switch them
+    /// between steps.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] if a new reading is outside a
+    /// thermometer's valid range; the remaining thermometers are still updated.
+    pub fn step(&mut self, home: &mut SmartHome, dt: Duration) -> Result<(), SmartHomeError> {
+        let mut result = Ok(());
+        for command in self.advance(home, dt) {
+            if let Err(e) = command.apply(home) {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
+    /// Like [`Simulation::step`], but for a shared home, so subscribers see a
+    /// `temperature_changed` event for every thermometer.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Simulation::step`].
+    pub fn step_shared(&mut self, shared: &SharedHome, dt: Duration) -> Result<(), SmartHomeError> {
+        let commands = shared.read(|home| self.advance(home, dt));
+        let mut result = Ok(());
+        for command in commands {
+            if let Err(e) = shared.execute(command) {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
+    /// Steps by the time `clock` moved since the previous call.
+    ///
+    /// The first call only notes the clock's time.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Simulation::step`].
+    pub fn sync(&mut self, home: &mut SmartHome, clock: &impl Clock) -> Result<(), SmartHomeError> {
+        let dt = self.due(clock);
+        self.step(home, dt)
+    }
+
+    /// Like [`Simulation::sync`], but for a shared home.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Simulation::step`].
+    pub fn sync_shared(
+        &mut self,
+        shared: &SharedHome,
+        clock: &impl Clock,
+    ) -> Result<(), SmartHomeError> {
+        let dt = self.due(clock);
+        self.step_shared(shared, dt)
+    }
+
+    fn due(&mut self, clock: &impl Clock) -> Duration {
+        let now = clock.now();
+        let dt = self.synced_at.map_or(Duration::ZERO, |then| {
+            now.duration_since(then).unwrap_or_default()
+        });
+        self.synced_at = Some(now);
+        dt
+    }
+
+    /// Updates the internal state and returns the thermometer updates to apply.
+    fn advance(&mut self, home: &SmartHome, dt: Duration) -> Vec<Command> {
+        let seconds = dt.as_secs_f32();
+        let power: f32 = home
+            .rooms()
+            .flat_map(|(_, room)| room.devices())
+            .filter_map(|(_, device)| device.as_socket())
+            .map(|socket| socket.power())
+            .sum();
+        self.energy_wh += f64::from(power) * dt.as_secs_f64() / 3600.0;
+        self.elapsed += dt;
+
+        let mut commands = Vec::new();
+        for (room_key, simulated) in &mut self.rooms {
+            let Some(room) = home.get_room(room_key) else {
+                continue;
+            };
+            let heat: f32 = room
+                .devices()
+                .filter_map(|(device_key, device)| {
+                    let effect = self
+                        .effects
+                        .get(&(room_key.clone(), device_key.to_string()))?;
+                    Some(effect.heat(device.as_socket()?.power()))
+                })
+                .sum();
+            simulated.temperature = simulated.model.evolve(
+                simulated.temperature,
+                self.outside_temperature,
+                heat,
+                seconds,
+            );
+            commands.extend(
+                room.devices()
+                    .filter(|(_, device)| matches!(device, SmartDevice::Thermometer(_)))
+                    .map(|(device_key, _)| Command::SetTemperature {
+                        room: room_key.clone(),
+                        device: device_key.to_string(),
+                        temperature: simulated.temperature,
+                    }),
+            );
+        }
+        commands
+    }
+}
+
+fn check_temperature(what: &str, celsius: f32) -> Result<(), SmartHomeError> {
+    if !celsius.is_finite() {
+        return Err(SmartHomeError::InvalidValue(format!(
+            "{} must be a finite number, got {}",
+            what, celsius
+        )));
+    }
+    Ok(())
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Home");
+        home.add_room(
+            "bedroom",
+            room!(
+                "Bedroom",
+                "heater" => Socket::new("Heater", 1000.0),
+                "ac" => Socket::new("AC", 1000.0),
+                "lamp" => Socket::new("Lamp", 100.0),
+                "sensor" => Thermometer::new("Sensor", 20.0),
+            ),
+        )
+        .unwrap();
+        home
+    }
+
+    fn make_sim() -> Simulation {
+        let mut sim = Simulation::try_new(0.0).unwrap();
+        sim.add_room("bedroom", ThermalModel::default(), 20.0)
+            .unwrap();
+        sim.tag_socket(
+            "bedroom",
+            "heater",
+            ThermalEffect::Heater { efficiency: 1.0 },
+        );
+        sim.tag_socket("bedroom", "ac", ThermalEffect::Cooler { efficiency: 3.0 });
+        sim
+    }
+
+    fn reading(home: &SmartHome) -> f32 {
+        home.get_thermometer("bedroom", "sensor")
+            .unwrap()
+            .temperature()
+    }
+
+    #[test]
+    fn test_model_validation() {
+        assert!(ThermalModel::try_new(1.0, 1.0).is_ok());
+        assert!(ThermalModel::try_new(0.0, 1.0).is_err());
+        assert!(ThermalModel::try_new(1.0, f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_temperature_validation() {
+        assert!(Simulation::try_new(f32::NAN).is_err());
+        let mut sim = make_sim();
+        assert!(sim.set_outside_temperature(f32::INFINITY).is_err());
+        assert_eq!(sim.outside_temperature(), 0.0);
+        assert!(sim
+            .add_room("kitchen", ThermalModel::default(), f32::NAN)
+            .is_err());
+        assert_eq!(sim.room_temperature("kitchen"), None);
+    }
+
+    #[test]
+    fn test_room_cools_towards_outside() {
+        let mut home = make_home();
+        let mut sim = make_sim();
+        sim.step(&mut home, Duration::from_secs(600)).unwrap();
+        let after = reading(&home);
+        assert!(after < 20.0 && after > 0.0);
+
+        // Long enough to settle completely.
+        sim.step(&mut home, Duration::from_secs(100 * 3600))
+            .unwrap();
+        assert!(reading(&home).abs() < 0.01);
+        assert_eq!(sim.elapsed(), Duration::from_secs(600 + 100 * 3600));
+    }
+
+    #[test]
+    fn test_heater_equilibrium_and_steps_agree() {
+        let mut home = make_home();
+        home.get_socket_mut("bedroom", "heater").unwrap().turn_on();
+
+        // Equilibrium: 1000 W / (150 W/K) above outside.
+        let mut sim = make_sim();
+        sim.step(&mut home, Duration::from_secs(100 * 3600))
+            .unwrap();
+        assert!((reading(&home) - 1000.0 / 150.0).abs() < 0.01);
+
+        let mut one = make_sim();
+        let mut many = make_sim();
+        one.step(&mut home, Duration::from_secs(3600)).unwrap();
+        for _ in 0..60 {
+            many.step(&mut home, Duration::from_secs(60)).unwrap();
+        }
+        let (a, b) = (
+            one.room_temperature("bedroom").unwrap(),
+            many.room_temperature("bedroom").unwrap(),
+        );
+        assert!((a - b).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_cooler_and_untagged_sockets() {
+        let mut home = make_home();
+        home.get_socket_mut("bedroom", "ac").unwrap().turn_on();
+        home.get_socket_mut("bedroom", "lamp").unwrap().turn_on();
+        let mut sim = make_sim();
+        sim.set_outside_temperature(20.0).unwrap();
+        sim.step(&mut home, Duration::from_secs(3600)).unwrap();
+        assert!(reading(&home) < 20.0);
+        assert!((sim.energy_kwh() - 1.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sync_follows_clock() {
+        let clock = ManualClock::default();
+        let mut home = make_home();
+        let mut sim = make_sim();
+        sim.sync(&mut home, &clock).unwrap();
+        assert_eq!(sim.elapsed(), Duration::ZERO);
+
+        clock.advance(Duration::from_secs(1800));
+        sim.sync(&mut home, &clock).unwrap();
+        clock.advance(Duration::from_secs(1800));
+        sim.sync(&mut home, &clock).unwrap();
+        assert_eq!(sim.elapsed(), Duration::from_secs(3600));
+
+        let mut stepped = make_sim();
+        stepped
+            .step(&mut make_home(), Duration::from_secs(3600))
+            .unwrap();
+        assert!((reading(&home) - stepped.room_temperature("bedroom").unwrap()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_step_shared_publishes_events() {
+        let shared = SharedHome::new(make_home());
+        let events = shared.subscribe();
+        let mut sim = make_sim();
+        sim.step_shared(&shared, Duration::from_secs(60)).unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(crate::HomeEvent::TemperatureChanged { .. })
+        ));
+    }
+}
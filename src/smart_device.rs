@@ -1,11 +1,17 @@
 //! Smart device — enum wrapper around concrete device types.
 
-use crate::devices::{Socket, Thermometer};
+use crate::devices::{Socket, Thermometer, Wireless};
+use crate::metadata::Metadata;
 use crate::report::Report;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// A smart device: either a [`Thermometer`] or a [`Socket`].
-#[derive(Debug, Clone)]
+///
+/// Serialized with a `"kind"` tag (`"thermometer"` or `"socket"`) next to the
+/// device fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
 pub enum SmartDevice {
     /// Thermometer variant.
     Thermometer(Thermometer),
@@ -45,6 +51,47 @@ impl SmartDevice {
             SmartDevice::Socket(_) => None,
         }
     }
+
+    /// Returns the device's tags and attributes.
+    pub fn metadata(&self) -> &Metadata {
+        match self {
+            SmartDevice::Thermometer(t) => t.metadata(),
+            SmartDevice::Socket(s) => s.metadata(),
+        }
+    }
+
+    /// Returns the device's tags and attributes for editing.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        match self {
+            SmartDevice::Thermometer(t) => t.metadata_mut(),
+            SmartDevice::Socket(s) => s.metadata_mut(),
+        }
+    }
+
+    /// Returns the battery and signal state of a wireless device, or `None`
+    /// for devices without a radio.
+    pub fn wireless(&self) -> Option<&Wireless> {
+        match self {
+            SmartDevice::Thermometer(t) => Some(t.wireless()),
+            SmartDevice::Socket(_) => None,
+        }
+    }
+
+    /// Returns the battery and signal state of a wireless device for updating.
+    pub fn wireless_mut(&mut self) -> Option<&mut Wireless> {
+        match self {
+            SmartDevice::Thermometer(t) => Some(t.wireless_mut()),
+            SmartDevice::Socket(_) => None,
+        }
+    }
+
+    /// Returns a short lowercase name of the device kind: `"thermometer"` or `"socket"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SmartDevice::Thermometer(_) => "thermometer",
+            SmartDevice::Socket(_) => "socket",
+        }
+    }
 }
 
 // ── From conversions ──────────────────────────────────────────────────────────
@@ -67,7 +114,12 @@ impl Report for SmartDevice {
     fn report(&self) -> String {
         match self {
             SmartDevice::Thermometer(t) => {
-                format!("Thermometer '{}': {} °C", t.name(), t.temperature())
+                format!(
+                    "Thermometer '{}': {} °C{}",
+                    t.name(),
+                    t.temperature(),
+                    t.wireless().report_suffix()
+                )
             }
             SmartDevice::Socket(s) => {
                 let status = if s.is_on() { "on" } else { "off" };
@@ -110,6 +162,24 @@ mod tests {
         assert!(device.as_socket().unwrap().is_on());
     }
 
+    #[test]
+    fn test_kind() {
+        let device: SmartDevice = Socket::new("Lamp", 60.0).into();
+        assert_eq!(device.kind(), "socket");
+        let device: SmartDevice = Thermometer::new("Sensor", 22.0).into();
+        assert_eq!(device.kind(), "thermometer");
+    }
+
+    #[test]
+    fn test_serde_kind_tag() {
+        let device: SmartDevice = Socket::new("Lamp", 60.0).into();
+        let json = serde_json::to_value(&device).unwrap();
+        assert_eq!(json["kind"], "socket");
+
+        let back: SmartDevice = serde_json::from_value(json).unwrap();
+        assert_eq!(back.as_socket().unwrap().power_consumption(), 60.0);
+    }
+
     #[test]
     fn test_from_socket() {
         let device: SmartDevice = Socket::new("Lamp", 60.0).into();
@@ -130,6 +200,20 @@ mod tests {
         assert!(r.contains("22.5"));
     }
 
+    #[test]
+    fn test_report_low_battery() {
+        let mut thermometer = Thermometer::new("Sensor", 21.0);
+        thermometer.wireless_mut().set_battery(Some(9)).unwrap();
+        thermometer.wireless_mut().set_signal_dbm(Some(-88));
+        let device: SmartDevice = thermometer.into();
+        assert_eq!(
+            device.report(),
+            "Thermometer 'Sensor': 21 °C (battery: 9%, signal: -88 dBm) [LOW BATTERY]"
+        );
+        let socket: SmartDevice = Socket::new("Lamp", 60.0).into();
+        assert!(socket.wireless().is_none());
+    }
+
     #[test]
     fn test_report_socket_off() {
         let device: SmartDevice = Socket::new("Lamp", 60.0).into();
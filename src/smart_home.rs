@@ -1,16 +1,26 @@
 //! Smart home — top-level container for rooms.
 
+use crate::devices::{Socket, Thermometer};
 use crate::error::SmartHomeError;
+use crate::group::{Group, Member};
+use crate::metadata::Query;
 use crate::report::Report;
 use crate::room::Room;
 use crate::smart_device::SmartDevice;
-use std::collections::HashMap;
+use crate::storage::serialize_sorted;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 /// A smart home that holds a named collection of rooms.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SmartHome {
     name: String,
+    #[serde(default, serialize_with = "serialize_sorted")]
     rooms: HashMap<String, Room>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<String, Group>,
 }
 
 impl SmartHome {
@@ -19,6 +29,7 @@ impl SmartHome {
         Self {
             name: name.into(),
             rooms: HashMap::new(),
+            groups: BTreeMap::new(),
         }
     }
 
@@ -32,9 +43,70 @@ impl SmartHome {
         self.rooms.len()
     }
 
+    /// Returns an iterator over `(key, room)` pairs in arbitrary order.
+    pub fn rooms(&self) -> impl Iterator<Item = (&str, &Room)> {
+        self.rooms.iter().map(|(k, r)| (k.as_str(), r))
+    }
+
+    /// Returns the `(key, room)` pairs of rooms matching `query`, sorted by key.
+    pub fn find_rooms(&self, query: &Query) -> Vec<(&str, &Room)> {
+        let mut found: Vec<_> = self
+            .rooms()
+            .filter(|(_, room)| query.matches_room(room.metadata()))
+            .collect();
+        found.sort_by_key(|(key, _)| *key);
+        found
+    }
+
+    /// Returns the `(room key, device key, device)` triples of devices matching
+    /// `query`, sorted by room and device key.
+    pub fn find_devices(&self, query: &Query) -> Vec<(&str, &str, &SmartDevice)> {
+        let mut found: Vec<_> = self
+            .rooms()
+            .flat_map(|(room_key, room)| {
+                room.devices()
+                    .filter(|(_, device)| query.matches_device(device))
+                    .map(move |(key, device)| (room_key, key, device))
+            })
+            .collect();
+        found.sort_by_key(|(room, device, _)| (*room, *device));
+        found
+    }
+
+    /// Returns the names of all floors rooms are placed on, sorted.
+    pub fn floors(&self) -> Vec<&str> {
+        let floors: BTreeSet<&str> = self.rooms.values().filter_map(Room::floor).collect();
+        floors.into_iter().collect()
+    }
+
+    /// Returns the names of all zones rooms belong to, sorted.
+    pub fn zones(&self) -> Vec<&str> {
+        let zones: BTreeSet<&str> = self.rooms.values().flat_map(Room::zones).collect();
+        zones.into_iter().collect()
+    }
+
     /// Adds a room under the given key.
-    pub fn add_room(&mut self, name: impl Into<String>, room: Room) {
-        self.rooms.insert(name.into(), room);
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::DuplicateRoom`] if a room with this key already
+    /// exists; the existing room is left untouched.  Use
+    /// [`SmartHome::insert_or_replace`] to overwrite on purpose.
+    pub fn add_room(&mut self, name: impl Into<String>, room: Room) -> Result<(), SmartHomeError> {
+        match self.rooms.entry(name.into()) {
+            Entry::Occupied(e) => Err(SmartHomeError::DuplicateRoom(e.key().clone())),
+            Entry::Vacant(e) => {
+                e.insert(room);
+                Ok(())
+            }
+        }
+    }
+
+    /// Inserts a room under the given key, replacing any existing one.
+    ///
+    /// Returns the replaced room, if there was one.
+    pub fn insert_or_replace(&mut self, name: impl Into<String>, room: Room) -> Option<Room> {
+        self.rooms.insert(name.into(), room)
     }
 
     /// Removes and returns the room with the given key, or `None` if absent.
@@ -52,6 +124,138 @@ impl SmartHome {
         self.rooms.get_mut(name)
     }
 
+    /// Changes the key of a room, keeping the room and its devices as they are.
+    /// Group memberships of the devices move along.
+    ///
+    /// Renaming a room to its own key does nothing.
+    ///
+    /// # Errors
+    ///
+    /// - [`SmartHomeError::RoomNotFound`] if there is no room `from`.
+    /// - [`SmartHomeError::DuplicateRoom`] if the key `to` is taken by another room.
+    pub fn rename_room(&mut self, from: &str, to: impl Into<String>) -> Result<(), SmartHomeError> {
+        let to = to.into();
+        if !self.rooms.contains_key(from) {
+            return Err(SmartHomeError::RoomNotFound(from.to_string()));
+        }
+        if from != to && self.rooms.contains_key(&to) {
+            return Err(SmartHomeError::DuplicateRoom(to));
+        }
+        if let Some(room) = self.rooms.remove(from) {
+            self.rekey_members(|m| (m.room == from).then(|| Member::new(&to, &m.device)));
+            self.rooms.insert(to, room);
+        }
+        Ok(())
+    }
+
+    /// Changes the key of a device within its room.
+    ///
+    /// Same as [`SmartHome::move_device`] with the same room on both ends.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SmartHome::move_device`].
+    pub fn rename_device(
+        &mut self,
+        room_name: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<(), SmartHomeError> {
+        self.move_device((room_name, from), (room_name, to))
+    }
+
+    /// Moves a device from one `(room, device)` path to another, keeping its
+    /// state and group memberships.  The destination room must exist; moving a
+    /// device onto itself does nothing.
+    ///
+    /// # Errors
+    ///
+    /// - [`SmartHomeError::RoomNotFound`] or [`SmartHomeError::DeviceNotFound`]
+    ///   if the source device does not exist.
+    /// - [`SmartHomeError::RoomNotFound`] if the destination room does not exist.
+    /// - [`SmartHomeError::DuplicateDevice`] if the destination key is taken.
+    ///
+    /// The home is unchanged on error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smart_home::{room, Room, SmartHome, Socket};
+    ///
+    /// let mut home = SmartHome::new("Flat");
+    /// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+    /// home.add_room("study", Room::new("Study"))?;
+    /// home.get_socket_mut("hall", "lamp")?.turn_on();
+    ///
+    /// home.move_device(("hall", "lamp"), ("study", "desk_lamp"))?;
+    /// assert!(home.get_socket("study", "desk_lamp")?.is_on());
+    /// assert!(home.get_device("hall", "lamp").is_err());
+    /// # Ok::<(), smart_home::SmartHomeError>(())
+    /// ```
+    pub fn move_device(
+        &mut self,
+        from: (&str, &str),
+        to: (&str, &str),
+    ) -> Result<(), SmartHomeError> {
+        self.get_device(from.0, from.1)?;
+        let target = self
+            .rooms
+            .get(to.0)
+            .ok_or_else(|| SmartHomeError::RoomNotFound(to.0.to_string()))?;
+        if from == to {
+            return Ok(());
+        }
+        if target.get_device(to.1).is_some() {
+            return Err(SmartHomeError::DuplicateDevice {
+                room: to.0.to_string(),
+                device: to.1.to_string(),
+            });
+        }
+        let device = self.remove_device(from.0, from.1)?;
+        self.rekey_members(|m| {
+            (m.room == from.0 && m.device == from.1).then(|| Member::new(to.0, to.1))
+        });
+        self.add_device(to.0, to.1, device)
+    }
+
+    /// Adds a device to an existing room.
+    ///
+    /// # Errors
+    ///
+    /// - [`SmartHomeError::RoomNotFound`] if `room_name` does not exist.
+    /// - [`SmartHomeError::DuplicateDevice`] if the room already has `device_name`.
+    pub fn add_device(
+        &mut self,
+        room_name: &str,
+        device_name: impl Into<String>,
+        device: impl Into<SmartDevice>,
+    ) -> Result<(), SmartHomeError> {
+        self.room_mut(room_name)?
+            .add_device(device_name, device)
+            .map_err(|e| match e {
+                SmartHomeError::DuplicateDevice { device, .. } => SmartHomeError::DuplicateDevice {
+                    room: room_name.to_string(),
+                    device,
+                },
+                e => e,
+            })
+    }
+
+    /// Removes and returns a device identified by room and device keys.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SmartHome::get_device`].
+    pub fn remove_device(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<SmartDevice, SmartHomeError> {
+        self.room_mut(room_name)?
+            .remove_device(device_name)
+            .ok_or_else(|| device_not_found(room_name, device_name))
+    }
+
     /// Returns a shared reference to a device identified by room and device keys.
     ///
     /// # Errors
@@ -68,14 +272,243 @@ impl SmartHome {
             .get(room_name)
             .ok_or_else(|| SmartHomeError::RoomNotFound(room_name.to_string()))?;
         room.get_device(device_name)
-            .ok_or_else(|| SmartHomeError::DeviceNotFound(device_name.to_string()))
+            .ok_or_else(|| device_not_found(room_name, device_name))
+    }
+
+    /// Returns a mutable reference to a device identified by room and device keys.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SmartHome::get_device`].
+    pub fn get_device_mut(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<&mut SmartDevice, SmartHomeError> {
+        self.room_mut(room_name)?
+            .get_device_mut(device_name)
+            .ok_or_else(|| device_not_found(room_name, device_name))
+    }
+
+    /// Returns a shared reference to the [`Socket`] at the given room and device keys.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SmartHome::get_device`], plus [`SmartHomeError::WrongDeviceKind`]
+    /// if the device is not a socket.
+    pub fn get_socket(
+        &self,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<&Socket, SmartHomeError> {
+        let device = self.get_device(room_name, device_name)?;
+        let found = device.kind();
+        device
+            .as_socket()
+            .ok_or_else(|| wrong_kind(device_name, "socket", found))
+    }
+
+    /// Returns a mutable reference to the [`Socket`] at the given room and device keys.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SmartHome::get_socket`].
+    pub fn get_socket_mut(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<&mut Socket, SmartHomeError> {
+        let device = self.get_device_mut(room_name, device_name)?;
+        let found = device.kind();
+        device
+            .as_socket_mut()
+            .ok_or_else(|| wrong_kind(device_name, "socket", found))
+    }
+
+    /// Returns a shared reference to the [`Thermometer`] at the given room and device keys.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SmartHome::get_device`], plus [`SmartHomeError::WrongDeviceKind`]
+    /// if the device is not a thermometer.
+    pub fn get_thermometer(
+        &self,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<&Thermometer, SmartHomeError> {
+        let device = self.get_device(room_name, device_name)?;
+        let found = device.kind();
+        device
+            .as_thermometer()
+            .ok_or_else(|| wrong_kind(device_name, "thermometer", found))
+    }
+
+    /// Returns a mutable reference to the [`Thermometer`] at the given room and device keys.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SmartHome::get_thermometer`].
+    pub fn get_thermometer_mut(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<&mut Thermometer, SmartHomeError> {
+        let device = self.get_device_mut(room_name, device_name)?;
+        let found = device.kind();
+        device
+            .as_thermometer_mut()
+            .ok_or_else(|| wrong_kind(device_name, "thermometer", found))
+    }
+
+    /// Runs several changes as one: either all of them take effect or none.
+    ///
+    /// `f` works on a copy of the home, which replaces the home only if `f`
+    /// returns `Ok`.  On error the home is left exactly as it was.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error returned by `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smart_home::{room, SmartHome, SmartHomeError, Socket};
+    ///
+    /// let mut home = SmartHome::new("Flat");
+    /// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+    ///
+    /// let result = home.transaction(|h| {
+    ///     h.get_socket_mut("hall", "lamp")?.turn_on();
+    ///     h.get_socket_mut("hall", "missing")?.turn_on();
+    ///     Ok(())
+    /// });
+    /// assert!(matches!(result, Err(SmartHomeError::DeviceNotFound { .. })));
+    /// assert!(!home.get_socket("hall", "lamp")?.is_on());
+    /// # Ok::<(), SmartHomeError>(())
+    /// ```
+    pub fn transaction<T>(
+        &mut self,
+        f: impl FnOnce(&mut SmartHome) -> Result<T, SmartHomeError>,
+    ) -> Result<T, SmartHomeError> {
+        let mut staged = self.clone();
+        let value = f(&mut staged)?;
+        *self = staged;
+        Ok(value)
+    }
+
+    /// Returns an iterator over `(name, group)` pairs sorted by name.
+    pub fn groups(&self) -> impl Iterator<Item = (&str, &Group)> {
+        self.groups.iter().map(|(k, g)| (k.as_str(), g))
+    }
+
+    /// Adds a device group under the given name.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::DuplicateGroup`] if the name is taken.
+    pub fn add_group(
+        &mut self,
+        name: impl Into<String>,
+        group: Group,
+    ) -> Result<(), SmartHomeError> {
+        match self.groups.entry(name.into()) {
+            btree_map::Entry::Occupied(e) => Err(SmartHomeError::DuplicateGroup(e.key().clone())),
+            btree_map::Entry::Vacant(e) => {
+                e.insert(group);
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes and returns the group with the given name, or `None` if absent.
+    ///
+    /// The member devices stay in the home.
+    pub fn remove_group(&mut self, name: &str) -> Option<Group> {
+        self.groups.remove(name)
+    }
+
+    /// Returns the group with the given name, or `None`.
+    pub fn get_group(&self, name: &str) -> Option<&Group> {
+        self.groups.get(name)
+    }
+
+    /// Adds the device at `room_name`/`device_name` to a group; returns `false`
+    /// if it already was a member.
+    ///
+    /// Removing the device later keeps it in the group, where bulk operations
+    /// report it as a failure until it is re-added or taken out of the group.
+    ///
+    /// # Errors
+    ///
+    /// - [`SmartHomeError::GroupNotFound`] if there is no such group.
+    /// - Same as [`SmartHome::get_device`] if the device does not exist.
+    pub fn add_to_group(
+        &mut self,
+        group: &str,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<bool, SmartHomeError> {
+        self.get_device(room_name, device_name)?;
+        Ok(self
+            .group_mut(group)?
+            .insert(Member::new(room_name, device_name)))
+    }
+
+    /// Removes the device at `room_name`/`device_name` from a group; returns
+    /// `false` if it was not a member.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::GroupNotFound`] if there is no such group.
+    pub fn remove_from_group(
+        &mut self,
+        group: &str,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<bool, SmartHomeError> {
+        Ok(self.group_mut(group)?.remove(room_name, device_name))
+    }
+
+    fn group_mut(&mut self, name: &str) -> Result<&mut Group, SmartHomeError> {
+        self.groups
+            .get_mut(name)
+            .ok_or_else(|| SmartHomeError::GroupNotFound(name.to_string()))
+    }
+
+    fn rekey_members(&mut self, mut f: impl FnMut(&Member) -> Option<Member>) {
+        for group in self.groups.values_mut() {
+            group.rekey(&mut f);
+        }
+    }
+
+    fn room_mut(&mut self, room_name: &str) -> Result<&mut Room, SmartHomeError> {
+        self.rooms
+            .get_mut(room_name)
+            .ok_or_else(|| SmartHomeError::RoomNotFound(room_name.to_string()))
+    }
+}
+
+fn device_not_found(room_name: &str, device_name: &str) -> SmartHomeError {
+    SmartHomeError::DeviceNotFound {
+        room: room_name.to_string(),
+        device: device_name.to_string(),
+    }
+}
+
+fn wrong_kind(device_name: &str, expected: &'static str, found: &'static str) -> SmartHomeError {
+    SmartHomeError::WrongDeviceKind {
+        device: device_name.to_string(),
+        expected,
+        found,
     }
 }
 
 // ── Report ────────────────────────────────────────────────────────────────────
 
-impl Report for SmartHome {
-    fn report(&self) -> String {
+impl SmartHome {
+    /// Builds the report with `note(room_key, device_key)` appended to each
+    /// device line.
+    pub(crate) fn report_with(&self, note: impl Fn(&str, &str) -> String) -> String {
         let sep = "=".repeat(50);
         let mut s = format!(
             "\n{}\nSmart Home '{}' ({} room(s)):\n{}\n",
@@ -88,33 +521,46 @@ impl Report for SmartHome {
         keys.sort();
         for key in keys {
             s.push_str(&format!("\n[Room: {}]\n", key));
-            s.push_str(&self.rooms[key].report());
+            s.push_str(&self.rooms[key].report_with(|device| note(key, device)));
         }
         s.push_str(&format!("\n{}\n", sep));
         s
     }
 }
 
+impl Report for SmartHome {
+    fn report(&self) -> String {
+        self.report_with(|_, _| String::new())
+    }
+}
+
 // ── Tests ─────────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::devices::{Socket, Thermometer};
 
     fn make_home() -> SmartHome {
         let mut home = SmartHome::new("Apartment");
 
         let mut living_room = Room::new("Living room");
-        living_room.add_device("sensor", Thermometer::new("Sensor", 20.0));
-        living_room.add_device("lamp", Socket::new("Lamp", 60.0));
+        living_room
+            .add_device("sensor", Thermometer::new("Sensor", 20.0))
+            .unwrap();
+        living_room
+            .add_device("lamp", Socket::new("Lamp", 60.0))
+            .unwrap();
 
         let mut bedroom = Room::new("Bedroom");
-        bedroom.add_device("sensor", Thermometer::new("Sensor", 18.0));
-        bedroom.add_device("heater", Socket::new("Space heater", 2000.0));
+        bedroom
+            .add_device("sensor", Thermometer::new("Sensor", 18.0))
+            .unwrap();
+        bedroom
+            .add_device("heater", Socket::new("Space heater", 2000.0))
+            .unwrap();
 
-        home.add_room("living_room", living_room);
-        home.add_room("bedroom", bedroom);
+        home.add_room("living_room", living_room).unwrap();
+        home.add_room("bedroom", bedroom).unwrap();
         home
     }
 
@@ -128,7 +574,7 @@ mod tests {
     #[test]
     fn test_add_remove_room() {
         let mut home = SmartHome::new("Home");
-        home.add_room("kitchen", Room::new("Kitchen"));
+        home.add_room("kitchen", Room::new("Kitchen")).unwrap();
         assert_eq!(home.room_count(), 1);
 
         assert!(home.remove_room("kitchen").is_some());
@@ -184,8 +630,223 @@ mod tests {
         let home = make_home();
         assert!(matches!(
             home.get_device("living_room", "no_such_device"),
-            Err(SmartHomeError::DeviceNotFound(_))
+            Err(SmartHomeError::DeviceNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_add_duplicate_room_keeps_original() {
+        let mut home = make_home();
+        let err = home.add_room("bedroom", Room::new("Other")).unwrap_err();
+        assert!(matches!(err, SmartHomeError::DuplicateRoom(ref key) if key == "bedroom"));
+        assert_eq!(home.get_room("bedroom").unwrap().device_count(), 2);
+    }
+
+    #[test]
+    fn test_insert_or_replace_room() {
+        let mut home = make_home();
+        let old = home.insert_or_replace("bedroom", Room::new("Other"));
+        assert_eq!(old.unwrap().name(), "Bedroom");
+        assert_eq!(home.get_room("bedroom").unwrap().name(), "Other");
+    }
+
+    #[test]
+    fn test_floors_and_zones() {
+        let mut home = make_home();
+        assert!(home.floors().is_empty());
+        for (key, floor) in [("living_room", "ground"), ("bedroom", "first")] {
+            let room = home.get_room_mut(key).unwrap();
+            room.set_floor(Some(floor.to_string()));
+            room.add_zone("heating");
+        }
+        home.get_room_mut("bedroom").unwrap().add_zone("quiet");
+        assert_eq!(home.floors(), ["first", "ground"]);
+        assert_eq!(home.zones(), ["heating", "quiet"]);
+    }
+
+    #[test]
+    fn test_rename_room() {
+        let mut home = make_home();
+        home.get_socket_mut("bedroom", "heater").unwrap().turn_on();
+        home.rename_room("bedroom", "master_bedroom").unwrap();
+        assert!(home.get_room("bedroom").is_none());
+        assert!(home.get_socket("master_bedroom", "heater").unwrap().is_on());
+
+        home.rename_room("living_room", "living_room").unwrap();
+        assert!(matches!(
+            home.rename_room("living_room", "master_bedroom"),
+            Err(SmartHomeError::DuplicateRoom(ref key)) if key == "master_bedroom"
+        ));
+        assert!(matches!(
+            home.rename_room("attic", "loft"),
+            Err(SmartHomeError::RoomNotFound(_))
+        ));
+        assert_eq!(home.room_count(), 2);
+    }
+
+    #[test]
+    fn test_rename_and_move_device() {
+        let mut home = make_home();
+        home.rename_device("bedroom", "heater", "radiator").unwrap();
+        assert!(home.get_socket("bedroom", "radiator").is_ok());
+        assert!(matches!(
+            home.rename_device("bedroom", "radiator", "sensor"),
+            Err(SmartHomeError::DuplicateDevice { .. })
+        ));
+
+        home.move_device(("bedroom", "radiator"), ("living_room", "radiator"))
+            .unwrap();
+        assert_eq!(home.get_room("bedroom").unwrap().device_count(), 1);
+        assert!(home.get_socket("living_room", "radiator").is_ok());
+
+        assert!(matches!(
+            home.move_device(("bedroom", "sensor"), ("living_room", "sensor")),
+            Err(SmartHomeError::DuplicateDevice { .. })
+        ));
+        assert!(matches!(
+            home.move_device(("bedroom", "sensor"), ("attic", "sensor")),
+            Err(SmartHomeError::RoomNotFound(ref key)) if key == "attic"
+        ));
+        assert!(matches!(
+            home.move_device(("bedroom", "lamp"), ("attic", "lamp")),
+            Err(SmartHomeError::DeviceNotFound { .. })
+        ));
+        assert!(home.get_thermometer("bedroom", "sensor").is_ok());
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut home = make_home();
+        home.add_group("heating", Group::new()).unwrap();
+        assert!(matches!(
+            home.add_group("heating", Group::new()),
+            Err(SmartHomeError::DuplicateGroup(_))
+        ));
+        assert!(home.add_to_group("heating", "bedroom", "heater").unwrap());
+        assert!(!home.add_to_group("heating", "bedroom", "heater").unwrap());
+        assert!(matches!(
+            home.add_to_group("heating", "bedroom", "fan"),
+            Err(SmartHomeError::DeviceNotFound { .. })
+        ));
+        assert!(matches!(
+            home.add_to_group("lights", "bedroom", "heater"),
+            Err(SmartHomeError::GroupNotFound(_))
         ));
+
+        home.rename_room("bedroom", "guest_room").unwrap();
+        home.rename_device("guest_room", "heater", "radiator")
+            .unwrap();
+        let group = home.get_group("heating").unwrap();
+        assert!(group.contains("guest_room", "radiator"));
+        assert_eq!(group.len(), 1);
+
+        assert!(home
+            .remove_from_group("heating", "guest_room", "radiator")
+            .unwrap());
+        assert!(home.remove_group("heating").unwrap().is_empty());
+        assert_eq!(home.groups().count(), 0);
+    }
+
+    #[test]
+    fn test_add_device_to_room() {
+        let mut home = make_home();
+        home.add_device("bedroom", "lamp", Socket::new("Lamp", 40.0))
+            .unwrap();
+        assert!(matches!(
+            home.add_device("bedroom", "lamp", Socket::new("Lamp", 40.0)),
+            Err(SmartHomeError::DuplicateDevice { .. })
+        ));
+        assert!(matches!(
+            home.add_device("attic", "lamp", Socket::new("Lamp", 40.0)),
+            Err(SmartHomeError::RoomNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_device() {
+        let mut home = make_home();
+        assert!(home.remove_device("bedroom", "heater").is_ok());
+        assert!(matches!(
+            home.remove_device("bedroom", "heater"),
+            Err(SmartHomeError::DeviceNotFound { .. })
+        ));
+        assert!(matches!(
+            home.remove_device("attic", "heater"),
+            Err(SmartHomeError::RoomNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_device_not_found_includes_room() {
+        let home = make_home();
+        let err = home.get_device("bedroom", "lamp").unwrap_err();
+        assert!(
+            matches!(err, SmartHomeError::DeviceNotFound { ref room, ref device }
+                if room == "bedroom" && device == "lamp")
+        );
+        assert!(err.to_string().contains("bedroom"));
+    }
+
+    #[test]
+    fn test_get_socket_mut_and_wrong_kind() {
+        let mut home = make_home();
+        home.get_socket_mut("bedroom", "heater").unwrap().turn_on();
+        assert!(home.get_socket("bedroom", "heater").unwrap().is_on());
+
+        assert!(matches!(
+            home.get_socket_mut("bedroom", "sensor"),
+            Err(SmartHomeError::WrongDeviceKind {
+                expected: "socket",
+                found: "thermometer",
+                ..
+            })
+        ));
+        assert!(matches!(
+            home.get_thermometer("bedroom", "heater"),
+            Err(SmartHomeError::WrongDeviceKind {
+                expected: "thermometer",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_get_thermometer_mut() {
+        let mut home = make_home();
+        home.get_thermometer_mut("bedroom", "sensor")
+            .unwrap()
+            .set_temperature(21.0);
+        assert_eq!(
+            home.get_thermometer("bedroom", "sensor")
+                .unwrap()
+                .temperature(),
+            21.0
+        );
+    }
+
+    #[test]
+    fn test_transaction_commits_or_rolls_back() {
+        let mut home = make_home();
+        let err = home
+            .transaction(|h| {
+                h.get_socket_mut("bedroom", "heater")?.turn_on();
+                h.remove_room("living_room");
+                h.add_room("bedroom", Room::new("Duplicate"))
+            })
+            .unwrap_err();
+        assert!(matches!(err, SmartHomeError::DuplicateRoom(_)));
+        assert!(!home.get_socket("bedroom", "heater").unwrap().is_on());
+        assert_eq!(home.room_count(), 2);
+
+        let removed = home
+            .transaction(|h| {
+                h.get_socket_mut("bedroom", "heater")?.turn_on();
+                Ok(h.remove_room("living_room").is_some())
+            })
+            .unwrap();
+        assert!(removed);
+        assert!(home.get_socket("bedroom", "heater").unwrap().is_on());
+        assert_eq!(home.room_count(), 1);
     }
 
     #[test]
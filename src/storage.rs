@@ -0,0 +1,138 @@
+//! Loading and saving a [`SmartHome`] as a JSON "home file".
+//!
+//! Devices are deserialized through their validating constructors
+//! ([`Socket::try_new`][crate::Socket::try_new],
+//! [`Thermometer::try_new`][crate::Thermometer::try_new]), so a file with NaN or
+//! out-of-range values is rejected instead of being loaded silently.
+
+use crate::error::SmartHomeError;
+use crate::smart_home::SmartHome;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Serializes a home to pretty-printed JSON.
+///
+/// Rooms and devices are written in key order, so saving the same home twice
+/// produces identical files.
+pub fn to_json(home: &SmartHome) -> String {
+    // Serializing plain data with string keys cannot fail.
+    serde_json::to_string_pretty(home).expect("home is always serializable")
+}
+
+/// Parses a home from JSON.
+///
+/// # Errors
+///
+/// Returns [`SmartHomeError::Io`] with [`std::io::ErrorKind::InvalidData`] if the text
+/// is not a valid home description (including invalid device values).
+pub fn from_json(json: &str) -> Result<SmartHome, SmartHomeError> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Reads a home from the JSON file at `path`.
+///
+/// # Errors
+///
+/// Returns [`SmartHomeError::Io`] if the file cannot be read or parsed.
+///
+/// # Examples
+///
+/// ```no_run
+/// let home = smart_home::storage::load("home.json")?;
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+pub fn load(path: impl AsRef<Path>) -> Result<SmartHome, SmartHomeError> {
+    from_json(&fs::read_to_string(path)?)
+}
+
+/// Writes a home to `path` as JSON, replacing the file if it exists.
+///
+/// # Errors
+///
+/// Returns [`SmartHomeError::Io`] if the file cannot be written.
+pub fn save(home: &SmartHome, path: impl AsRef<Path>) -> Result<(), SmartHomeError> {
+    fs::write(path, to_json(home) + "\n").map_err(SmartHomeError::from)
+}
+
+/// Serializes a `HashMap` with its entries sorted by key.
+pub(crate) fn serialize_sorted<V, S>(map: &HashMap<String, V>, s: S) -> Result<S::Ok, S::Error>
+where
+    V: Serialize,
+    S: Serializer,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(s)
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, Report, Socket, Thermometer};
+    use std::io;
+
+    fn make_home() -> SmartHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "kitchen",
+            room!(
+                "Kitchen",
+                "kettle" => Socket::new("Kettle", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 23.0),
+            ),
+        )
+        .unwrap();
+        home.get_socket_mut("kitchen", "kettle").unwrap().turn_on();
+        home
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let home = make_home();
+        let back = from_json(&to_json(&home)).unwrap();
+        assert_eq!(back.report(), home.report());
+        assert!(back.get_socket("kitchen", "kettle").unwrap().is_on());
+    }
+
+    #[test]
+    fn test_json_is_stable() {
+        assert_eq!(to_json(&make_home()), to_json(&make_home()));
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let json = r#"{
+            "name": "Flat",
+            "rooms": {
+                "kitchen": {
+                    "name": "Kitchen",
+                    "devices": {
+                        "sensor": { "kind": "thermometer", "name": "Sensor", "temperature": -500.0 }
+                    }
+                }
+            }
+        }"#;
+        let err = from_json(json).unwrap_err();
+        assert!(matches!(err, SmartHomeError::Io(ref e) if e.kind() == io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_save_and_load_file() {
+        let path = std::env::temp_dir().join(format!("smart_home_{}.json", std::process::id()));
+        save(&make_home(), &path).unwrap();
+        let home = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(home.name(), "Flat");
+        assert_eq!(home.room_count(), 1);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        assert!(matches!(
+            load("/nonexistent/home.json"),
+            Err(SmartHomeError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,464 @@
+//! Recording and replaying traces of home changes.
+//!
+//! A [`Recorder`] writes every change announced by a [`SharedHome`] to a trace
+//! in JSON Lines: a header with the starting state, then one line per event
+//! with its time (milliseconds since the Unix epoch) and device path:
+//!
+//! ```text
+//! {"trace": 1, "at_ms": 1760756400000, "home": {...}}
+//! {"at_ms": 1760756410250, "path": "bedroom/heater", "event": "socket_switched", "room": "bedroom", "device": "heater", "on": true, "power": 2000.0}
+//! ```
+//!
+//! A [`Trace`] read back from that file can rebuild the home as it was at any
+//! moment, or drive another home through the same changes.
+
+use crate::clock::{Clock, SystemClock};
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::shared::SharedHome;
+use crate::smart_home::SmartHome;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Version written to and expected in trace headers.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    trace: u32,
+    at_ms: u64,
+    home: SmartHome,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    at_ms: u64,
+    path: String,
+    #[serde(flatten)]
+    event: HomeEvent,
+}
+
+/// One recorded change.
+#[derive(Debug, Clone)]
+pub struct TraceEntry {
+    /// When the change was recorded.
+    pub at: SystemTime,
+    /// `"room/device"`, `"room"` or `"group:<name>"`; see [`HomeEvent::path`].
+    pub path: String,
+    /// What changed.
+    pub event: HomeEvent,
+}
+
+// ── Recorder ──────────────────────────────────────────────────────────────────
+
+/// Writes the changes of a [`SharedHome`] to a trace.
+///
+/// Events are timestamped when the recorder picks them up, so call
+/// [`Recorder::poll`] right after changes or keep a thread in
+/// [`Recorder::wait`] for accurate times.
+pub struct Recorder<W, C = SystemClock> {
+    out: W,
+    clock: C,
+    events: Receiver<HomeEvent>,
+}
+
+impl<W: Write, C: Clock> Recorder<W, C> {
+    /// Writes the trace header with the current state of `shared` and starts
+    /// listening for changes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if writing fails.
+    pub fn start(shared: &SharedHome, clock: C, mut out: W) -> Result<Self, SmartHomeError> {
+        let (home, events) = shared.snapshot_and_subscribe();
+        let header = Header {
+            trace: FORMAT_VERSION,
+            at_ms: unix_millis(clock.now()),
+            home,
+        };
+        serde_json::to_writer(&mut out, &header)?;
+        out.write_all(b"\n")?;
+        out.flush()?;
+        Ok(Self { out, clock, events })
+    }
+
+    /// Records all changes made so far and returns how many there were.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if writing fails.
+    pub fn poll(&mut self) -> Result<usize, SmartHomeError> {
+        let count = self.drain()?;
+        if count > 0 {
+            self.out.flush()?;
+        }
+        Ok(count)
+    }
+
+    /// Waits up to `timeout` for a change, then records it and any others
+    /// made so far.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if writing fails.
+    pub fn wait(&mut self, timeout: Duration) -> Result<usize, SmartHomeError> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => {
+                self.write(event)?;
+                let count = 1 + self.drain()?;
+                self.out.flush()?;
+                Ok(count)
+            }
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => Ok(0),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes the changes made so far without flushing.
+    fn drain(&mut self) -> Result<usize, SmartHomeError> {
+        let mut count = 0;
+        while let Ok(event) = self.events.try_recv() {
+            self.write(event)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn write(&mut self, event: HomeEvent) -> Result<(), SmartHomeError> {
+        let record = Record {
+            at_ms: unix_millis(self.clock.now()),
+            path: event.path(),
+            event,
+        };
+        serde_json::to_writer(&mut self.out, &record)?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+// ── Trace ─────────────────────────────────────────────────────────────────────
+
+/// A trace read back from a file.
+///
+/// # Examples
+///
+/// ```
+/// use smart_home::trace::{Recorder, Trace};
+/// use smart_home::{room, Command, ManualClock, SharedHome, SmartHome, Socket};
+/// use std::time::Duration;
+///
+/// let mut home = SmartHome::new("Flat");
+/// home.add_room("hall", room!("Hall", "lamp" => Socket::new("Lamp", 60.0)))?;
+/// let shared = SharedHome::new(home);
+/// let clock = ManualClock::default();
+///
+/// let mut recorder = Recorder::start(&shared, clock.clone(), Vec::new())?;
+/// clock.advance(Duration::from_secs(60));
+/// shared.execute(Command::TurnOn { room: "hall".into(), device: "lamp".into() })?;
+/// recorder.poll()?;
+///
+/// let trace = Trace::read(recorder.into_inner().as_slice())?;
+/// assert_eq!(trace.entries[0].path, "hall/lamp");
+/// let before = trace.state_at(trace.started_at + Duration::from_secs(30))?;
+/// assert!(!before.get_socket("hall", "lamp")?.is_on());
+/// # Ok::<(), smart_home::SmartHomeError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Trace {
+    /// When recording started.
+    pub started_at: SystemTime,
+    /// The home when recording started.
+    pub initial: SmartHome,
+    /// Changes in the order they were made.
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// Reads a trace in the format written by [`Recorder`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::InvalidValue`] naming the line that is not
+    /// valid, or [`SmartHomeError::Io`] if reading fails.
+    pub fn read(reader: impl BufRead) -> Result<Self, SmartHomeError> {
+        let mut lines = reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !matches!(line, Ok(l) if l.trim().is_empty()));
+        let (index, first) = lines
+            .next()
+            .ok_or_else(|| SmartHomeError::InvalidValue("empty trace".to_string()))?;
+        let header: Header = parse_line(index + 1, &first?)?;
+        if header.trace != FORMAT_VERSION {
+            return Err(SmartHomeError::InvalidValue(format!(
+                "unsupported trace version {}",
+                header.trace
+            )));
+        }
+        let mut entries = Vec::new();
+        for (index, line) in lines {
+            let record: Record = parse_line(index + 1, &line?)?;
+            entries.push(TraceEntry {
+                at: from_unix_millis(record.at_ms),
+                path: record.path,
+                event: record.event,
+            });
+        }
+        Ok(Self {
+            started_at: from_unix_millis(header.at_ms),
+            initial: header.home,
+            entries,
+        })
+    }
+
+    /// Reads a trace from the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Trace::read`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SmartHomeError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Rebuilds the home with the first `count` changes applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of a change that does not apply, which means the trace
+    /// was edited or mixed up with another one.
+    pub fn state_after(&self, count: usize) -> Result<SmartHome, SmartHomeError> {
+        let mut home = self.initial.clone();
+        for entry in self.entries.iter().take(count) {
+            Command::from(entry.event.clone()).apply(&mut home)?;
+        }
+        Ok(home)
+    }
+
+    /// Rebuilds the home as it was at `time`, including changes made exactly then.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Trace::state_after`].
+    pub fn state_at(&self, time: SystemTime) -> Result<SmartHome, SmartHomeError> {
+        let count = self.entries.iter().take_while(|e| e.at <= time).count();
+        self.state_after(count)
+    }
+
+    /// Applies every recorded change to `shared` in order, announcing each one.
+    ///
+    /// `shared` would usually hold a copy of [`Trace::initial`].
+    ///
+    /// # Errors
+    ///
+    /// Stops at and returns the first change that does not apply.
+    pub fn replay(&self, shared: &SharedHome) -> Result<(), SmartHomeError> {
+        for entry in &self.entries {
+            shared.execute(entry.event.clone().into())?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_line<T: for<'de> Deserialize<'de>>(
+    number: usize,
+    line: &str,
+) -> Result<T, SmartHomeError> {
+    serde_json::from_str(line)
+        .map_err(|e| SmartHomeError::InvalidValue(format!("trace line {}: {}", number, e)))
+}
+
+pub(crate) fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+pub(crate) fn from_unix_millis(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+/// Formats `time` as an ISO 8601 UTC timestamp with milliseconds,
+/// e.g. `2025-10-18T03:00:00.000Z`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let millis = unix_millis(time);
+    let (days, ms_of_day) = ((millis / 86_400_000) as i64, millis % 86_400_000);
+    // Civil date from days since the epoch (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        ms_of_day / 3_600_000,
+        ms_of_day / 60_000 % 60,
+        ms_of_day / 1000 % 60,
+        ms_of_day % 1000
+    )
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_shared() -> SharedHome {
+        let mut home = SmartHome::new("Home");
+        home.add_room(
+            "bedroom",
+            room!(
+                "Bedroom",
+                "heater" => Socket::new("Heater", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 18.0),
+            ),
+        )
+        .unwrap();
+        SharedHome::new(home)
+    }
+
+    fn heater(on: bool) -> Command {
+        let (room, device) = ("bedroom".to_string(), "heater".to_string());
+        if on {
+            Command::TurnOn { room, device }
+        } else {
+            Command::TurnOff { room, device }
+        }
+    }
+
+    /// Records: heater on at +1h, 21 °C at +2h, heater off at +3h.
+    fn record() -> Vec<u8> {
+        let shared = make_shared();
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_000_000));
+        let mut recorder = Recorder::start(&shared, clock.clone(), Vec::new()).unwrap();
+        let hour = Duration::from_secs(3600);
+        clock.advance(hour);
+        shared.execute(heater(true)).unwrap();
+        recorder.poll().unwrap();
+        clock.advance(hour);
+        shared
+            .execute(Command::SetTemperature {
+                room: "bedroom".into(),
+                device: "sensor".into(),
+                temperature: 21.0,
+            })
+            .unwrap();
+        recorder.poll().unwrap();
+        clock.advance(hour);
+        shared.execute(heater(false)).unwrap();
+        assert_eq!(recorder.wait(Duration::ZERO).unwrap(), 1);
+        recorder.into_inner()
+    }
+
+    #[test]
+    fn test_state_at_any_time() {
+        let trace = Trace::read(record().as_slice()).unwrap();
+        assert_eq!(trace.entries.len(), 3);
+        assert_eq!(trace.entries[0].path, "bedroom/heater");
+
+        let hour = Duration::from_secs(3600);
+        let at = |h: u32| trace.state_at(trace.started_at + hour * h).unwrap();
+        let heater_on = |home: &SmartHome| home.get_socket("bedroom", "heater").unwrap().is_on();
+        assert!(!heater_on(&at(0)));
+        assert!(heater_on(&at(1)));
+        assert_eq!(
+            at(2)
+                .get_thermometer("bedroom", "sensor")
+                .unwrap()
+                .temperature(),
+            21.0
+        );
+        assert!(!heater_on(&at(3)));
+    }
+
+    #[test]
+    fn test_replay_into_fresh_home() {
+        let trace = Trace::read(record().as_slice()).unwrap();
+        let fresh = SharedHome::new(trace.initial.clone());
+        let events = fresh.subscribe();
+        trace.replay(&fresh).unwrap();
+        assert_eq!(events.try_iter().count(), 3);
+        assert_eq!(
+            fresh.read(|h| h
+                .get_thermometer("bedroom", "sensor")
+                .unwrap()
+                .temperature()),
+            21.0
+        );
+    }
+
+    #[test]
+    fn test_records_writes_and_replacements() {
+        let shared = make_shared();
+        let mut recorder = Recorder::start(&shared, SystemClock, Vec::new()).unwrap();
+        shared.write(|h| Ok(h.remove_room("bedroom"))).unwrap();
+        shared.replace(SmartHome::new("Other")).unwrap();
+        assert_eq!(recorder.poll().unwrap(), 2);
+
+        let trace = Trace::read(recorder.into_inner().as_slice()).unwrap();
+        assert_eq!(trace.entries[0].path, "home");
+        let end = trace.state_at(SystemTime::now()).unwrap();
+        assert_eq!(end.name(), "Other");
+    }
+
+    /// A writer whose contents stay readable while a recorder owns it.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_wait_flushes_a_single_change() {
+        let shared = make_shared();
+        let buffer = SharedBuffer::default();
+        let out = std::io::BufWriter::new(buffer.clone());
+        let mut recorder = Recorder::start(&shared, ManualClock::default(), out).unwrap();
+        shared.execute(heater(true)).unwrap();
+        assert_eq!(recorder.wait(Duration::ZERO).unwrap(), 1);
+
+        let data = buffer.0.lock().unwrap().clone();
+        let trace = Trace::read(data.as_slice()).unwrap();
+        assert_eq!(trace.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_read_errors_name_the_line() {
+        let mut data = record();
+        data.extend_from_slice(b"{\"at_ms\": 1}\n");
+        let err = Trace::read(data.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("trace line 5"), "{}", err);
+        assert!(Trace::read(&b""[..]).is_err());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let t = UNIX_EPOCH + Duration::from_millis(1_709_175_845_123);
+        assert_eq!(format_timestamp(t), "2024-02-29T03:04:05.123Z");
+    }
+}
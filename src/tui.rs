@@ -0,0 +1,401 @@
+//! Terminal dashboard showing live home status.
+//!
+//! Available with the `tui` feature.  Rooms are drawn as panels listing their
+//! devices; the selected socket can be toggled from the keyboard.  The dashboard
+//! reads from a [`SharedHome`], so changes made by other threads (a file watcher,
+//! a server, a poller fetching a remote home, ...) show up on the next refresh.
+
+use crate::command::Command;
+use crate::event::HomeEvent;
+use crate::shared::SharedHome;
+use crate::smart_device::SmartDevice;
+use crate::smart_home::SmartHome;
+use ratatui::backend::Backend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use std::io;
+use std::time::Duration;
+
+/// Maximum number of room panels placed side by side.
+const MAX_COLUMNS: usize = 3;
+
+/// Result of handling a key press.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Nothing to do besides redrawing.
+    None,
+    /// A socket was switched; the home changed.
+    Toggled {
+        /// Room key.
+        room: String,
+        /// Device key.
+        device: String,
+        /// New state.
+        on: bool,
+    },
+    /// The user asked to leave.
+    Quit,
+}
+
+/// Selection and status line of the dashboard.
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    selected: usize,
+    status: String,
+}
+
+impl Dashboard {
+    /// Creates a dashboard with the first device selected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the `(room, device)` keys of the selected device, if any.
+    pub fn selected(&self, home: &SmartHome) -> Option<(String, String)> {
+        let paths = device_paths(home);
+        paths
+            .get(self.selected.min(paths.len().saturating_sub(1)))
+            .cloned()
+    }
+
+    /// Handles one key press.
+    ///
+    /// `Up`/`k` and `Down`/`j` move the selection, `Enter`/`Space`/`t` toggle the
+    /// selected socket and `q`/`Esc` quit.
+    pub fn handle_key(&mut self, code: KeyCode, shared: &SharedHome) -> Action {
+        let count = shared.read(|h| device_paths(h).len());
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.min(count).saturating_sub(1);
+                Action::None
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.selected + 1 < count {
+                    self.selected += 1;
+                }
+                Action::None
+            }
+            KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Char('t') => self.toggle(shared),
+            _ => Action::None,
+        }
+    }
+
+    fn toggle(&mut self, shared: &SharedHome) -> Action {
+        let Some((room, device)) = shared.read(|home| self.selected(home)) else {
+            self.status = "no device selected".to_string();
+            return Action::None;
+        };
+        match shared.execute(Command::Toggle { room, device }) {
+            Ok(HomeEvent::SocketSwitched {
+                room, device, on, ..
+            }) => {
+                let state = if on { "on" } else { "off" };
+                self.status = format!("{}/{} switched {}", room, device, state);
+                Action::Toggled { room, device, on }
+            }
+            Ok(event) => {
+                self.status = format!("{} changed", event.path());
+                Action::None
+            }
+            Err(e) => {
+                self.status = e.to_string();
+                Action::None
+            }
+        }
+    }
+
+    /// Sets the message shown in the footer.
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = status.into();
+    }
+
+    /// Draws the whole dashboard for `home`.
+    pub fn render(&self, frame: &mut Frame, home: &SmartHome) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(2),
+        ])
+        .areas(frame.area());
+
+        let total_power: f32 = home
+            .rooms()
+            .flat_map(|(_, room)| room.devices())
+            .filter_map(|(_, device)| device.as_socket())
+            .map(|socket| socket.power())
+            .sum();
+        frame.render_widget(
+            Paragraph::new(format!(
+                "{} room(s), total power {} W",
+                home.room_count(),
+                total_power
+            ))
+            .block(Block::bordered().title(format!(" {} ", home.name()))),
+            header,
+        );
+
+        self.render_rooms(frame, body, home);
+
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(self.status.as_str()),
+                Line::from("↑/↓ select · Enter toggle socket · q quit").style(dim()),
+            ]),
+            footer,
+        );
+    }
+
+    fn render_rooms(&self, frame: &mut Frame, area: Rect, home: &SmartHome) {
+        let mut rooms: Vec<_> = home.rooms().collect();
+        rooms.sort_by_key(|(key, _)| *key);
+        if rooms.is_empty() {
+            frame.render_widget(Paragraph::new("No rooms").style(dim()), area);
+            return;
+        }
+
+        let selected = self.selected(home);
+        let columns = rooms.len().min(MAX_COLUMNS);
+        let rows = rooms.len().div_ceil(columns);
+        let row_areas = Layout::vertical(vec![Constraint::Ratio(1, rows as u32); rows]).split(area);
+
+        for (i, (key, room)) in rooms.into_iter().enumerate() {
+            let col_areas = Layout::horizontal(vec![Constraint::Ratio(1, columns as u32); columns])
+                .split(row_areas[i / columns]);
+
+            let mut devices: Vec<_> = room.devices().collect();
+            devices.sort_by_key(|(k, _)| *k);
+            let lines: Vec<Line> = devices
+                .into_iter()
+                .map(|(device_key, device)| {
+                    let is_selected = selected
+                        .as_ref()
+                        .is_some_and(|(r, d)| r == key && d == device_key);
+                    device_line(device_key, device, is_selected)
+                })
+                .collect();
+
+            let title = format!(" {} [{}] ", room.name(), key);
+            frame.render_widget(
+                Paragraph::new(lines)
+                    .block(Block::bordered().title(title))
+                    .wrap(Wrap { trim: true }),
+                col_areas[i % columns],
+            );
+        }
+    }
+}
+
+fn device_line<'a>(key: &'a str, device: &SmartDevice, selected: bool) -> Line<'a> {
+    let marker = if selected { "▶ " } else { "  " };
+    let state = match device {
+        SmartDevice::Socket(s) if s.is_on() => Span::styled(
+            format!("ON  {} W", s.power()),
+            Style::default().fg(Color::Green),
+        ),
+        SmartDevice::Socket(_) => Span::styled("off", dim()),
+        SmartDevice::Thermometer(t) => Span::styled(
+            format!("{:.1} °C", t.temperature()),
+            Style::default().fg(Color::Cyan),
+        ),
+    };
+    let name = match device {
+        SmartDevice::Socket(s) => s.name(),
+        SmartDevice::Thermometer(t) => t.name(),
+    };
+    let line = Line::from(vec![
+        Span::raw(marker),
+        Span::styled(key, Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(format!(" {}  ", name)),
+        state,
+    ]);
+    if selected {
+        line.style(Style::default().add_modifier(Modifier::REVERSED))
+    } else {
+        line
+    }
+}
+
+fn dim() -> Style {
+    Style::default().fg(Color::DarkGray)
+}
+
+/// Returns `(room, device)` keys of all devices in display order.
+fn device_paths(home: &SmartHome) -> Vec<(String, String)> {
+    let mut paths: Vec<_> = home
+        .rooms()
+        .flat_map(|(room_key, room)| {
+            room.devices()
+                .map(move |(device_key, _)| (room_key.to_string(), device_key.to_string()))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Runs the dashboard until the user quits.
+///
+/// The screen is redrawn from `shared` at least every `refresh`.  `on_toggle`
+/// is called after each socket switch with a snapshot of the updated home and
+/// the command that repeats the switch, e.g. to persist the home or to send
+/// the command on to a remote one.  No lock on `shared` is held during the
+/// call.
+///
+/// # Errors
+///
+/// Returns any I/O error from the terminal.
+pub fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    shared: &SharedHome,
+    refresh: Duration,
+    mut on_toggle: impl FnMut(&SmartHome, &Command) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut dashboard = Dashboard::new();
+    loop {
+        let home = shared.snapshot();
+        terminal.draw(|frame| dashboard.render(frame, &home))?;
+
+        if !event::poll(refresh)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match dashboard.handle_key(key.code, shared) {
+            Action::Quit => return Ok(()),
+            Action::Toggled { room, device, on } => {
+                let command = if on {
+                    Command::TurnOn { room, device }
+                } else {
+                    Command::TurnOff { room, device }
+                };
+                if let Err(e) = on_toggle(&shared.snapshot(), &command) {
+                    dashboard.set_status(format!("error: {}", e));
+                }
+            }
+            Action::None => {}
+        }
+    }
+}
+
+/// Renders the dashboard once into a plain-text string, one line per terminal row.
+///
+/// Useful for logging and tests; `width`×`height` is the virtual screen size.
+pub fn render_to_string(home: &SmartHome, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(width, height))
+        .expect("test backend never fails");
+    let dashboard = Dashboard::new();
+    terminal
+        .draw(|frame| dashboard.render(frame, home))
+        .expect("test backend never fails");
+    let buffer = terminal.backend().buffer();
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{room, Socket, Thermometer};
+
+    fn make_shared() -> SharedHome {
+        let mut home = SmartHome::new("Flat");
+        home.add_room(
+            "bedroom",
+            room!(
+                "Bedroom",
+                "heater" => Socket::new("Heater", 2000.0),
+                "sensor" => Thermometer::new("Sensor", 19.5),
+            ),
+        )
+        .unwrap();
+        home.add_room(
+            "kitchen",
+            room!("Kitchen", "kettle" => Socket::new("Kettle", 1500.0)),
+        )
+        .unwrap();
+        SharedHome::new(home)
+    }
+
+    #[test]
+    fn test_render_shows_rooms_and_devices() {
+        let shared = make_shared();
+        let screen = render_to_string(&shared.snapshot(), 100, 12);
+        assert!(screen.contains("Flat"));
+        assert!(screen.contains("Bedroom [bedroom]"));
+        assert!(screen.contains("Kitchen [kitchen]"));
+        assert!(screen.contains("19.5 °C"));
+        assert!(screen.contains("total power 0 W"));
+    }
+
+    #[test]
+    fn test_navigation_and_toggle() {
+        let shared = make_shared();
+        let mut dashboard = Dashboard::new();
+        assert_eq!(
+            dashboard.selected(&shared.snapshot()),
+            Some(("bedroom".into(), "heater".into()))
+        );
+
+        assert_eq!(
+            dashboard.handle_key(KeyCode::Enter, &shared),
+            Action::Toggled {
+                room: "bedroom".into(),
+                device: "heater".into(),
+                on: true,
+            }
+        );
+        assert!(shared.read(|h| h.get_socket("bedroom", "heater").unwrap().is_on()));
+
+        dashboard.handle_key(KeyCode::Down, &shared);
+        dashboard.handle_key(KeyCode::Down, &shared);
+        dashboard.handle_key(KeyCode::Down, &shared);
+        assert_eq!(
+            dashboard.selected(&shared.snapshot()),
+            Some(("kitchen".into(), "kettle".into()))
+        );
+        dashboard.handle_key(KeyCode::Up, &shared);
+        assert_eq!(
+            dashboard.selected(&shared.snapshot()),
+            Some(("bedroom".into(), "sensor".into()))
+        );
+
+        // Thermometers cannot be toggled.
+        assert_eq!(dashboard.handle_key(KeyCode::Enter, &shared), Action::None);
+        assert!(dashboard.status.contains("thermometer"));
+        assert_eq!(
+            dashboard.handle_key(KeyCode::Char('q'), &shared),
+            Action::Quit
+        );
+    }
+
+    #[test]
+    fn test_selection_survives_removed_devices() {
+        let shared = make_shared();
+        let mut dashboard = Dashboard::new();
+        dashboard.handle_key(KeyCode::Down, &shared);
+        dashboard.handle_key(KeyCode::Down, &shared);
+        shared.write(|h| Ok(h.remove_room("kitchen"))).unwrap();
+        assert_eq!(
+            dashboard.selected(&shared.snapshot()),
+            Some(("bedroom".into(), "sensor".into()))
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! WebSocket push channel for live state updates.
+//!
+//! Available with the `ws` feature.  Every connection first receives a
+//! snapshot of the whole home, then one message per change made through
+//! [`SharedHome::execute`] (by any client, the REST API, the dashboard, ...):
+//!
+//! ```text
+//! {"type": "snapshot", "home": {...}}
+//! {"type": "event", "event": "socket_switched", "room": "kitchen", "device": "kettle", "on": true, "power": 2000.0}
+//! ```
+//!
+//! Clients send [`Command`]s as JSON text messages on the same connection, e.g.
+//! `{"command": "turn_on", "room": "kitchen", "device": "kettle"}`.  A successful
+//! command is answered by its event (broadcast to everyone); a failed one by
+//! `{"type": "error", "error": .., "message": ..}` to the sender only.
+
+use crate::command::Command;
+use crate::error::SmartHomeError;
+use crate::event::HomeEvent;
+use crate::http::error_name;
+use crate::shared::SharedHome;
+use crate::smart_home::SmartHome;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+/// How long a connection waits for client messages before pushing pending events.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a client may take to complete the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connection-level result; the error is boxed because `tungstenite::Error` is large.
+type WsResult = Result<(), Box<tungstenite::Error>>;
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    Snapshot { home: &'a SmartHome },
+    Event(&'a HomeEvent),
+    Error { error: &'a str, message: String },
+}
+
+/// A WebSocket server streaming changes of a [`SharedHome`].
+///
+/// Each connection is served on its own thread.
+pub struct WsServer {
+    listener: TcpListener,
+    shared: SharedHome,
+    stop: Arc<AtomicBool>,
+}
+
+impl WsServer {
+    /// Binds the server to `addr` (use port 0 to pick a free port).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SmartHomeError::Io`] if the address cannot be bound.
+    pub fn bind(addr: impl ToSocketAddrs, shared: SharedHome) -> Result<Self, SmartHomeError> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            shared,
+            stop: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.listener
+            .local_addr()
+            .expect("listener is bound to an address")
+    }
+
+    /// Accepts connections until the server is shut down through a [`WsHandle`].
+    pub fn run(&self) {
+        for stream in self.listener.incoming() {
+            if self.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(stream) = stream else { continue };
+            let shared = self.shared.clone();
+            let stop = Arc::clone(&self.stop);
+            thread::spawn(move || {
+                // Errors end only this connection; the client sees it drop.
+                let _ = serve_connection(stream, &shared, &stop);
+            });
+        }
+    }
+
+    /// Runs the server on a background thread.
+    pub fn spawn(self) -> WsHandle {
+        let addr = self.local_addr();
+        let stop = Arc::clone(&self.stop);
+        let thread = thread::spawn(move || self.run());
+        WsHandle {
+            addr,
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+fn serve_connection(stream: TcpStream, shared: &SharedHome, stop: &AtomicBool) -> WsResult {
+    stream
+        .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+        .map_err(tungstenite::Error::Io)?;
+    let Ok(mut ws) = tungstenite::accept(stream) else {
+        // Not a WebSocket handshake, or too slow; nothing to serve.
+        return Ok(());
+    };
+    ws.get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(tungstenite::Error::Io)?;
+
+    let (home, events) = shared.snapshot_and_subscribe();
+    send(&mut ws, &ServerMessage::Snapshot { home: &home })?;
+    drop(home);
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            ws.close(None)?;
+            return Ok(());
+        }
+        push_events(&mut ws, &events)?;
+
+        match ws.read() {
+            Ok(Message::Text(text)) => {
+                let reply = serde_json::from_str::<Command>(&text)
+                    .map_err(|e| ("bad_request", e.to_string()))
+                    .and_then(|cmd| {
+                        shared
+                            .execute(cmd)
+                            .map_err(|e| (error_name(&e), e.to_string()))
+                    });
+                if let Err((error, message)) = reply {
+                    send(&mut ws, &ServerMessage::Error { error, message })?;
+                }
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                return Ok(())
+            }
+            Err(e) => return Err(Box::new(e)),
+        }
+    }
+}
+
+fn push_events(ws: &mut WebSocket<TcpStream>, events: &Receiver<HomeEvent>) -> WsResult {
+    while let Ok(event) = events.try_recv() {
+        send(ws, &ServerMessage::Event(&event))?;
+    }
+    Ok(())
+}
+
+fn send(ws: &mut WebSocket<TcpStream>, msg: &ServerMessage) -> WsResult {
+    let text = serde_json::to_string(msg).expect("home data is always serializable");
+    Ok(ws.send(Message::Text(text))?)
+}
+
+/// Handle to a server started with [`WsServer::spawn`].
+pub struct WsHandle {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WsHandle {
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Closes all connections, stops accepting new ones and waits for the
+    /// accept thread to exit.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the blocking accept() so the loop sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WsHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,82 @@
+//! Delivery of alerts to a webhook served on loopback.
+
+use smart_home::alert::{AlertManager, TemperatureRange, WebhookSink};
+use smart_home::{room, ManualClock, SmartHome, SmartHomeError, Thermometer};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+// ── Helper ────────────────────────────────────────────────────────────────────
+
+fn make_home() -> SmartHome {
+    let mut home = SmartHome::new("Home");
+    home.add_room(
+        "cellar",
+        room!("Cellar", "sensor" => Thermometer::new("Sensor", 2.0)),
+    )
+    .unwrap();
+    home
+}
+
+fn manager(url: &str) -> AlertManager<ManualClock> {
+    let mut alerts = AlertManager::new(ManualClock::default())
+        .with_temperature_range(TemperatureRange::new(5.0, 25.0).unwrap());
+    alerts.add_sink(WebhookSink::new(url).unwrap());
+    alerts
+}
+
+/// Accepts one request, answers with `status` and returns the request line and body.
+fn serve_once(listener: TcpListener, status: &'static str) -> JoinHandle<(String, String)> {
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header.trim_end().is_empty() {
+                break;
+            }
+            if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+        reader.get_mut().write_all(response.as_bytes()).unwrap();
+        (request_line, String::from_utf8(body).unwrap())
+    })
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_webhook_receives_alert_json() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hooks/alerts", listener.local_addr().unwrap());
+    let server = serve_once(listener, "204 No Content");
+
+    manager(&url).evaluate(&make_home()).unwrap();
+    let (request_line, body) = server.join().unwrap();
+    assert_eq!(request_line.trim_end(), "POST /hooks/alerts HTTP/1.1");
+    let alert: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(alert["path"], "cellar/sensor");
+    assert_eq!(alert["severity"], "warning");
+    assert_eq!(alert["message"], "2 °C is below 5 °C");
+}
+
+#[test]
+fn test_failed_delivery_is_reported_but_alert_kept() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let server = serve_once(listener, "500 Internal Server Error");
+
+    let mut alerts = manager(&url);
+    let err = alerts.evaluate(&make_home()).unwrap_err();
+    server.join().unwrap();
+    assert!(matches!(err, SmartHomeError::Remote(m) if m.contains("500")));
+    assert_eq!(alerts.open().count(), 1);
+}
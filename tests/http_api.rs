@@ -0,0 +1,126 @@
+//! End-to-end tests of the REST API over a real localhost socket.
+
+#![cfg(feature = "http")]
+
+use smart_home::http::HttpServer;
+use smart_home::{room, SharedHome, SmartHome, Socket, Thermometer};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+
+// ── Helper ────────────────────────────────────────────────────────────────────
+
+fn make_shared() -> SharedHome {
+    let mut home = SmartHome::new("Home");
+    home.add_room(
+        "bedroom",
+        room!(
+            "Bedroom",
+            "heater" => Socket::new("Space heater", 2000.0),
+            "sensor" => Thermometer::new("Sensor", 20.0),
+        ),
+    )
+    .unwrap();
+    SharedHome::new(home)
+}
+
+/// Sends one request and returns `(status, body)`.
+fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .expect("status line");
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, b)| b.to_string())
+        .unwrap_or_default();
+    (status, body)
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_rest_api_on_localhost() {
+    let shared = make_shared();
+    let server = HttpServer::bind("127.0.0.1:0", shared.clone())
+        .unwrap()
+        .spawn();
+    let addr = server.local_addr();
+
+    let (status, body) = request(addr, "GET", "/rooms", "");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"bedroom\""));
+
+    let (status, body) = request(addr, "POST", "/rooms/bedroom/devices/heater/socket/on", "");
+    assert_eq!(status, 200, "{}", body);
+    assert!(shared.read(|h| h.get_socket("bedroom", "heater").unwrap().is_on()));
+
+    let (status, _) = request(
+        addr,
+        "PUT",
+        "/rooms/bedroom/devices/sensor/thermometer/temperature",
+        r#"{"temperature": 22.0}"#,
+    );
+    assert_eq!(status, 200);
+
+    let (status, body) = request(addr, "GET", "/rooms/bedroom/devices/lamp", "");
+    assert_eq!(status, 404);
+    assert!(body.contains("device_not_found"));
+
+    let (status, _) = request(addr, "POST", "/rooms", r#"{"key": "bedroom"}"#);
+    assert_eq!(status, 409);
+
+    server.shutdown();
+}
+
+#[test]
+fn test_oversized_bodies_are_rejected() {
+    let shared = make_shared();
+    let server = HttpServer::bind("127.0.0.1:0", shared.clone())
+        .unwrap()
+        .spawn();
+    let addr = server.local_addr();
+
+    // Valid JSON padded past the limit: a body cut at the limit would still
+    // parse and add the room.
+    let padding = " ".repeat(1024 * 1024);
+    let body = format!(r#"{{"key": "attic"}}{}"#, padding);
+    let (status, body) = request(addr, "POST", "/rooms", &body);
+    assert_eq!(status, 413);
+    assert!(body.contains("payload_too_large"));
+    assert!(shared.read(|h| h.get_room("attic").is_none()));
+
+    server.shutdown();
+}
+
+#[test]
+fn test_on_change_runs_after_mutations_only() {
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&changes);
+    let server = HttpServer::bind("127.0.0.1:0", make_shared())
+        .unwrap()
+        .on_change(move |home| seen.lock().unwrap().push(home.room_count()))
+        .spawn();
+    let addr = server.local_addr();
+
+    request(addr, "GET", "/rooms", "");
+    request(addr, "POST", "/rooms", r#"{"key": "kitchen"}"#);
+    request(addr, "DELETE", "/rooms/attic", "");
+
+    server.shutdown();
+    assert_eq!(*changes.lock().unwrap(), vec![2]);
+}
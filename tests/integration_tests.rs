@@ -6,15 +6,23 @@ fn make_home() -> SmartHome {
     let mut home = SmartHome::new("Home");
 
     let mut living_room = Room::new("Living room");
-    living_room.add_device("sensor", Thermometer::new("Sensor", 22.0));
-    living_room.add_device("lamp", Socket::new("Lamp", 60.0));
+    living_room
+        .add_device("sensor", Thermometer::new("Sensor", 22.0))
+        .unwrap();
+    living_room
+        .add_device("lamp", Socket::new("Lamp", 60.0))
+        .unwrap();
 
     let mut bedroom = Room::new("Bedroom");
-    bedroom.add_device("sensor", Thermometer::new("Sensor", 20.0));
-    bedroom.add_device("heater", Socket::new("Space heater", 2000.0));
-
-    home.add_room("living_room", living_room);
-    home.add_room("bedroom", bedroom);
+    bedroom
+        .add_device("sensor", Thermometer::new("Sensor", 20.0))
+        .unwrap();
+    bedroom
+        .add_device("heater", Socket::new("Space heater", 2000.0))
+        .unwrap();
+
+    home.add_room("living_room", living_room).unwrap();
+    home.add_room("bedroom", bedroom).unwrap();
     home
 }
 
@@ -64,7 +72,7 @@ fn test_dynamic_room_management() {
     let mut home = SmartHome::new("Home");
     assert_eq!(home.room_count(), 0);
 
-    home.add_room("kitchen", Room::new("Kitchen"));
+    home.add_room("kitchen", Room::new("Kitchen")).unwrap();
     assert_eq!(home.room_count(), 1);
     assert!(home.get_room("kitchen").is_some());
 
@@ -81,7 +89,7 @@ fn test_dynamic_device_management() {
     let mut room = Room::new("Living room");
     assert_eq!(room.device_count(), 0);
 
-    room.add_device("lamp", Socket::new("Lamp", 60.0));
+    room.add_device("lamp", Socket::new("Lamp", 60.0)).unwrap();
     assert_eq!(room.device_count(), 1);
     assert!(room.get_device("lamp").is_some());
 
@@ -110,10 +118,64 @@ fn test_get_device_room_not_found() {
 fn test_get_device_device_not_found() {
     let home = make_home();
     let result = home.get_device("living_room", "no_such_device");
-    assert!(matches!(result, Err(SmartHomeError::DeviceNotFound(_))));
+    assert!(matches!(result, Err(SmartHomeError::DeviceNotFound { .. })));
     assert!(result.unwrap_err().to_string().contains("no_such_device"));
 }
 
+// ── Duplicate keys ────────────────────────────────────────────────────────────
+
+#[test]
+fn test_duplicate_room_is_rejected() {
+    let mut home = make_home();
+    let result = home.add_room("bedroom", Room::new("Guest room"));
+    assert!(matches!(result, Err(SmartHomeError::DuplicateRoom(_))));
+    assert_eq!(home.get_room("bedroom").unwrap().name(), "Bedroom");
+
+    assert!(home
+        .insert_or_replace("bedroom", Room::new("Guest room"))
+        .is_some());
+    assert_eq!(home.get_room("bedroom").unwrap().name(), "Guest room");
+}
+
+#[test]
+fn test_duplicate_device_is_rejected() {
+    let mut home = make_home();
+    let result = home.add_device("bedroom", "heater", Socket::new("Other heater", 1000.0));
+    assert!(matches!(
+        result,
+        Err(SmartHomeError::DuplicateDevice { ref room, ref device })
+            if room == "bedroom" && device == "heater"
+    ));
+    assert_eq!(
+        home.get_socket("bedroom", "heater")
+            .unwrap()
+            .power_consumption(),
+        2000.0
+    );
+}
+
+#[test]
+#[should_panic(expected = "lamp")]
+fn test_room_macro_duplicate_key_panics() {
+    let _ = room!(
+        "Hall",
+        "lamp" => Socket::new("Lamp", 60.0),
+        "lamp" => Socket::new("Lamp", 40.0),
+    );
+}
+
+// ── Typed device access ───────────────────────────────────────────────────────
+
+#[test]
+fn test_wrong_device_kind() {
+    let mut home = make_home();
+    let result = home.get_socket_mut("living_room", "sensor");
+    assert!(matches!(
+        result,
+        Err(SmartHomeError::WrongDeviceKind { .. })
+    ));
+}
+
 // ── From<Socket> / From<Thermometer> for SmartDevice ─────────────────────────
 
 #[test]
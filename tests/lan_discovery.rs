@@ -0,0 +1,55 @@
+//! Discovery of several simulated device servers on loopback.
+
+use smart_home::discovery::{Announcement, Announcer, DiscoveryListener};
+use smart_home::{Room, SharedHome, SmartDevice, SmartHome, Socket, Thermometer};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+#[test]
+fn test_discover_and_assign_simulated_devices() {
+    let listener = DiscoveryListener::bind("127.0.0.1:0").unwrap();
+    let devices: [(&str, u16, SmartDevice); 3] = [
+        ("kettle-01", 7001, Socket::new("Kettle", 2000.0).into()),
+        ("lamp-01", 7002, Socket::new("Lamp", 60.0).into()),
+        ("sensor-01", 7003, Thermometer::new("Sensor", 21.5).into()),
+    ];
+    let handles: Vec<_> = devices
+        .into_iter()
+        .map(|(id, port, device)| {
+            let endpoint: SocketAddr = ([127, 0, 0, 1], port).into();
+            Announcer::new(
+                Announcement::new(id, endpoint, device),
+                listener.local_addr(),
+            )
+            .unwrap()
+            .spawn(Duration::from_millis(20))
+        })
+        .collect();
+
+    let found = listener.collect(Duration::from_millis(300)).unwrap();
+    for handle in handles {
+        handle.shutdown();
+    }
+    assert_eq!(found.len(), 3);
+
+    let mut home = SmartHome::new("Home");
+    home.add_room("kitchen", Room::new("Kitchen")).unwrap();
+    let shared = SharedHome::new(home);
+    for candidate in found {
+        let key = candidate.announcement.id.clone();
+        shared
+            .execute(candidate.into_command("kitchen", key))
+            .unwrap();
+    }
+
+    shared.read(|h| {
+        assert_eq!(h.get_room("kitchen").unwrap().device_count(), 3);
+        assert_eq!(h.get_socket("kitchen", "lamp-01").unwrap().name(), "Lamp");
+        assert_eq!(
+            h.get_thermometer("kitchen", "sensor-01")
+                .unwrap()
+                .temperature(),
+            21.5
+        );
+    });
+}
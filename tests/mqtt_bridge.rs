@@ -0,0 +1,255 @@
+//! Tests of the MQTT bridge against the in-memory broker.
+
+#![cfg(feature = "mqtt")]
+
+use smart_home::availability::AvailabilityTracker;
+use smart_home::homeassistant::Discovery;
+use smart_home::mqtt::{
+    availability_will, MemoryBroker, MemoryClient, Message, MqttBridge, Transport, DEFAULT_PREFIX,
+};
+use smart_home::{room, Command, ManualClock, SharedHome, SmartHome, Socket, Thermometer};
+use std::time::{Duration, UNIX_EPOCH};
+
+// ── Helper ────────────────────────────────────────────────────────────────────
+
+fn make_shared() -> SharedHome {
+    let mut home = SmartHome::new("Home");
+    home.add_room(
+        "kitchen",
+        room!(
+            "Kitchen",
+            "kettle" => Socket::new("Kettle", 2000.0),
+            "sensor" => Thermometer::new("Sensor", 22.0),
+        ),
+    )
+    .unwrap();
+    SharedHome::new(home)
+}
+
+fn start(broker: &MemoryBroker, shared: &SharedHome) -> MqttBridge<MemoryClient> {
+    let transport = broker.connect(Some(availability_will(DEFAULT_PREFIX)));
+    MqttBridge::start(transport, shared.clone(), DEFAULT_PREFIX).unwrap()
+}
+
+fn state(broker: &MemoryBroker, room: &str, device: &str) -> Option<serde_json::Value> {
+    broker
+        .retained(&format!("home/{}/{}/state", room, device))
+        .map(|payload| serde_json::from_str(&payload).unwrap())
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_start_publishes_retained_state() {
+    let broker = MemoryBroker::new();
+    let shared = make_shared();
+    let _bridge = start(&broker, &shared);
+
+    assert_eq!(broker.retained("home/status").as_deref(), Some("online"));
+    assert_eq!(state(&broker, "kitchen", "kettle").unwrap()["state"], "OFF");
+    assert_eq!(
+        state(&broker, "kitchen", "sensor").unwrap()["temperature"],
+        22.0
+    );
+
+    // A late subscriber still sees the retained state.
+    let mut late = broker.connect(None);
+    late.subscribe("home/kitchen/+/state").unwrap();
+    let first = late.poll(Duration::ZERO).unwrap().unwrap();
+    assert!(first.retain);
+}
+
+#[test]
+fn test_set_topics_change_the_home() {
+    let broker = MemoryBroker::new();
+    let shared = make_shared();
+    let mut bridge = start(&broker, &shared);
+
+    let mut remote = broker.connect(None);
+    remote.subscribe("home/+/+/error").unwrap();
+    remote.publish_str("home/kitchen/kettle/set", "ON");
+    remote.publish_str("home/kitchen/sensor/set", "19.5");
+    remote.publish_str("home/kitchen/sensor/set", "ON");
+    bridge.poll(Duration::ZERO).unwrap();
+
+    assert!(shared.read(|h| h.get_socket("kitchen", "kettle").unwrap().is_on()));
+    assert_eq!(
+        state(&broker, "kitchen", "kettle").unwrap()["power"],
+        2000.0
+    );
+    assert_eq!(
+        state(&broker, "kitchen", "sensor").unwrap()["temperature"],
+        19.5
+    );
+    let error = remote.poll(Duration::ZERO).unwrap().unwrap();
+    assert_eq!(error.topic, "home/kitchen/sensor/error");
+}
+
+#[test]
+fn test_state_includes_battery() {
+    let broker = MemoryBroker::new();
+    let shared = make_shared();
+    let mut bridge = start(&broker, &shared);
+
+    shared
+        .execute(Command::UpdateWireless {
+            room: "kitchen".into(),
+            device: "sensor".into(),
+            battery: Some(18),
+            signal_dbm: Some(-75),
+        })
+        .unwrap();
+    shared
+        .execute(Command::SetTemperature {
+            room: "kitchen".into(),
+            device: "sensor".into(),
+            temperature: 20.0,
+        })
+        .unwrap();
+    bridge.poll(Duration::ZERO).unwrap();
+
+    let sensor = state(&broker, "kitchen", "sensor").unwrap();
+    assert_eq!(sensor["temperature"], 20.0);
+    assert_eq!(sensor["battery"], 18);
+    assert_eq!(sensor["battery_low"], true);
+    assert_eq!(sensor["signal_dbm"], -75);
+}
+
+#[test]
+fn test_removed_devices_are_cleared() {
+    let broker = MemoryBroker::new();
+    let shared = make_shared();
+    let mut bridge = start(&broker, &shared);
+
+    shared
+        .execute(Command::RemoveRoom {
+            room: "kitchen".into(),
+        })
+        .unwrap();
+    bridge.poll(Duration::ZERO).unwrap();
+    assert!(state(&broker, "kitchen", "kettle").is_none());
+    assert!(state(&broker, "kitchen", "sensor").is_none());
+}
+
+#[test]
+fn test_availability() {
+    let broker = MemoryBroker::new();
+    let shared = make_shared();
+
+    // Crash: the broker publishes the last will.
+    drop(start(&broker, &shared));
+    assert_eq!(broker.retained("home/status").as_deref(), Some("offline"));
+
+    // Clean shutdown: the bridge says goodbye itself.
+    let bridge = start(&broker, &shared);
+    assert_eq!(broker.retained("home/status").as_deref(), Some("online"));
+    bridge.stop().unwrap().disconnect();
+    assert_eq!(broker.retained("home/status").as_deref(), Some("offline"));
+
+    let mut other = broker.connect(None);
+    other
+        .publish(Message::new("home/status", "", true))
+        .unwrap();
+    assert_eq!(broker.retained("home/status"), None);
+}
+
+#[test]
+fn test_device_availability_follows_changes() {
+    let broker = MemoryBroker::new();
+    let shared = make_shared();
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut bridge = start(&broker, &shared)
+        .enable_availability(AvailabilityTracker::new(clock.clone()))
+        .unwrap();
+    let availability =
+        |device: &str| broker.retained(&format!("home/kitchen/{}/availability", device));
+    assert_eq!(availability("kettle").as_deref(), Some("online"));
+    assert_eq!(availability("sensor").as_deref(), Some("online"));
+
+    clock.advance(Duration::from_secs(6 * 60));
+    bridge.poll(Duration::ZERO).unwrap();
+    assert_eq!(availability("kettle").as_deref(), Some("stale"));
+
+    shared
+        .execute(Command::Toggle {
+            room: "kitchen".into(),
+            device: "kettle".into(),
+        })
+        .unwrap();
+    bridge.poll(Duration::ZERO).unwrap();
+    assert_eq!(availability("kettle").as_deref(), Some("online"));
+    assert_eq!(availability("sensor").as_deref(), Some("stale"));
+
+    shared
+        .execute(Command::RemoveDevice {
+            room: "kitchen".into(),
+            device: "sensor".into(),
+        })
+        .unwrap();
+    bridge.poll(Duration::ZERO).unwrap();
+    assert_eq!(availability("sensor"), None);
+}
+
+#[test]
+fn test_home_assistant_discovery_follows_changes() {
+    let broker = MemoryBroker::new();
+    let shared = make_shared();
+    let mut bridge = start(&broker, &shared);
+    bridge.enable_discovery(Discovery::default()).unwrap();
+
+    let config = |object: &str, component: &str| {
+        broker.retained(&format!(
+            "homeassistant/{}/smart_home/Home-{}/config",
+            component, object
+        ))
+    };
+    assert!(config("kitchen-kettle-switch", "switch").is_some());
+    assert!(config("kitchen-sensor-temperature", "sensor").is_some());
+
+    shared
+        .execute(Command::AddDevice {
+            room: "kitchen".into(),
+            device: "lamp".into(),
+            data: Socket::new("Lamp", 60.0).into(),
+        })
+        .unwrap();
+    shared
+        .execute(Command::RemoveDevice {
+            room: "kitchen".into(),
+            device: "kettle".into(),
+        })
+        .unwrap();
+    bridge.poll(Duration::ZERO).unwrap();
+
+    let lamp: serde_json::Value =
+        serde_json::from_str(&config("kitchen-lamp-switch", "switch").unwrap()).unwrap();
+    assert_eq!(lamp["device"]["suggested_area"], "Kitchen");
+    assert!(config("kitchen-kettle-switch", "switch").is_none());
+    assert!(config("kitchen-kettle-power", "sensor").is_none());
+
+    shared
+        .execute(Command::RenameRoom {
+            room: "kitchen".into(),
+            to: "galley".into(),
+        })
+        .unwrap();
+    shared
+        .execute(Command::RenameDevice {
+            room: "galley".into(),
+            device: "lamp".into(),
+            to: "spot".into(),
+        })
+        .unwrap();
+    bridge.poll(Duration::ZERO).unwrap();
+
+    assert!(config("kitchen-lamp-switch", "switch").is_none());
+    assert!(config("galley-lamp-switch", "switch").is_none());
+    assert!(config("galley-spot-switch", "switch").is_some());
+    assert!(config("galley-sensor-temperature", "sensor").is_some());
+    assert_eq!(broker.retained("home/kitchen/sensor/state"), None);
+    assert_eq!(
+        state(&broker, "galley", "sensor").unwrap()["temperature"],
+        22.0
+    );
+    assert!(state(&broker, "galley", "spot").is_some());
+}
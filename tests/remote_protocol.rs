@@ -0,0 +1,186 @@
+//! End-to-end tests of the authenticated remote protocol on localhost.
+
+#![cfg(feature = "remote")]
+
+use smart_home::remote::{PresharedKey, RemoteClient, RemoteHandle, RemoteServer};
+use smart_home::{room, Command, SharedHome, SmartHome, SmartHomeError, Socket};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+// ── Helper ────────────────────────────────────────────────────────────────────
+
+fn make_shared() -> SharedHome {
+    let mut home = SmartHome::new("Home");
+    home.add_room(
+        "kitchen",
+        room!("Kitchen", "freezer" => Socket::new("Freezer", 120.0)),
+    )
+    .unwrap();
+    SharedHome::new(home)
+}
+
+fn start(shared: &SharedHome, key: &PresharedKey) -> RemoteHandle {
+    RemoteServer::bind("127.0.0.1:0", shared.clone(), key.clone())
+        .unwrap()
+        .spawn()
+}
+
+fn toggle() -> Command {
+    Command::Toggle {
+        room: "kitchen".into(),
+        device: "freezer".into(),
+    }
+}
+
+fn freezer_on(shared: &SharedHome) -> bool {
+    shared.read(|home| home.get_socket("kitchen", "freezer").unwrap().is_on())
+}
+
+/// Relays one client connection to the server like an attacker on the
+/// network would: records every frame the client sends and, with `tamper`,
+/// flips a bit in the first command.  Returns the recorded frames and the
+/// client's result.
+fn relay(
+    server: SocketAddr,
+    key: &PresharedKey,
+    tamper: bool,
+) -> (Vec<Vec<u8>>, Result<(), SmartHomeError>) {
+    let proxy = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_addr = proxy.local_addr().unwrap();
+    let relay = std::thread::spawn(move || {
+        let (mut client, _) = proxy.accept().unwrap();
+        let mut upstream = TcpStream::connect(server).unwrap();
+        let mut frames = Vec::new();
+        // Hello, proof and one command, each answered by the server.
+        for step in 0..3 {
+            let mut frame = read_raw_frame(&mut client);
+            frames.push(frame.clone());
+            if tamper && step == 2 {
+                let last = frame.len() - 1;
+                frame[last] ^= 0x01;
+                upstream.write_all(&frame).unwrap();
+                // The server drops the connection; pass that on to the client.
+                assert!(closed_without_reply(&mut upstream));
+                break;
+            }
+            upstream.write_all(&frame).unwrap();
+            client.write_all(&read_raw_frame(&mut upstream)).unwrap();
+        }
+        frames
+    });
+    let mut client = RemoteClient::connect(proxy_addr, key).unwrap();
+    let result = client.execute(toggle()).map(drop);
+    drop(client);
+    (relay.join().unwrap(), result)
+}
+
+fn read_raw_frame(stream: &mut TcpStream) -> Vec<u8> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len).unwrap();
+    let mut frame = len.to_vec();
+    frame.resize(4 + u32::from_be_bytes(len) as usize, 0);
+    stream.read_exact(&mut frame[4..]).unwrap();
+    frame
+}
+
+/// Returns `true` if the server closed `stream` without answering.
+fn closed_without_reply(stream: &mut TcpStream) -> bool {
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut byte = [0; 1];
+    matches!(stream.read(&mut byte), Ok(0) | Err(_))
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_client_with_key_controls_devices() {
+    let shared = make_shared();
+    let key = PresharedKey::generate().unwrap();
+    let server = start(&shared, &key);
+
+    let mut client = RemoteClient::connect(server.local_addr(), &key).unwrap();
+    client.execute(toggle()).unwrap();
+    assert!(freezer_on(&shared));
+
+    let missing = Command::TurnOn {
+        room: "attic".into(),
+        device: "fan".into(),
+    };
+    let err = client.execute(missing).unwrap_err();
+    assert!(matches!(err, SmartHomeError::Remote(m) if m == "Room 'attic' not found"));
+    // The connection stays usable after a failed command.
+    client.execute(toggle()).unwrap();
+    assert!(!freezer_on(&shared));
+}
+
+#[test]
+fn test_wrong_key_is_rejected() {
+    let shared = make_shared();
+    let server = start(&shared, &PresharedKey::generate().unwrap());
+    let intruder = PresharedKey::generate().unwrap();
+    let err = RemoteClient::connect(server.local_addr(), &intruder).err();
+    assert!(matches!(err, Some(SmartHomeError::AuthenticationFailed(_))));
+    assert!(!freezer_on(&shared));
+}
+
+#[test]
+fn test_plain_commands_are_rejected() {
+    let shared = make_shared();
+    let server = start(&shared, &PresharedKey::generate().unwrap());
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    let json = br#"{"command":"toggle","room":"kitchen","device":"freezer"}"#;
+    stream
+        .write_all(&(json.len() as u32).to_be_bytes())
+        .unwrap();
+    stream.write_all(json).unwrap();
+    assert!(closed_without_reply(&mut stream));
+    assert!(!freezer_on(&shared));
+}
+
+#[test]
+fn test_oversized_hello_is_rejected_unread() {
+    let shared = make_shared();
+    let server = start(&shared, &PresharedKey::generate().unwrap());
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    // Announce a 1 MiB hello but never send it: the server must refuse at
+    // once instead of buffering and waiting for the body.
+    stream.write_all(&(1024u32 * 1024).to_be_bytes()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let mut byte = [0; 1];
+    assert_eq!(stream.read(&mut byte).unwrap(), 0);
+}
+
+#[test]
+fn test_recorded_session_cannot_be_replayed() {
+    let shared = make_shared();
+    let key = PresharedKey::generate().unwrap();
+    let server = start(&shared, &key);
+    let (frames, result) = relay(server.local_addr(), &key, false);
+    result.unwrap();
+    assert!(freezer_on(&shared));
+
+    // Replaying the whole conversation fails at the proof: the server picked
+    // a new nonce, so the old proof no longer matches.
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    stream.write_all(&frames[0]).unwrap();
+    read_raw_frame(&mut stream);
+    stream.write_all(&frames[1]).unwrap();
+    stream.write_all(&frames[2]).unwrap();
+    assert!(closed_without_reply(&mut stream));
+    assert!(freezer_on(&shared), "replayed toggle must not apply");
+}
+
+#[test]
+fn test_tampered_command_is_rejected() {
+    let shared = make_shared();
+    let key = PresharedKey::generate().unwrap();
+    let server = start(&shared, &key);
+    let (_, result) = relay(server.local_addr(), &key, true);
+    assert!(result.is_err(), "server must drop the connection");
+    assert!(!freezer_on(&shared), "tampered toggle must not apply");
+}
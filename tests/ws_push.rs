@@ -0,0 +1,133 @@
+//! End-to-end tests of the WebSocket push channel on localhost.
+
+#![cfg(feature = "ws")]
+
+use smart_home::http::HttpServer;
+use smart_home::ws::WsServer;
+use smart_home::{room, SharedHome, SmartHome, Socket, Thermometer};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+type Client = WebSocket<MaybeTlsStream<TcpStream>>;
+
+// ── Helper ────────────────────────────────────────────────────────────────────
+
+fn make_shared() -> SharedHome {
+    let mut home = SmartHome::new("Home");
+    home.add_room(
+        "kitchen",
+        room!(
+            "Kitchen",
+            "kettle" => Socket::new("Kettle", 2000.0),
+            "sensor" => Thermometer::new("Sensor", 22.0),
+        ),
+    )
+    .unwrap();
+    SharedHome::new(home)
+}
+
+fn connect(addr: SocketAddr) -> Client {
+    let (client, _) = tungstenite::connect(format!("ws://{}/", addr)).unwrap();
+    if let MaybeTlsStream::Plain(stream) = client.get_ref() {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+    }
+    client
+}
+
+fn next_json(client: &mut Client) -> serde_json::Value {
+    loop {
+        if let Message::Text(text) = client.read().unwrap() {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+fn send(client: &mut Client, json: &str) {
+    client.send(Message::Text(json.to_string())).unwrap();
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_snapshot_then_events_from_commands() {
+    let shared = make_shared();
+    let server = WsServer::bind("127.0.0.1:0", shared.clone())
+        .unwrap()
+        .spawn();
+
+    let mut a = connect(server.local_addr());
+    let mut b = connect(server.local_addr());
+    let snapshot = next_json(&mut a);
+    assert_eq!(snapshot["type"], "snapshot");
+    assert_eq!(snapshot["home"]["name"], "Home");
+    next_json(&mut b);
+
+    send(
+        &mut a,
+        r#"{"command": "turn_on", "room": "kitchen", "device": "kettle"}"#,
+    );
+    for client in [&mut a, &mut b] {
+        let event = next_json(client);
+        assert_eq!(event["type"], "event");
+        assert_eq!(event["event"], "socket_switched");
+        assert_eq!(event["on"], true);
+    }
+    assert!(shared.read(|h| h.get_socket("kitchen", "kettle").unwrap().is_on()));
+
+    send(
+        &mut a,
+        r#"{"command": "set_temperature", "room": "kitchen", "device": "sensor", "temperature": -500}"#,
+    );
+    let error = next_json(&mut a);
+    assert_eq!(error["type"], "error");
+    assert_eq!(error["error"], "invalid_value");
+
+    send(&mut a, "not a command");
+    assert_eq!(next_json(&mut a)["error"], "bad_request");
+
+    server.shutdown();
+}
+
+#[test]
+fn test_http_changes_are_pushed() {
+    let shared = make_shared();
+    let ws = WsServer::bind("127.0.0.1:0", shared.clone())
+        .unwrap()
+        .spawn();
+    let http = HttpServer::bind("127.0.0.1:0", shared).unwrap().spawn();
+
+    let mut client = connect(ws.local_addr());
+    next_json(&mut client);
+
+    let mut stream = TcpStream::connect(http.local_addr()).unwrap();
+    write!(
+        stream,
+        "DELETE /rooms/kitchen HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 0\r\n\r\n"
+    )
+    .unwrap();
+
+    let event = next_json(&mut client);
+    assert_eq!(event["event"], "room_removed");
+    assert_eq!(event["room"], "kitchen");
+
+    http.shutdown();
+    ws.shutdown();
+}
+
+#[test]
+fn test_silent_clients_are_dropped() {
+    let shared = make_shared();
+    let server = WsServer::bind("127.0.0.1:0", shared).unwrap().spawn();
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .unwrap();
+    // Never send the handshake; the server gives up and closes.
+    let mut byte = [0; 1];
+    assert_eq!(stream.read(&mut byte).unwrap(), 0);
+}